@@ -0,0 +1,417 @@
+use clap::{Arg, ArgMatches, Command};
+use sol_escrow::{
+    entrypoint::process_instruction,
+    indexer::{offer_filters, pegged_offer_filters, OpenOffer, OpenPegged, OrderBook},
+    instruction,
+    state::{format_price, ConfigPDA, EscrowPDA, OfferData},
+};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::RpcFilterType,
+};
+use solana_program_test::{processor, tokio, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_token::state::Mint;
+use std::{error::Error, str::FromStr};
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+struct Config {
+    rpc: RpcClient,
+    program_id: Pubkey,
+    keypair_path: String,
+    dry_run: bool,
+}
+
+fn main() {
+    let matches = app().get_matches();
+    if let Err(err) = run(&matches) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn app() -> Command<'static> {
+    Command::new("sol-escrow")
+        .about("create, take and inspect sol-escrow offers")
+        .subcommand_required(true)
+        .arg(
+            Arg::new("url")
+                .long("url")
+                .short('u')
+                .takes_value(true)
+                .global(true)
+                .default_value("http://127.0.0.1:8899")
+                .help("RPC endpoint, e.g. a local solana-test-validator"),
+        )
+        .arg(
+            Arg::new("program-id")
+                .long("program-id")
+                .takes_value(true)
+                .global(true)
+                .help("deployed sol-escrow program id"),
+        )
+        .arg(
+            Arg::new("keypair")
+                .long("keypair")
+                .short('k')
+                .takes_value(true)
+                .global(true)
+                .help("signer keypair file [default: ~/.config/solana/id.json]"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .global(true)
+                .help("run the transaction in an in-process bank seeded from the RPC endpoint"),
+        )
+        .subcommand(
+            Command::new("offer")
+                .about("escrow the tokens in a temp account against a strike")
+                .arg(required_arg(
+                    "temp",
+                    "token account holding exactly token-qty of token-mint",
+                ))
+                .arg(required_arg(
+                    "receive",
+                    "strike token account, or wallet to use the ATA of",
+                ))
+                .arg(required_arg("token-mint", "mint of the offered tokens"))
+                .arg(required_arg("token-qty", "amount of offered tokens"))
+                .arg(required_arg("strike-mint", "mint of the requested tokens"))
+                .arg(required_arg("strike-qty", "amount of requested tokens"))
+                .arg(
+                    Arg::new("contract-no")
+                        .long("contract-no")
+                        .takes_value(true)
+                        .default_value("0")
+                        .help("index seed contract number, distinguishes equal offers"),
+                ),
+        )
+        .subcommand(
+            Command::new("accept")
+                .about("take an offer at its current terms")
+                .arg(required_arg("escrow", "escrow PDA of the offer"))
+                .arg(required_arg(
+                    "strike-account",
+                    "token account paying the strike",
+                ))
+                .arg(required_arg(
+                    "receive",
+                    "token account receiving the offered tokens",
+                )),
+        )
+        .subcommand(
+            Command::new("cancel")
+                .about("cancel an offer and return the temp account")
+                .arg(required_arg("escrow", "escrow PDA of the offer")),
+        )
+        .subcommand(
+            Command::new("show")
+                .about("print an offer")
+                .arg(required_arg("escrow", "escrow PDA of the offer")),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("list open offers grouped by market, best price first")
+                .arg(required_arg("seller", "only offers by this seller").required(false))
+                .arg(required_arg("token-mint", "only offers of this mint").required(false))
+                .arg(required_arg("strike-mint", "only offers for this mint").required(false)),
+        )
+}
+
+fn required_arg(name: &'static str, help: &'static str) -> Arg<'static> {
+    Arg::new(name)
+        .long(name)
+        .takes_value(true)
+        .required(true)
+        .help(help)
+}
+
+fn run(matches: &ArgMatches) -> CliResult<()> {
+    let (command, args) = matches.subcommand().unwrap();
+    let program_id = pubkey_of(args, "program-id")?;
+    let config = Config {
+        rpc: RpcClient::new_with_commitment(
+            args.value_of("url").unwrap().to_string(),
+            CommitmentConfig::confirmed(),
+        ),
+        program_id,
+        keypair_path: match args.value_of("keypair") {
+            Some(path) => path.to_string(),
+            None => format!("{}/.config/solana/id.json", std::env::var("HOME")?),
+        },
+        dry_run: args.is_present("dry-run"),
+    };
+
+    match command {
+        "offer" => offer(&config, args),
+        "accept" => accept(&config, args),
+        "cancel" => cancel(&config, args),
+        "show" => show(&config, args),
+        "list" => list(&config, args),
+        _ => unreachable!(),
+    }
+}
+
+fn offer(config: &Config, args: &ArgMatches) -> CliResult<()> {
+    let seller = keypair(config)?;
+    let offer_data = OfferData {
+        token_type: pubkey_of(args, "token-mint")?,
+        token_qty: value_of(args, "token-qty")?,
+        strike_type: pubkey_of(args, "strike-mint")?,
+        strike_qty: value_of(args, "strike-qty")?,
+    };
+    let index_seed = instruction::index_seed(&seller.pubkey(), 0, value_of(args, "contract-no")?);
+    let mut ix = instruction::offer(
+        &config.program_id,
+        &seller.pubkey(),
+        &pubkey_of(args, "temp")?,
+        &pubkey_of(args, "receive")?,
+        &offer_data,
+        &index_seed,
+    );
+    if fetch_config(config)?.allow_list {
+        ix = instruction::with_allowed_mints(&config.program_id, ix, &offer_data);
+    }
+    println!(
+        "escrow: {}",
+        instruction::escrow_address(&config.program_id, &offer_data, &index_seed)
+    );
+    send(config, ix, &seller)
+}
+
+fn accept(config: &Config, args: &ArgMatches) -> CliResult<()> {
+    let buyer = keypair(config)?;
+    let escrow_pda = pubkey_of(args, "escrow")?;
+    let escrow = fetch_escrow(config, &escrow_pda)?;
+    let ix = instruction::accept(
+        &config.program_id,
+        &buyer.pubkey(),
+        &pubkey_of(args, "strike-account")?,
+        &pubkey_of(args, "receive")?,
+        &escrow_pda,
+        &escrow,
+    );
+    send(config, ix, &buyer)
+}
+
+fn cancel(config: &Config, args: &ArgMatches) -> CliResult<()> {
+    let seller = keypair(config)?;
+    let escrow_pda = pubkey_of(args, "escrow")?;
+    let escrow = fetch_escrow(config, &escrow_pda)?;
+    if escrow.seller_main != seller.pubkey() {
+        return Err(format!("offer belongs to seller {}", escrow.seller_main).into());
+    }
+    let ix = instruction::cancel(&config.program_id, &escrow_pda, &escrow);
+    send(config, ix, &seller)
+}
+
+fn show(config: &Config, args: &ArgMatches) -> CliResult<()> {
+    let escrow_pda = pubkey_of(args, "escrow")?;
+    let escrow = fetch_escrow(config, &escrow_pda)?;
+    println!("escrow:         {}", escrow_pda);
+    println!(
+        "token:          {} x {}",
+        escrow.offer_data.token_qty, escrow.offer_data.token_type
+    );
+    println!(
+        "strike:         {} x {}",
+        escrow.offer_data.strike_qty, escrow.offer_data.strike_type
+    );
+    let token_decimals = fetch_decimals(config, &escrow.offer_data.token_type)?;
+    let strike_decimals = fetch_decimals(config, &escrow.offer_data.strike_type)?;
+    if let Ok(price) = escrow
+        .offer_data
+        .limit_price(token_decimals, strike_decimals)
+    {
+        println!("price:          {} per token", format_price(price));
+    }
+    println!("seller:         {}", escrow.seller_main);
+    println!("seller_temp:    {}", escrow.seller_temp);
+    println!("seller_receive: {}", escrow.seller_receive);
+    if let Some(operator) = escrow.operator {
+        println!("operator:       {}", operator);
+    }
+    println!("rent_payer:     {}", escrow.rent_payer);
+    Ok(())
+}
+
+fn list(config: &Config, args: &ArgMatches) -> CliResult<()> {
+    let seller = optional_pubkey_of(args, "seller")?;
+    let token_type = optional_pubkey_of(args, "token-mint")?;
+    let strike_type = optional_pubkey_of(args, "strike-mint")?;
+
+    let book = OrderBook::from_accounts(fetch_program_accounts(
+        config,
+        offer_filters(seller.as_ref(), token_type.as_ref(), strike_type.as_ref()),
+    )?);
+    for (token_type, strike_type) in book.markets() {
+        println!("{} / {}", token_type, strike_type);
+        for offer in book.offers(token_type, strike_type) {
+            let data = &offer.escrow.offer_data;
+            println!(
+                "  {}  {} for {}  seller {}",
+                offer.pda, data.token_qty, data.strike_qty, offer.escrow.seller_main
+            );
+        }
+    }
+    println!("{} open offers", book.len());
+
+    let pegged = OpenPegged::from_accounts(fetch_program_accounts(
+        config,
+        pegged_offer_filters(seller.as_ref(), token_type.as_ref(), strike_type.as_ref()),
+    )?);
+    for offer in &pegged {
+        let data = &offer.pegged.pegged_data;
+        println!(
+            "  {}  {} {} for {} at oracle {} {:+} bps  seller {}",
+            offer.pda,
+            data.token_qty,
+            data.token_type,
+            data.strike_type,
+            data.oracle,
+            data.spread_bps,
+            offer.pegged.seller_main
+        );
+    }
+    println!("{} open pegged offers", pegged.len());
+    Ok(())
+}
+
+// runs one getProgramAccounts query per filter set
+fn fetch_program_accounts(
+    config: &Config,
+    filter_sets: Vec<Vec<RpcFilterType>>,
+) -> CliResult<Vec<(Pubkey, Account)>> {
+    let mut accounts = vec![];
+    for filters in filter_sets {
+        accounts.extend(config.rpc.get_program_accounts_with_config(
+            &config.program_id,
+            RpcProgramAccountsConfig {
+                filters: Some(filters),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )?);
+    }
+    Ok(accounts)
+}
+
+fn send(config: &Config, ix: Instruction, signer: &Keypair) -> CliResult<()> {
+    if config.dry_run {
+        return dry_run(config, ix, signer);
+    }
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&signer.pubkey()),
+        &[signer],
+        config.rpc.get_latest_blockhash()?,
+    );
+    let signature = config.rpc.send_and_confirm_transaction(&tx)?;
+    println!("signature: {}", signature);
+    Ok(())
+}
+
+// copies every non-program account the instruction touches from the RPC endpoint into a
+// fresh solana-program-test bank running the native processor, then runs it there
+fn dry_run(config: &Config, ix: Instruction, signer: &Keypair) -> CliResult<()> {
+    let mut program_test = ProgramTest::new(
+        "sol_escrow",
+        config.program_id,
+        processor!(process_instruction),
+    );
+    for meta in &ix.accounts {
+        if meta.pubkey == config.program_id {
+            continue;
+        }
+        if let Ok(account) = config.rpc.get_account(&meta.pubkey) {
+            if !account.executable {
+                program_test.add_account(meta.pubkey, account);
+            }
+        }
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let result = runtime.block_on(async {
+        let (mut banks_client, payer, blockhash) = program_test.start().await;
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&payer.pubkey()),
+            &[&payer, signer],
+            blockhash,
+        );
+        banks_client.process_transaction_with_metadata(tx).await
+    })?;
+
+    if let Some(metadata) = result.metadata {
+        for log in metadata.log_messages {
+            println!("{}", log);
+        }
+    }
+    match result.result {
+        Ok(()) => {
+            println!("dry run succeeded");
+            Ok(())
+        }
+        Err(err) => Err(format!("dry run failed: {}", err).into()),
+    }
+}
+
+fn fetch_escrow(config: &Config, escrow_pda: &Pubkey) -> CliResult<EscrowPDA> {
+    let account = config.rpc.get_account(escrow_pda)?;
+    match OpenOffer::decode(*escrow_pda, &account) {
+        Some(offer) if account.owner == config.program_id => Ok(offer.escrow),
+        _ => Err(format!("{} is not an open offer", escrow_pda).into()),
+    }
+}
+
+fn fetch_config(config: &Config) -> CliResult<ConfigPDA> {
+    let address = instruction::config_address(&config.program_id);
+    match config.rpc.get_account(&address) {
+        Ok(account) if account.owner == config.program_id => {
+            Ok(ConfigPDA::unpack_from_slice(&account.data)?)
+        }
+        _ => Err(format!("config {} is not initialised", address).into()),
+    }
+}
+
+fn fetch_decimals(config: &Config, mint: &Pubkey) -> CliResult<u8> {
+    let account = config.rpc.get_account(mint)?;
+    Ok(Mint::unpack(&account.data)?.decimals)
+}
+
+fn keypair(config: &Config) -> CliResult<Keypair> {
+    read_keypair_file(&config.keypair_path)
+        .map_err(|err| format!("could not read keypair {}: {}", config.keypair_path, err).into())
+}
+
+fn pubkey_of(args: &ArgMatches, name: &str) -> CliResult<Pubkey> {
+    optional_pubkey_of(args, name)?.ok_or_else(|| format!("--{} is required", name).into())
+}
+
+fn optional_pubkey_of(args: &ArgMatches, name: &str) -> CliResult<Option<Pubkey>> {
+    args.value_of(name)
+        .map(|value| Pubkey::from_str(value).map_err(|err| format!("--{}: {}", name, err).into()))
+        .transpose()
+}
+
+fn value_of(args: &ArgMatches, name: &str) -> CliResult<u64> {
+    let value = args.value_of(name).unwrap();
+    value
+        .parse()
+        .map_err(|err| format!("--{}: {}", name, err).into())
+}
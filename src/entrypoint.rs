@@ -19,7 +19,59 @@ pub fn process_instruction(
             offer_data,
             index_seed,
         } => create_offer(program_id, accounts, offer_data, index_seed),
-        ACCEPT => accept_offer(program_id, accounts),
+        ACCEPT { expected } => accept_offer(program_id, accounts, expected),
         CANCEL => cancel_offer(program_id, accounts),
+        HTLC_OFFER {
+            htlc_data,
+            index_seed,
+        } => create_htlc(program_id, accounts, htlc_data, index_seed),
+        HTLC_CLAIM { preimage } => claim_htlc(program_id, accounts, preimage),
+        HTLC_REFUND => refund_htlc(program_id, accounts),
+        ARBITER_OFFER {
+            arbiter_data,
+            index_seed,
+        } => create_arbiter(program_id, accounts, arbiter_data, index_seed),
+        ARBITER_RELEASE => release_arbiter(program_id, accounts),
+        ARBITER_REFUND => refund_arbiter(program_id, accounts),
+        ARBITER_DISPUTE => dispute_arbiter(program_id, accounts),
+        ARBITER_RESOLVE { seller_bps } => resolve_arbiter(program_id, accounts, seller_bps),
+        MILESTONE_OFFER {
+            milestone_data,
+            index_seed,
+        } => create_milestone(program_id, accounts, milestone_data, index_seed),
+        MILESTONE_APPROVE { milestone } => approve_milestone(program_id, accounts, milestone),
+        MILESTONE_REFUND => refund_milestone(program_id, accounts),
+        VESTING_OFFER {
+            vesting_data,
+            index_seed,
+        } => create_vesting(program_id, accounts, vesting_data, index_seed),
+        VESTING_WITHDRAW => withdraw_vesting(program_id, accounts),
+        MULTISIG_CREATE {
+            multisig_data,
+            index_seed,
+        } => create_multisig(program_id, accounts, multisig_data, index_seed),
+        MULTISIG_APPROVE { action_hash } => approve_multisig(program_id, accounts, action_hash),
+        MULTISIG_EXECUTE { data } => execute_multisig(program_id, accounts, data),
+        SET_OPERATOR => set_operator(program_id, accounts),
+        CANCEL_MANY { count } => cancel_many(program_id, accounts, count),
+        CREATE_MANY { offers } => create_many(program_id, accounts, offers),
+        PEGGED_OFFER {
+            pegged_data,
+            index_seed,
+        } => create_pegged(program_id, accounts, pegged_data, index_seed),
+        PEGGED_ACCEPT { max_strike_qty } => accept_pegged(program_id, accounts, max_strike_qty),
+        PEGGED_CANCEL => cancel_pegged(program_id, accounts),
+        LIMIT_OFFER {
+            limit_order,
+            index_seed,
+        } => create_limit_offer(program_id, accounts, limit_order, index_seed),
+        CONFIG_INIT => init_config(program_id, accounts),
+        SET_PAUSED { paused } => set_paused(program_id, accounts, paused),
+        PROPOSE_ADMIN { admin } => propose_admin(program_id, accounts, admin),
+        SET_ALLOW_LIST { enabled } => set_allow_list(program_id, accounts, enabled),
+        ALLOW_MINT { mint } => allow_mint(program_id, accounts, mint),
+        DISALLOW_MINT => disallow_mint(program_id, accounts),
+        AMEND { strike_qty } => amend_offer(program_id, accounts, strike_qty),
+        ACCEPT_ADMIN => accept_admin(program_id, accounts),
     }
 }
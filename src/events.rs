@@ -0,0 +1,206 @@
+use crate::state::OfferData;
+use arrayref::{array_refs, mut_array_refs};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program::{log::sol_log_data, program_error::ProgramError, pubkey::Pubkey};
+use std::convert::TryInto;
+
+// event format: [0 = EVENT_VERSION, 1 = event tag, 2.. = event body], logged via sol_log_data
+pub const EVENT_VERSION: u8 = 1;
+
+// pegged offers have no strike until they are accepted, so their OfferCreated and OfferCancelled
+// events carry strike_qty 0 and OfferAccepted the strike the buyer paid
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum EscrowEvent {
+    OfferCreated {
+        escrow_pda: Pubkey,
+        seller: Pubkey,
+        seller_temp: Pubkey,
+        seller_receive: Pubkey,
+        offer_data: OfferData,
+    },
+    OfferAccepted {
+        escrow_pda: Pubkey,
+        seller: Pubkey,
+        buyer: Pubkey,
+        buyer_receive: Pubkey,
+        offer_data: OfferData,
+    },
+    OfferCancelled {
+        escrow_pda: Pubkey,
+        seller: Pubkey,
+        offer_data: OfferData,
+    },
+}
+
+impl EscrowEvent {
+    const FOUR_KEY_LEN: usize = 208;
+    const TWO_KEY_LEN: usize = 144;
+
+    pub fn emit(&self) {
+        sol_log_data(&[&self.to_bytes()]);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut dst = vec![EVENT_VERSION];
+        match self {
+            EscrowEvent::OfferCreated {
+                escrow_pda,
+                seller,
+                seller_temp,
+                seller_receive,
+                offer_data,
+            } => {
+                dst.push(0);
+                dst.extend(pack_four(
+                    [escrow_pda, seller, seller_temp, seller_receive],
+                    offer_data,
+                ));
+            }
+            EscrowEvent::OfferAccepted {
+                escrow_pda,
+                seller,
+                buyer,
+                buyer_receive,
+                offer_data,
+            } => {
+                dst.push(1);
+                dst.extend(pack_four(
+                    [escrow_pda, seller, buyer, buyer_receive],
+                    offer_data,
+                ));
+            }
+            EscrowEvent::OfferCancelled {
+                escrow_pda,
+                seller,
+                offer_data,
+            } => {
+                dst.push(2);
+                let mut body = [0; Self::TWO_KEY_LEN];
+                let (pda_dst, seller_dst, offer_dst) = mut_array_refs![&mut body, 32, 32, 80];
+                pda_dst.copy_from_slice(&escrow_pda.to_bytes());
+                seller_dst.copy_from_slice(&seller.to_bytes());
+                offer_dst.copy_from_slice(&offer_data.to_bytes());
+                dst.extend(body);
+            }
+        }
+        dst
+    }
+
+    pub fn from_bytes(src: &[u8]) -> Result<Self, ProgramError> {
+        let (version, tag, body) = match src {
+            [version, tag, body @ ..] => (*version, *tag, body),
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        if version != EVENT_VERSION {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        match tag {
+            0 => {
+                let ([escrow_pda, seller, seller_temp, seller_receive], offer_data) =
+                    unpack_four(body)?;
+                Ok(EscrowEvent::OfferCreated {
+                    escrow_pda,
+                    seller,
+                    seller_temp,
+                    seller_receive,
+                    offer_data,
+                })
+            }
+            1 => {
+                let ([escrow_pda, seller, buyer, buyer_receive], offer_data) = unpack_four(body)?;
+                Ok(EscrowEvent::OfferAccepted {
+                    escrow_pda,
+                    seller,
+                    buyer,
+                    buyer_receive,
+                    offer_data,
+                })
+            }
+            2 => {
+                let body: &[u8; Self::TWO_KEY_LEN] = body
+                    .try_into()
+                    .map_err(|_| ProgramError::InvalidAccountData)?;
+                let (escrow_pda, seller, offer_data) = array_refs![body, 32, 32, 80];
+                Ok(EscrowEvent::OfferCancelled {
+                    escrow_pda: Pubkey::new_from_array(*escrow_pda),
+                    seller: Pubkey::new_from_array(*seller),
+                    offer_data: OfferData::from_bytes(offer_data),
+                })
+            }
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
+    // decodes the events logged by program_id from a transaction's log messages, skipping data
+    // logged by other programs and events from unknown versions. a failed invoke at any depth
+    // fails the whole transaction, so its logs yield no events
+    pub fn from_logs(program_id: &Pubkey, logs: &[String]) -> Vec<Self> {
+        let mut stack: Vec<Pubkey> = vec![];
+        let mut events = vec![];
+        for log in logs {
+            if let Some(fields) = log.strip_prefix("Program data: ") {
+                if stack.last() == Some(program_id) {
+                    events.extend(
+                        fields
+                            .split(' ')
+                            .filter_map(|field| STANDARD.decode(field).ok())
+                            .filter_map(|data| Self::from_bytes(&data).ok()),
+                    );
+                }
+                continue;
+            }
+            match parse_program_log(log) {
+                Some(ProgramLog::Invoke(id)) => stack.push(id),
+                Some(ProgramLog::Success(id)) if stack.last() == Some(&id) => {
+                    stack.pop();
+                }
+                Some(ProgramLog::Failed) => return vec![],
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+enum ProgramLog {
+    Invoke(Pubkey),
+    Success(Pubkey),
+    Failed,
+}
+
+// matches the runtime's exact "Program <id> invoke [<depth>]", "Program <id> success" and
+// "Program <id> failed: <error>" lines, anything else, including program logs that merely look
+// alike, is None
+fn parse_program_log(log: &str) -> Option<ProgramLog> {
+    let (id, rest) = log.strip_prefix("Program ")?.split_once(' ')?;
+    let id = id.parse::<Pubkey>().ok()?;
+    if rest == "success" {
+        return Some(ProgramLog::Success(id));
+    }
+    if rest.starts_with("failed: ") {
+        return Some(ProgramLog::Failed);
+    }
+    let depth = rest.strip_prefix("invoke [")?.strip_suffix(']')?;
+    depth.parse::<u8>().ok()?;
+    Some(ProgramLog::Invoke(id))
+}
+
+fn pack_four(keys: [&Pubkey; 4], offer_data: &OfferData) -> [u8; EscrowEvent::FOUR_KEY_LEN] {
+    let mut dst = [0; EscrowEvent::FOUR_KEY_LEN];
+    let (k0, k1, k2, k3, offer_dst) = mut_array_refs![&mut dst, 32, 32, 32, 32, 80];
+    for (key_dst, key) in [k0, k1, k2, k3].into_iter().zip(keys) {
+        key_dst.copy_from_slice(&key.to_bytes());
+    }
+    offer_dst.copy_from_slice(&offer_data.to_bytes());
+    dst
+}
+
+fn unpack_four(src: &[u8]) -> Result<([Pubkey; 4], OfferData), ProgramError> {
+    let src: &[u8; EscrowEvent::FOUR_KEY_LEN] = src
+        .try_into()
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let (k0, k1, k2, k3, offer_data) = array_refs![src, 32, 32, 32, 32, 80];
+    let keys = [k0, k1, k2, k3].map(|key| Pubkey::new_from_array(*key));
+    Ok((keys, OfferData::from_bytes(offer_data)))
+}
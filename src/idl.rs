@@ -0,0 +1,239 @@
+use crate::instruction::{instruction_accounts, InstructionType};
+use crate::math::MathError;
+use crate::state::{MilestonePDA, PeggedData};
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use borsh::BorshSchema;
+use serde_json::{json, Value};
+use solana_program::program_error::ProgramError;
+use std::collections::BTreeMap;
+
+// machine readable interface description, checked in as idl/sol_escrow.json by tests/idl.rs.
+// instruction args and borsh types come from the BorshSchema derives, account lists from the
+// table the instruction builders read, errors from the error enums, and packed layouts from the
+// table below, which the same test checks against the Pack impls
+
+// (field, type, size) in packed order, the only layout of each account. nested data types are
+// laid out as in "types", EscrowPDA pads a none operator to 33 bytes
+pub type AccountLayout = (&'static str, &'static [(&'static str, &'static str, usize)]);
+
+pub const ACCOUNT_LAYOUTS: &[AccountLayout] = &[
+    (
+        "EscrowPDA",
+        &[
+            ("seller_main", "Pubkey", 32),
+            ("seller_temp", "Pubkey", 32),
+            ("seller_receive", "Pubkey", 32),
+            ("offer_data", "OfferData", 80),
+            ("index_seed", "Array<u8, 32>", 32),
+            ("operator", "Option<Pubkey>", 33),
+            ("rent_payer", "Pubkey", 32),
+            ("bump", "u8", 1),
+        ],
+    ),
+    (
+        "HtlcPDA",
+        &[
+            ("seller_main", "Pubkey", 32),
+            ("seller_temp", "Pubkey", 32),
+            ("htlc_data", "HtlcData", 112),
+            ("index_seed", "Array<u8, 32>", 32),
+        ],
+    ),
+    (
+        "ArbiterPDA",
+        &[
+            ("buyer_main", "Pubkey", 32),
+            ("buyer_temp", "Pubkey", 32),
+            ("arbiter_data", "ArbiterData", 104),
+            ("index_seed", "Array<u8, 32>", 32),
+            ("disputed", "bool", 1),
+        ],
+    ),
+    (
+        "VestingPDA",
+        &[
+            ("grantor_main", "Pubkey", 32),
+            ("grantor_temp", "Pubkey", 32),
+            ("vesting_data", "VestingData", 96),
+            ("index_seed", "Array<u8, 32>", 32),
+            ("withdrawn", "u64", 8),
+        ],
+    ),
+    (
+        "MultisigPDA",
+        &[
+            ("m", "u8", 1),
+            ("n", "u8", 1),
+            ("signers", "Array<Pubkey, 11>", 352),
+            ("index_seed", "Array<u8, 32>", 32),
+            ("pending", "Array<u8, 32>", 32),
+            ("approvals", "u16", 2),
+        ],
+    ),
+    (
+        "PeggedPDA",
+        &[
+            ("seller_main", "Pubkey", 32),
+            ("seller_temp", "Pubkey", 32),
+            ("seller_receive", "Pubkey", 32),
+            ("pegged_data", "PeggedData", PeggedData::LEN),
+            ("index_seed", "Array<u8, 32>", 32),
+            ("bump", "u8", 1),
+        ],
+    ),
+    (
+        "ConfigPDA",
+        &[
+            ("admin", "Pubkey", 32),
+            ("paused", "bool", 1),
+            ("allow_list", "bool", 1),
+            ("bump", "u8", 1),
+            ("pending_admin", "Pubkey", 32),
+        ],
+    ),
+    (
+        "AllowedMintPDA",
+        &[("mint", "Pubkey", 32), ("bump", "u8", 1)],
+    ),
+    // followed by count milestones of (amount u64, deadline i64, 0 = none), then count
+    // released flags, see MilestonePDA::len
+    (
+        "MilestonePDA",
+        &[
+            ("payer_main", "Pubkey", 32),
+            ("payer_temp", "Pubkey", 32),
+            ("index_seed", "Array<u8, 32>", 32),
+            ("token_type", "Pubkey", 32),
+            ("payee", "Pubkey", 32),
+            ("count", "u8", 1),
+        ],
+    ),
+];
+
+// every ProgramError the processor can return directly
+const PROGRAM_ERRORS: &[ProgramError] = &[
+    ProgramError::InvalidArgument,
+    ProgramError::InvalidInstructionData,
+    ProgramError::InvalidAccountData,
+    ProgramError::AccountDataTooSmall,
+    ProgramError::InsufficientFunds,
+    ProgramError::IncorrectProgramId,
+    ProgramError::MissingRequiredSignature,
+    ProgramError::AccountAlreadyInitialized,
+    ProgramError::UninitializedAccount,
+    ProgramError::NotEnoughAccountKeys,
+    ProgramError::AccountBorrowFailed,
+    ProgramError::ArithmeticOverflow,
+];
+
+// the program's own errors, returned as ProgramError::Custom
+const CUSTOM_ERRORS: &[MathError] = &[MathError::DivideByZero];
+
+pub fn idl() -> Value {
+    json!({
+        "name": "sol_escrow",
+        "version": env!("CARGO_PKG_VERSION"),
+        "instructions": instructions(),
+        "types": types(),
+        "accounts": accounts(),
+        "errors": errors(),
+    })
+}
+
+fn instructions() -> Vec<Value> {
+    let schema = InstructionType::schema_container();
+    let variants = match &schema.definitions[&schema.declaration] {
+        Definition::Enum { variants } => variants,
+        _ => unreachable!(),
+    };
+    variants
+        .iter()
+        .enumerate()
+        .map(|(tag, (name, declaration))| {
+            let accounts = instruction_accounts(name)
+                .unwrap_or_else(|| panic!("no IDL accounts listed for {}", name));
+            json!({
+                "name": name,
+                "tag": tag,
+                "args": fields(&schema, declaration),
+                "accounts": accounts.iter().map(|account| json!({
+                    "name": account.name,
+                    "signer": account.signer,
+                    "writable": account.writable,
+                    "optional": account.optional,
+                    "repeated": account.repeated,
+                    "with": account.with,
+                })).collect::<Vec<_>>(),
+            })
+        })
+        .collect()
+}
+
+// named borsh structs used by instructions, excluding the per-variant structs
+fn types() -> Vec<Value> {
+    let schema = InstructionType::schema_container();
+    let mut definitions = BTreeMap::new();
+    for (declaration, definition) in &schema.definitions {
+        let named = matches!(
+            definition,
+            Definition::Struct {
+                fields: Fields::NamedFields(_)
+            }
+        );
+        if named && !declaration.starts_with("InstructionType") {
+            definitions.insert(declaration.clone(), fields(&schema, declaration));
+        }
+    }
+    definitions
+        .into_iter()
+        .map(|(name, fields)| json!({ "name": name, "fields": fields }))
+        .collect()
+}
+
+fn fields(schema: &BorshSchemaContainer, declaration: &Declaration) -> Vec<Value> {
+    match schema.definitions.get(declaration) {
+        Some(Definition::Struct {
+            fields: Fields::NamedFields(fields),
+        }) => fields
+            .iter()
+            .map(|(name, ty)| json!({ "name": name, "type": ty }))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn accounts() -> Vec<Value> {
+    ACCOUNT_LAYOUTS
+        .iter()
+        .map(|(name, layout)| {
+            let mut offset = 0;
+            let fields: Vec<_> = layout
+                .iter()
+                .map(|(field, ty, size)| {
+                    let value =
+                        json!({ "name": field, "type": ty, "offset": offset, "size": size });
+                    offset += size;
+                    value
+                })
+                .collect();
+            let size = match *name {
+                "MilestonePDA" => json!(MilestonePDA::len(0)),
+                _ => json!(offset),
+            };
+            json!({ "name": name, "size": size, "fields": fields })
+        })
+        .collect()
+}
+
+// error codes as returned to clients
+fn errors() -> Vec<Value> {
+    let custom = CUSTOM_ERRORS
+        .iter()
+        .map(|err| (format!("{:?}", err), ProgramError::from(*err)));
+    PROGRAM_ERRORS
+        .iter()
+        .map(|err| (format!("{:?}", err), err.clone()))
+        .chain(custom)
+        .map(|(name, err)| json!({ "code": u64::from(err), "name": name }))
+        .collect()
+}
@@ -0,0 +1,186 @@
+use crate::state::{EscrowPDA, PeggedPDA};
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use solana_rpc_client_api::filter::{Memcmp, RpcFilterType};
+use solana_sdk::account::Account;
+use std::collections::HashMap;
+
+// an open offer as read from getProgramAccounts
+#[derive(Debug, PartialEq)]
+pub struct OpenOffer {
+    pub pda: Pubkey,
+    pub escrow: EscrowPDA,
+}
+
+impl OpenOffer {
+    // decodes an escrow offer account of any supported size, None for other account types
+    // owned by the program. HtlcPDAs share the v1 size, so the address has to match as well
+    pub fn decode(pda: Pubkey, account: &Account) -> Option<Self> {
+        let escrow = EscrowPDA::unpack_from_slice(&account.data).ok()?;
+        let (address, _bump) = escrow.address(&account.owner, account.data.len())?;
+        (address == pda).then_some(OpenOffer { pda, escrow })
+    }
+}
+
+// an open oracle pegged offer. these have no fixed price, so they stay out of the OrderBook
+#[derive(Debug, PartialEq)]
+pub struct OpenPegged {
+    pub pda: Pubkey,
+    pub pegged: PeggedPDA,
+}
+
+impl OpenPegged {
+    pub fn decode(pda: Pubkey, account: &Account) -> Option<Self> {
+        if account.data.len() != PeggedPDA::LEN {
+            return None;
+        }
+        let pegged = PeggedPDA::unpack_from_slice(&account.data).ok()?;
+        (pegged.address(&account.owner)? == pda).then_some(OpenPegged { pda, pegged })
+    }
+
+    pub fn from_accounts<I: IntoIterator<Item = (Pubkey, Account)>>(accounts: I) -> Vec<Self> {
+        accounts
+            .into_iter()
+            .filter_map(|(pda, account)| OpenPegged::decode(pda, &account))
+            .collect()
+    }
+}
+
+// open offers keyed by (token_type, strike_type), each market sorted by ascending price
+// (strike_qty per token_qty) so the best offer for a taker comes first
+#[derive(Debug, Default)]
+pub struct OrderBook {
+    markets: HashMap<(Pubkey, Pubkey), Vec<OpenOffer>>,
+}
+
+impl OrderBook {
+    pub fn from_accounts<I: IntoIterator<Item = (Pubkey, Account)>>(accounts: I) -> Self {
+        let mut book = OrderBook::default();
+        for (pda, account) in accounts {
+            if let Some(offer) = OpenOffer::decode(pda, &account) {
+                book.insert(offer);
+            }
+        }
+        book
+    }
+
+    pub fn insert(&mut self, offer: OpenOffer) {
+        self.remove(&offer.pda);
+        let data = &offer.escrow.offer_data;
+        let market = self
+            .markets
+            .entry((data.token_type, data.strike_type))
+            .or_default();
+        let index = market.partition_point(|other| price_cmp(other, &offer).is_le());
+        market.insert(index, offer);
+    }
+
+    pub fn remove(&mut self, pda: &Pubkey) -> Option<OpenOffer> {
+        let (key, index) = self.markets.iter().find_map(|(key, market)| {
+            market
+                .iter()
+                .position(|offer| offer.pda == *pda)
+                .map(|index| (*key, index))
+        })?;
+        let market = self.markets.get_mut(&key)?;
+        let offer = market.remove(index);
+        if market.is_empty() {
+            self.markets.remove(&key);
+        }
+        Some(offer)
+    }
+
+    pub fn offers(&self, token_type: &Pubkey, strike_type: &Pubkey) -> &[OpenOffer] {
+        self.markets
+            .get(&(*token_type, *strike_type))
+            .map_or(&[], |market| market.as_slice())
+    }
+
+    pub fn best(&self, token_type: &Pubkey, strike_type: &Pubkey) -> Option<&OpenOffer> {
+        self.offers(token_type, strike_type).first()
+    }
+
+    pub fn markets(&self) -> impl Iterator<Item = &(Pubkey, Pubkey)> {
+        self.markets.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.markets.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.markets.is_empty()
+    }
+}
+
+// compares strike_a / token_a against strike_b / token_b without dividing
+fn price_cmp(a: &OpenOffer, b: &OpenOffer) -> std::cmp::Ordering {
+    let (a, b) = (&a.escrow.offer_data, &b.escrow.offer_data);
+    (a.strike_qty as u128 * b.token_qty as u128).cmp(&(b.strike_qty as u128 * a.token_qty as u128))
+}
+
+// getProgramAccounts filters, one set per size in EscrowPDA::DATA_LENS to run as a query each.
+// the data size filter skips most other account types, OpenOffer::decode drops the rest
+pub fn all_offers_filters() -> Vec<Vec<RpcFilterType>> {
+    EscrowPDA::DATA_LENS
+        .iter()
+        .map(|len| vec![RpcFilterType::DataSize(*len as u64)])
+        .collect()
+}
+
+pub fn offers_by_seller_filters(seller: &Pubkey) -> Vec<Vec<RpcFilterType>> {
+    offer_filters(Some(seller), None, None)
+}
+
+pub fn offers_by_token_type_filters(token_type: &Pubkey) -> Vec<Vec<RpcFilterType>> {
+    offer_filters(None, Some(token_type), None)
+}
+
+pub fn offers_by_strike_type_filters(strike_type: &Pubkey) -> Vec<Vec<RpcFilterType>> {
+    offer_filters(None, None, Some(strike_type))
+}
+
+pub fn offers_by_market_filters(
+    token_type: &Pubkey,
+    strike_type: &Pubkey,
+) -> Vec<Vec<RpcFilterType>> {
+    offer_filters(None, Some(token_type), Some(strike_type))
+}
+
+// any combination of the above, all_offers_filters if none are given. the fields sit at the
+// same offsets in every escrow size
+pub fn offer_filters(
+    seller: Option<&Pubkey>,
+    token_type: Option<&Pubkey>,
+    strike_type: Option<&Pubkey>,
+) -> Vec<Vec<RpcFilterType>> {
+    let mut filter_sets = all_offers_filters();
+    for filters in &mut filter_sets {
+        filters.extend(memcmp_filters([
+            (EscrowPDA::SELLER_MAIN_OFFSET, seller),
+            (EscrowPDA::TOKEN_TYPE_OFFSET, token_type),
+            (EscrowPDA::STRIKE_TYPE_OFFSET, strike_type),
+        ]));
+    }
+    filter_sets
+}
+
+// the same for oracle pegged offers, decoded with OpenPegged::decode
+pub fn pegged_offer_filters(
+    seller: Option<&Pubkey>,
+    token_type: Option<&Pubkey>,
+    strike_type: Option<&Pubkey>,
+) -> Vec<Vec<RpcFilterType>> {
+    let mut filters = vec![RpcFilterType::DataSize(PeggedPDA::LEN as u64)];
+    filters.extend(memcmp_filters([
+        (PeggedPDA::SELLER_MAIN_OFFSET, seller),
+        (PeggedPDA::TOKEN_TYPE_OFFSET, token_type),
+        (PeggedPDA::STRIKE_TYPE_OFFSET, strike_type),
+    ]));
+    vec![filters]
+}
+
+fn memcmp_filters(keys: [(usize, Option<&Pubkey>); 3]) -> impl Iterator<Item = RpcFilterType> + '_ {
+    keys.into_iter().filter_map(|(offset, key)| {
+        key.map(|key| RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, key.as_ref())))
+    })
+}
@@ -1,27 +1,1455 @@
-use crate::state::{get_seed, OfferData};
-use solana_program::program_error::ProgramError;
-use std::convert::TryInto;
+// BorshSchema's enum derive declares a struct per variant that is never read
+#![cfg_attr(feature = "idl", allow(dead_code))]
+
+use crate::state::{
+    get_seed, AllowedMintPDA, ArbiterData, ArbiterPDA, ConfigPDA, EscrowPDA, HtlcData, HtlcPDA,
+    LimitOrder, MilestoneData, MilestonePDA, MultisigData, MultisigPDA, OfferData, PeggedData,
+    PeggedPDA, VestingData, VestingPDA,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    bpf_loader_upgradeable,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
 
 // index seed format: [0..32 = initialiser main pubkey, 32 = contract_type, 33..41 = contract_no (u64)]
+// instructions are borsh encoded: [0 = variant index, 1.. = fields in declaration order], so new
+// variants go at the end. index_seed fields carry the sha256 of the raw index seed
+#[allow(non_camel_case_types)]
+#[cfg_attr(feature = "idl", derive(borsh::BorshSchema))]
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
 pub enum InstructionType {
     OFFER {
         offer_data: OfferData,
         index_seed: [u8; 32],
     },
-    ACCEPT,
+    // taker may append the OfferData they expect, trade fails if the stored terms differ
+    ACCEPT {
+        expected: Option<OfferData>,
+    },
     CANCEL,
+    HTLC_OFFER {
+        htlc_data: HtlcData,
+        index_seed: [u8; 32],
+    },
+    HTLC_CLAIM {
+        preimage: [u8; 32],
+    },
+    HTLC_REFUND,
+    ARBITER_OFFER {
+        arbiter_data: ArbiterData,
+        index_seed: [u8; 32],
+    },
+    ARBITER_RELEASE,
+    ARBITER_REFUND,
+    ARBITER_DISPUTE,
+    ARBITER_RESOLVE {
+        seller_bps: u16,
+    },
+    MILESTONE_OFFER {
+        milestone_data: MilestoneData,
+        index_seed: [u8; 32],
+    },
+    MILESTONE_APPROVE {
+        milestone: u8,
+    },
+    MILESTONE_REFUND,
+    VESTING_OFFER {
+        vesting_data: VestingData,
+        index_seed: [u8; 32],
+    },
+    VESTING_WITHDRAW,
+    MULTISIG_CREATE {
+        multisig_data: MultisigData,
+        index_seed: [u8; 32],
+    },
+    MULTISIG_APPROVE {
+        action_hash: [u8; 32],
+    },
+    MULTISIG_EXECUTE {
+        data: Vec<u8>,
+    },
+    SET_OPERATOR,
+    CANCEL_MANY {
+        count: u8,
+    },
+    CREATE_MANY {
+        offers: Vec<(OfferData, [u8; 32])>,
+    },
+    PEGGED_OFFER {
+        pegged_data: PeggedData,
+        index_seed: [u8; 32],
+    },
+    // taker caps the strike they pay, the oracle may move between quote and execution
+    PEGGED_ACCEPT {
+        max_strike_qty: u64,
+    },
+    PEGGED_CANCEL,
+    // stored as the OfferData LimitOrder::offer_data gives for the mints' decimals, so is
+    // accepted and cancelled like any other offer
+    LIMIT_OFFER {
+        limit_order: LimitOrder,
+        index_seed: [u8; 32],
+    },
+    // creates the ConfigPDA with the signer, the program's upgrade authority, as admin
+    CONFIG_INIT,
+    // admin only, stops or resumes new escrows and payouts, see ConfigPDA
+    SET_PAUSED {
+        paused: bool,
+    },
+    // admin only, proposes another key as admin, which takes over once it sends ACCEPT_ADMIN
+    PROPOSE_ADMIN {
+        admin: Pubkey,
+    },
+    // admin only, limits new offers to mints with an AllowedMintPDA while enabled
+    SET_ALLOW_LIST {
+        enabled: bool,
+    },
+    // admin only, creates the AllowedMintPDA for mint
+    ALLOW_MINT {
+        mint: Pubkey,
+    },
+    // admin only, closes an AllowedMintPDA
+    DISALLOW_MINT,
+    // seller or operator reprices an offer, moving it to the escrow address of the new terms
+    AMEND {
+        strike_qty: u64,
+    },
+    // signed by the proposed admin, completes the admin transfer
+    ACCEPT_ADMIN,
 }
 
+// raw index seed length as sent by clients, see format above
+pub const INDEX_SEED_LEN: usize = 41;
+
+// baseline clients predate borsh. they send OFFER with the raw INDEX_SEED_LEN byte index seed,
+// and ACCEPT and CANCEL as the tag followed by the same raw seed, which is ignored. no borsh
+// encoding of those variants has these lengths, so the length picks the format
 pub fn decode_instruction(instruction_data: &[u8]) -> Result<InstructionType, ProgramError> {
-    match instruction_data[0] {
-        0 => Ok(InstructionType::OFFER {
-            offer_data: OfferData::from_bytes(
-                instruction_data[1..OfferData::LEN + 1].try_into().unwrap(),
+    match instruction_data {
+        [0, rest @ ..] if rest.len() == OfferData::LEN + INDEX_SEED_LEN => {
+            let (offer_data, index_seed) = rest.split_at(OfferData::LEN);
+            Ok(InstructionType::OFFER {
+                offer_data: OfferData::try_from_slice(offer_data)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?,
+                index_seed: get_seed(index_seed),
+            })
+        }
+        [1, rest @ ..] if rest.is_empty() || rest.len() == INDEX_SEED_LEN => {
+            Ok(InstructionType::ACCEPT { expected: None })
+        }
+        [2, rest @ ..] if rest.len() == INDEX_SEED_LEN => Ok(InstructionType::CANCEL),
+        _ => InstructionType::try_from_slice(instruction_data)
+            .map_err(|_| ProgramError::InvalidInstructionData),
+    }
+}
+
+// accounts of every instruction, shared by the builders below, which take signer and writable
+// from here, and the IDL
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InstructionAccount {
+    pub name: &'static str,
+    pub signer: bool,
+    pub writable: bool,
+    // may be skipped by passing the program id, or left off when nothing follows it
+    pub optional: bool,
+    // repeated once per batch entry, or once per mint for allowed_mint
+    pub repeated: bool,
+    // only passed, and read, when the named optional account is
+    pub with: Option<&'static str>,
+}
+
+const fn account(name: &'static str, signer: bool, writable: bool) -> InstructionAccount {
+    InstructionAccount {
+        name,
+        signer,
+        writable,
+        optional: false,
+        repeated: false,
+        with: None,
+    }
+}
+
+impl InstructionAccount {
+    const fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    const fn repeated(mut self) -> Self {
+        self.repeated = true;
+        self
+    }
+
+    const fn with(mut self, account: &'static str) -> Self {
+        self.with = Some(account);
+        self
+    }
+}
+
+const SYSTEM_PROGRAM: InstructionAccount = account("system_program", false, false);
+const TOKEN_PROGRAM: InstructionAccount = account("token_program", false, false);
+const CONFIG: InstructionAccount = account("config", false, false);
+// the offered mints' AllowedMintPDAs, only read while the allow-list is on
+const ALLOWED_MINT: InstructionAccount =
+    account("allowed_mint", false, false).optional().repeated();
+
+const SELLER_REFUND: &[InstructionAccount] = &[
+    account("seller", true, true),
+    account("seller_temp", false, true),
+    account("escrow_pda", false, true),
+    SYSTEM_PROGRAM,
+    TOKEN_PROGRAM,
+];
+
+// accounts in the order the processor reads them, keyed by InstructionType variant name
+pub const INSTRUCTION_ACCOUNTS: &[(&str, &[InstructionAccount])] = &[
+    (
+        "OFFER",
+        &[
+            account("seller", true, true),
+            account("seller_temp", false, true),
+            account("seller_receive", false, false),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            account("operator", false, false).optional(),
+            account("rent_payer", true, true).optional(),
+            CONFIG,
+            ALLOWED_MINT,
+        ],
+    ),
+    (
+        "ACCEPT",
+        &[
+            account("buyer", true, true),
+            account("strike_acc", false, true),
+            account("buyer_receive", false, true),
+            account("escrow_pda", false, true),
+            account("seller", false, true),
+            account("seller_temp", false, true),
+            account("seller_receive", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            account("ata_program", false, false).optional(),
+            account("token_mint", false, false).with("ata_program"),
+            account("strike_mint", false, false).with("ata_program"),
+            account("seller_wallet", false, false)
+                .optional()
+                .with("ata_program"),
+            CONFIG,
+            account("rent_payer", false, true).optional(),
+        ],
+    ),
+    (
+        "CANCEL",
+        &[
+            account("seller", true, true),
+            account("seller_temp", false, true),
+            account("seller_receive", false, false),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            account("operator", true, false).optional(),
+            account("rent_payer", false, true).optional(),
+        ],
+    ),
+    (
+        "HTLC_OFFER",
+        &[
+            account("seller", true, true),
+            account("seller_temp", false, true),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            CONFIG,
+            ALLOWED_MINT,
+        ],
+    ),
+    (
+        "HTLC_CLAIM",
+        &[
+            account("buyer", true, true),
+            account("buyer_receive", false, true),
+            account("escrow_pda", false, true),
+            account("seller", false, true),
+            account("seller_temp", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            CONFIG,
+        ],
+    ),
+    ("HTLC_REFUND", SELLER_REFUND),
+    ("MILESTONE_REFUND", SELLER_REFUND),
+    (
+        "ARBITER_OFFER",
+        &[
+            account("buyer", true, true),
+            account("buyer_temp", false, true),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            CONFIG,
+            ALLOWED_MINT,
+        ],
+    ),
+    (
+        "ARBITER_RELEASE",
+        &[
+            account("buyer", true, true),
+            account("buyer_temp", false, true),
+            account("seller_receive", false, true),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            CONFIG,
+        ],
+    ),
+    (
+        "ARBITER_REFUND",
+        &[
+            account("seller", true, true),
+            account("buyer", false, true),
+            account("buyer_temp", false, true),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+        ],
+    ),
+    (
+        "ARBITER_DISPUTE",
+        &[
+            account("party", true, false),
+            account("escrow_pda", false, true),
+        ],
+    ),
+    (
+        "ARBITER_RESOLVE",
+        &[
+            account("arbiter", true, false),
+            account("buyer", false, true),
+            account("buyer_receive", false, true),
+            account("seller_receive", false, true),
+            account("buyer_temp", false, true),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            CONFIG,
+        ],
+    ),
+    (
+        "MILESTONE_OFFER",
+        &[
+            account("payer", true, true),
+            account("payer_temp", false, true),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            CONFIG,
+            ALLOWED_MINT,
+        ],
+    ),
+    (
+        "MILESTONE_APPROVE",
+        &[
+            account("payer", true, true),
+            account("payer_temp", false, true),
+            account("payee_receive", false, true),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            CONFIG,
+        ],
+    ),
+    (
+        "VESTING_OFFER",
+        &[
+            account("grantor", true, true),
+            account("grantor_temp", false, true),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            CONFIG,
+            ALLOWED_MINT,
+        ],
+    ),
+    (
+        "VESTING_WITHDRAW",
+        &[
+            account("beneficiary", true, true),
+            account("beneficiary_receive", false, true),
+            account("escrow_pda", false, true),
+            account("grantor", false, true),
+            account("grantor_temp", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            CONFIG,
+        ],
+    ),
+    (
+        "MULTISIG_CREATE",
+        &[
+            account("payer", true, true),
+            account("multisig_pda", false, true),
+            SYSTEM_PROGRAM,
+        ],
+    ),
+    (
+        "MULTISIG_APPROVE",
+        &[
+            account("signer", true, false),
+            account("multisig_pda", false, true),
+        ],
+    ),
+    // followed by the accounts of the approved instruction
+    (
+        "MULTISIG_EXECUTE",
+        &[
+            account("multisig_pda", false, true),
+            // writable only if the action writes it, it is hashed as the action lists it
+            account("authority", false, false),
+            account("target_program", false, false),
+        ],
+    ),
+    (
+        "SET_OPERATOR",
+        &[
+            account("seller", true, false),
+            account("escrow_pda", false, true),
+            account("operator", false, false).optional(),
+        ],
+    ),
+    (
+        "CANCEL_MANY",
+        &[
+            account("seller", true, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            account("escrow_pda", false, true).repeated(),
+            account("seller_temp", false, true).repeated(),
+            account("operator", true, false).optional(),
+        ],
+    ),
+    (
+        "CREATE_MANY",
+        &[
+            account("seller", true, true),
+            account("funding", false, true),
+            account("seller_receive", false, false),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            account("seller_temp", false, true).repeated(),
+            account("escrow_pda", false, true).repeated(),
+            account("operator", false, false).optional(),
+            account("rent_payer", true, true).optional(),
+            CONFIG,
+            ALLOWED_MINT,
+        ],
+    ),
+    (
+        "PEGGED_OFFER",
+        &[
+            account("seller", true, true),
+            account("seller_temp", false, true),
+            account("seller_receive", false, false),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            CONFIG,
+            ALLOWED_MINT,
+        ],
+    ),
+    (
+        "PEGGED_ACCEPT",
+        &[
+            account("buyer", true, true),
+            account("strike_acc", false, true),
+            account("buyer_receive", false, true),
+            account("escrow_pda", false, true),
+            account("seller", false, true),
+            account("seller_temp", false, true),
+            account("seller_receive", false, true),
+            account("oracle", false, false),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            CONFIG,
+        ],
+    ),
+    ("PEGGED_CANCEL", SELLER_REFUND),
+    (
+        "LIMIT_OFFER",
+        &[
+            account("seller", true, true),
+            account("seller_temp", false, true),
+            account("seller_receive", false, false),
+            account("escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            account("token_mint", false, false),
+            account("strike_mint", false, false),
+            account("operator", false, false).optional(),
+            account("rent_payer", true, true).optional(),
+            CONFIG,
+            ALLOWED_MINT,
+        ],
+    ),
+    (
+        "CONFIG_INIT",
+        &[
+            account("admin", true, true),
+            account("config", false, true),
+            SYSTEM_PROGRAM,
+            account("program_data", false, false),
+        ],
+    ),
+    (
+        "SET_PAUSED",
+        &[
+            account("admin", true, false),
+            account("config", false, true),
+        ],
+    ),
+    (
+        "PROPOSE_ADMIN",
+        &[
+            account("admin", true, false),
+            account("config", false, true),
+        ],
+    ),
+    (
+        "SET_ALLOW_LIST",
+        &[
+            account("admin", true, false),
+            account("config", false, true),
+        ],
+    ),
+    (
+        "ALLOW_MINT",
+        &[
+            account("admin", true, true),
+            account("config", false, false),
+            account("allowed_mint", false, true),
+            SYSTEM_PROGRAM,
+        ],
+    ),
+    (
+        "DISALLOW_MINT",
+        &[
+            account("admin", true, true),
+            account("config", false, false),
+            account("allowed_mint", false, true),
+        ],
+    ),
+    (
+        "AMEND",
+        &[
+            // signs unless the operator does
+            account("seller", true, true),
+            account("seller_temp", false, true),
+            account("escrow_pda", false, true),
+            account("new_escrow_pda", false, true),
+            SYSTEM_PROGRAM,
+            TOKEN_PROGRAM,
+            account("operator", true, true).optional(),
+            CONFIG,
+        ],
+    ),
+    (
+        "ACCEPT_ADMIN",
+        &[
+            account("new_admin", true, false),
+            account("config", false, true),
+        ],
+    ),
+];
+
+pub fn instruction_accounts(instruction: &str) -> Option<&'static [InstructionAccount]> {
+    INSTRUCTION_ACCOUNTS
+        .iter()
+        .find(|(name, _)| *name == instruction)
+        .map(|(_, accounts)| *accounts)
+}
+
+// client side builders, one per instruction. each lists its accounts as (name, key) pairs in
+// the order of INSTRUCTION_ACCOUNTS, an optional account passed as the program id is skipped
+
+fn account_metas(
+    program_id: &Pubkey,
+    instruction: &str,
+    keys: &[(&str, Pubkey)],
+) -> Vec<AccountMeta> {
+    let table = instruction_accounts(instruction).unwrap();
+    keys.iter()
+        .map(|(name, key)| {
+            let account = table
+                .iter()
+                .find(|account| account.name == *name)
+                .unwrap_or_else(|| panic!("{} has no account {}", instruction, name));
+            if account.optional && key == program_id {
+                return AccountMeta::new_readonly(*key, false);
+            }
+            AccountMeta {
+                pubkey: *key,
+                is_signer: account.signer,
+                is_writable: account.writable,
+            }
+        })
+        .collect()
+}
+
+fn build(
+    program_id: &Pubkey,
+    data: &InstructionType,
+    name: &str,
+    keys: &[(&str, Pubkey)],
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &data.try_to_vec().unwrap(),
+        account_metas(program_id, name, keys),
+    )
+}
+
+pub fn index_seed(
+    initialiser: &Pubkey,
+    contract_type: u8,
+    contract_no: u64,
+) -> [u8; INDEX_SEED_LEN] {
+    let mut index_seed = [0; INDEX_SEED_LEN];
+    index_seed[..32].copy_from_slice(&initialiser.to_bytes());
+    index_seed[32] = contract_type;
+    index_seed[33..].copy_from_slice(&contract_no.to_le_bytes());
+    index_seed
+}
+
+// the PDA of any escrow type, from the seed of its data and the raw index seed
+pub fn pda_address(program_id: &Pubkey, data_seed: &[u8; 32], index_seed: &[u8]) -> Pubkey {
+    Pubkey::find_program_address(&[data_seed, &get_seed(index_seed)], program_id).0
+}
+
+pub fn escrow_address(
+    program_id: &Pubkey,
+    offer_data: &OfferData,
+    index_seed: &[u8; INDEX_SEED_LEN],
+) -> Pubkey {
+    pda_address(program_id, &offer_data.get_seed(), index_seed)
+}
+
+pub fn config_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[ConfigPDA::SEED], program_id).0
+}
+
+// the upgradeable loader's ProgramData account, which records the upgrade authority
+pub fn program_data_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0
+}
+
+pub fn allowed_mint_address(program_id: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[AllowedMintPDA::SEED, mint.as_ref()], program_id).0
+}
+
+pub fn offer(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    seller_temp: &Pubkey,
+    seller_receive: &Pubkey,
+    offer_data: &OfferData,
+    index_seed: &[u8; INDEX_SEED_LEN],
+) -> Instruction {
+    let data = InstructionType::OFFER {
+        offer_data: *offer_data,
+        index_seed: get_seed(index_seed),
+    };
+    build(
+        program_id,
+        &data,
+        "OFFER",
+        &[
+            ("seller", *seller),
+            ("seller_temp", *seller_temp),
+            ("seller_receive", *seller_receive),
+            (
+                "escrow_pda",
+                escrow_address(program_id, offer_data, index_seed),
+            ),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+            ("operator", *program_id),
+            ("rent_payer", *program_id),
+            ("config", config_address(program_id)),
+        ],
+    )
+}
+
+// an offer instruction as built above, for when the allow-list is on: appends the
+// AllowedMintPDAs of the given mints
+pub fn with_mints(program_id: &Pubkey, mut ix: Instruction, mints: &[Pubkey]) -> Instruction {
+    ix.accounts.extend(mints.iter().map(|mint| AccountMeta {
+        pubkey: allowed_mint_address(program_id, mint),
+        is_signer: ALLOWED_MINT.signer,
+        is_writable: ALLOWED_MINT.writable,
+    }));
+    ix
+}
+
+pub fn with_allowed_mints(
+    program_id: &Pubkey,
+    ix: Instruction,
+    offer_data: &OfferData,
+) -> Instruction {
+    with_mints(
+        program_id,
+        ix,
+        &[offer_data.token_type, offer_data.strike_type],
+    )
+}
+
+// pins the terms in escrow as the expected offer data
+pub fn accept(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    strike_acc: &Pubkey,
+    buyer_receive: &Pubkey,
+    escrow_pda: &Pubkey,
+    escrow: &EscrowPDA,
+) -> Instruction {
+    let mut keys = vec![
+        ("buyer", *buyer),
+        ("strike_acc", *strike_acc),
+        ("buyer_receive", *buyer_receive),
+        ("escrow_pda", *escrow_pda),
+        ("seller", escrow.seller_main),
+        ("seller_temp", escrow.seller_temp),
+        ("seller_receive", escrow.seller_receive),
+        ("system_program", system_program::id()),
+        ("token_program", spl_token::id()),
+        ("ata_program", *program_id),
+        ("config", config_address(program_id)),
+    ];
+    if escrow.rent_payer != escrow.seller_main {
+        keys.push(("rent_payer", escrow.rent_payer));
+    }
+    let data = InstructionType::ACCEPT {
+        expected: Some(escrow.offer_data),
+    };
+    build(program_id, &data, "ACCEPT", &keys)
+}
+
+pub fn cancel(program_id: &Pubkey, escrow_pda: &Pubkey, escrow: &EscrowPDA) -> Instruction {
+    let mut keys = vec![
+        ("seller", escrow.seller_main),
+        ("seller_temp", escrow.seller_temp),
+        ("seller_receive", escrow.seller_receive),
+        ("escrow_pda", *escrow_pda),
+        ("system_program", system_program::id()),
+        ("token_program", spl_token::id()),
+    ];
+    if escrow.rent_payer != escrow.seller_main {
+        keys.push(("operator", *program_id));
+        keys.push(("rent_payer", escrow.rent_payer));
+    }
+    build(program_id, &InstructionType::CANCEL, "CANCEL", &keys)
+}
+
+// one or more escrows of one seller, with the rent payers of any funded by someone else
+pub fn cancel_many(program_id: &Pubkey, escrows: &[(&Pubkey, &EscrowPDA)]) -> Instruction {
+    assert!(!escrows.is_empty(), "cancel_many needs at least one escrow");
+    let seller = escrows[0].1.seller_main;
+    let mut keys = vec![
+        ("seller", seller),
+        ("system_program", system_program::id()),
+        ("token_program", spl_token::id()),
+    ];
+    for (escrow_pda, escrow) in escrows {
+        keys.push(("escrow_pda", **escrow_pda));
+        keys.push(("seller_temp", escrow.seller_temp));
+    }
+    let mut metas = account_metas(program_id, "CANCEL_MANY", &keys);
+    let mut rent_payers: Vec<_> = escrows
+        .iter()
+        .map(|(_, escrow)| escrow.rent_payer)
+        .filter(|rent_payer| *rent_payer != seller)
+        .collect();
+    rent_payers.sort();
+    rent_payers.dedup();
+    if !rent_payers.is_empty() {
+        metas.push(AccountMeta::new_readonly(*program_id, false));
+        // read by the cancels, not listed in the table as they follow its last account
+        metas.extend(
+            rent_payers
+                .into_iter()
+                .map(|key| AccountMeta::new(key, false)),
+        );
+    }
+    let data = InstructionType::CANCEL_MANY {
+        count: escrows.len() as u8,
+    };
+    Instruction::new_with_bytes(*program_id, &data.try_to_vec().unwrap(), metas)
+}
+
+// each entry is a new seller_temp funded with token_qty from funding, its offer and raw index seed
+pub fn create_many(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    funding: &Pubkey,
+    seller_receive: &Pubkey,
+    offers: &[(Pubkey, OfferData, [u8; INDEX_SEED_LEN])],
+) -> Instruction {
+    let mut keys = vec![
+        ("seller", *seller),
+        ("funding", *funding),
+        ("seller_receive", *seller_receive),
+        ("system_program", system_program::id()),
+        ("token_program", spl_token::id()),
+    ];
+    for (seller_temp, offer_data, index_seed) in offers {
+        keys.push(("seller_temp", *seller_temp));
+        keys.push((
+            "escrow_pda",
+            escrow_address(program_id, offer_data, index_seed),
+        ));
+    }
+    keys.extend([
+        ("operator", *program_id),
+        ("rent_payer", *program_id),
+        ("config", config_address(program_id)),
+    ]);
+    let data = InstructionType::CREATE_MANY {
+        offers: offers
+            .iter()
+            .map(|(_, offer_data, index_seed)| (*offer_data, get_seed(index_seed)))
+            .collect(),
+    };
+    build(program_id, &data, "CREATE_MANY", &keys)
+}
+
+// errors if the price gives no strike_qty for the mints' decimals
+pub fn limit_offer(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    seller_temp: &Pubkey,
+    seller_receive: &Pubkey,
+    limit_order: &LimitOrder,
+    decimals: (u8, u8),
+    index_seed: &[u8; INDEX_SEED_LEN],
+) -> Result<Instruction, ProgramError> {
+    let offer_data = limit_order.offer_data(decimals.0, decimals.1)?;
+    let data = InstructionType::LIMIT_OFFER {
+        limit_order: *limit_order,
+        index_seed: get_seed(index_seed),
+    };
+    Ok(build(
+        program_id,
+        &data,
+        "LIMIT_OFFER",
+        &[
+            ("seller", *seller),
+            ("seller_temp", *seller_temp),
+            ("seller_receive", *seller_receive),
+            (
+                "escrow_pda",
+                escrow_address(program_id, &offer_data, index_seed),
             ),
-            index_seed: get_seed(&instruction_data[OfferData::LEN + 1..]),
-        }),
-        1 => Ok(InstructionType::ACCEPT),
-        2 => Ok(InstructionType::CANCEL),
-        _ => Err(ProgramError::InvalidInstructionData),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+            ("token_mint", limit_order.token_type),
+            ("strike_mint", limit_order.strike_type),
+            ("operator", *program_id),
+            ("rent_payer", *program_id),
+            ("config", config_address(program_id)),
+        ],
+    ))
+}
+
+// signed by seller_main, operator None clears it
+pub fn set_operator(
+    program_id: &Pubkey,
+    escrow_pda: &Pubkey,
+    escrow: &EscrowPDA,
+    operator: Option<&Pubkey>,
+) -> Instruction {
+    let mut keys = vec![("seller", escrow.seller_main), ("escrow_pda", *escrow_pda)];
+    if let Some(operator) = operator {
+        keys.push(("operator", *operator));
     }
+    build(
+        program_id,
+        &InstructionType::SET_OPERATOR,
+        "SET_OPERATOR",
+        &keys,
+    )
+}
+
+// signed by the operator if given, else by seller_main, who then pays any extra rent
+pub fn amend(
+    program_id: &Pubkey,
+    escrow_pda: &Pubkey,
+    escrow: &EscrowPDA,
+    strike_qty: u64,
+    operator: Option<&Pubkey>,
+) -> Instruction {
+    let offer_data = OfferData {
+        strike_qty,
+        ..escrow.offer_data
+    };
+    let (new_escrow_pda, _bump) =
+        Pubkey::find_program_address(&[&offer_data.get_seed(), &escrow.index_seed], program_id);
+    let mut ix = build(
+        program_id,
+        &InstructionType::AMEND { strike_qty },
+        "AMEND",
+        &[
+            ("seller", escrow.seller_main),
+            ("seller_temp", escrow.seller_temp),
+            ("escrow_pda", *escrow_pda),
+            ("new_escrow_pda", new_escrow_pda),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+            ("operator", *operator.unwrap_or(program_id)),
+            ("config", config_address(program_id)),
+        ],
+    );
+    // the operator signs in place of seller_main
+    ix.accounts[0].is_signer = operator.is_none();
+    ix
+}
+
+pub fn pegged_offer(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    seller_temp: &Pubkey,
+    seller_receive: &Pubkey,
+    pegged_data: &PeggedData,
+    index_seed: &[u8; INDEX_SEED_LEN],
+) -> Instruction {
+    let data = InstructionType::PEGGED_OFFER {
+        pegged_data: *pegged_data,
+        index_seed: get_seed(index_seed),
+    };
+    build(
+        program_id,
+        &data,
+        "PEGGED_OFFER",
+        &[
+            ("seller", *seller),
+            ("seller_temp", *seller_temp),
+            ("seller_receive", *seller_receive),
+            (
+                "escrow_pda",
+                pda_address(program_id, &pegged_data.get_seed(), index_seed),
+            ),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+            ("config", config_address(program_id)),
+        ],
+    )
+}
+
+pub fn pegged_accept(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    strike_acc: &Pubkey,
+    buyer_receive: &Pubkey,
+    escrow_pda: &Pubkey,
+    pegged: &PeggedPDA,
+    max_strike_qty: u64,
+) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::PEGGED_ACCEPT { max_strike_qty },
+        "PEGGED_ACCEPT",
+        &[
+            ("buyer", *buyer),
+            ("strike_acc", *strike_acc),
+            ("buyer_receive", *buyer_receive),
+            ("escrow_pda", *escrow_pda),
+            ("seller", pegged.seller_main),
+            ("seller_temp", pegged.seller_temp),
+            ("seller_receive", pegged.seller_receive),
+            ("oracle", pegged.pegged_data.oracle),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+            ("config", config_address(program_id)),
+        ],
+    )
+}
+
+pub fn pegged_cancel(program_id: &Pubkey, escrow_pda: &Pubkey, pegged: &PeggedPDA) -> Instruction {
+    seller_refund(
+        program_id,
+        InstructionType::PEGGED_CANCEL,
+        "PEGGED_CANCEL",
+        &pegged.seller_main,
+        &pegged.seller_temp,
+        escrow_pda,
+    )
+}
+
+// the refunds that only return a temp account to its depositor
+fn seller_refund(
+    program_id: &Pubkey,
+    data: InstructionType,
+    name: &str,
+    seller: &Pubkey,
+    seller_temp: &Pubkey,
+    escrow_pda: &Pubkey,
+) -> Instruction {
+    build(
+        program_id,
+        &data,
+        name,
+        &[
+            ("seller", *seller),
+            ("seller_temp", *seller_temp),
+            ("escrow_pda", *escrow_pda),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+        ],
+    )
+}
+
+// the offers of the other escrow types: depositor, temp holding token_qty, the new PDA, then
+// config. with the allow-list on, add the mint's AllowedMintPDA with with_mints
+fn escrow_offer(
+    program_id: &Pubkey,
+    data: InstructionType,
+    name: &str,
+    depositor: (&str, &Pubkey),
+    temp: (&str, &Pubkey),
+    escrow_pda: Pubkey,
+) -> Instruction {
+    build(
+        program_id,
+        &data,
+        name,
+        &[
+            (depositor.0, *depositor.1),
+            (temp.0, *temp.1),
+            ("escrow_pda", escrow_pda),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+            ("config", config_address(program_id)),
+        ],
+    )
+}
+
+pub fn htlc_offer(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    seller_temp: &Pubkey,
+    htlc_data: HtlcData,
+    index_seed: &[u8; INDEX_SEED_LEN],
+) -> Instruction {
+    let escrow_pda = pda_address(program_id, &htlc_data.get_seed(), index_seed);
+    let data = InstructionType::HTLC_OFFER {
+        htlc_data,
+        index_seed: get_seed(index_seed),
+    };
+    escrow_offer(
+        program_id,
+        data,
+        "HTLC_OFFER",
+        ("seller", seller),
+        ("seller_temp", seller_temp),
+        escrow_pda,
+    )
+}
+
+// signed by htlc_data.buyer
+pub fn htlc_claim(
+    program_id: &Pubkey,
+    buyer_receive: &Pubkey,
+    escrow_pda: &Pubkey,
+    htlc: &HtlcPDA,
+    preimage: [u8; 32],
+) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::HTLC_CLAIM { preimage },
+        "HTLC_CLAIM",
+        &[
+            ("buyer", htlc.htlc_data.buyer),
+            ("buyer_receive", *buyer_receive),
+            ("escrow_pda", *escrow_pda),
+            ("seller", htlc.seller_main),
+            ("seller_temp", htlc.seller_temp),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+            ("config", config_address(program_id)),
+        ],
+    )
+}
+
+pub fn htlc_refund(program_id: &Pubkey, escrow_pda: &Pubkey, htlc: &HtlcPDA) -> Instruction {
+    seller_refund(
+        program_id,
+        InstructionType::HTLC_REFUND,
+        "HTLC_REFUND",
+        &htlc.seller_main,
+        &htlc.seller_temp,
+        escrow_pda,
+    )
+}
+
+pub fn arbiter_offer(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    buyer_temp: &Pubkey,
+    arbiter_data: ArbiterData,
+    index_seed: &[u8; INDEX_SEED_LEN],
+) -> Instruction {
+    let escrow_pda = pda_address(program_id, &arbiter_data.get_seed(), index_seed);
+    let data = InstructionType::ARBITER_OFFER {
+        arbiter_data,
+        index_seed: get_seed(index_seed),
+    };
+    escrow_offer(
+        program_id,
+        data,
+        "ARBITER_OFFER",
+        ("buyer", buyer),
+        ("buyer_temp", buyer_temp),
+        escrow_pda,
+    )
+}
+
+// signed by buyer_main, seller_receive is arbiter_data.seller's token account
+pub fn arbiter_release(
+    program_id: &Pubkey,
+    seller_receive: &Pubkey,
+    escrow_pda: &Pubkey,
+    arbiter: &ArbiterPDA,
+) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::ARBITER_RELEASE,
+        "ARBITER_RELEASE",
+        &[
+            ("buyer", arbiter.buyer_main),
+            ("buyer_temp", arbiter.buyer_temp),
+            ("seller_receive", *seller_receive),
+            ("escrow_pda", *escrow_pda),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+            ("config", config_address(program_id)),
+        ],
+    )
+}
+
+// signed by arbiter_data.seller
+pub fn arbiter_refund(
+    program_id: &Pubkey,
+    escrow_pda: &Pubkey,
+    arbiter: &ArbiterPDA,
+) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::ARBITER_REFUND,
+        "ARBITER_REFUND",
+        &[
+            ("seller", arbiter.arbiter_data.seller),
+            ("buyer", arbiter.buyer_main),
+            ("buyer_temp", arbiter.buyer_temp),
+            ("escrow_pda", *escrow_pda),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+        ],
+    )
+}
+
+// signed by either party
+pub fn arbiter_dispute(program_id: &Pubkey, party: &Pubkey, escrow_pda: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::ARBITER_DISPUTE,
+        "ARBITER_DISPUTE",
+        &[("party", *party), ("escrow_pda", *escrow_pda)],
+    )
+}
+
+// signed by arbiter_data.arbiter
+pub fn arbiter_resolve(
+    program_id: &Pubkey,
+    buyer_receive: &Pubkey,
+    seller_receive: &Pubkey,
+    escrow_pda: &Pubkey,
+    arbiter: &ArbiterPDA,
+    seller_bps: u16,
+) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::ARBITER_RESOLVE { seller_bps },
+        "ARBITER_RESOLVE",
+        &[
+            ("arbiter", arbiter.arbiter_data.arbiter),
+            ("buyer", arbiter.buyer_main),
+            ("buyer_receive", *buyer_receive),
+            ("seller_receive", *seller_receive),
+            ("buyer_temp", arbiter.buyer_temp),
+            ("escrow_pda", *escrow_pda),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+            ("config", config_address(program_id)),
+        ],
+    )
+}
+
+pub fn milestone_offer(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    payer_temp: &Pubkey,
+    milestone_data: MilestoneData,
+    index_seed: &[u8; INDEX_SEED_LEN],
+) -> Instruction {
+    let escrow_pda = pda_address(program_id, &milestone_data.get_seed(), index_seed);
+    let data = InstructionType::MILESTONE_OFFER {
+        milestone_data,
+        index_seed: get_seed(index_seed),
+    };
+    escrow_offer(
+        program_id,
+        data,
+        "MILESTONE_OFFER",
+        ("payer", payer),
+        ("payer_temp", payer_temp),
+        escrow_pda,
+    )
+}
+
+// signed by payer_main
+pub fn milestone_approve(
+    program_id: &Pubkey,
+    payee_receive: &Pubkey,
+    escrow_pda: &Pubkey,
+    milestones: &MilestonePDA,
+    milestone: u8,
+) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::MILESTONE_APPROVE { milestone },
+        "MILESTONE_APPROVE",
+        &[
+            ("payer", milestones.payer_main),
+            ("payer_temp", milestones.payer_temp),
+            ("payee_receive", *payee_receive),
+            ("escrow_pda", *escrow_pda),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+            ("config", config_address(program_id)),
+        ],
+    )
+}
+
+pub fn milestone_refund(
+    program_id: &Pubkey,
+    escrow_pda: &Pubkey,
+    milestones: &MilestonePDA,
+) -> Instruction {
+    seller_refund(
+        program_id,
+        InstructionType::MILESTONE_REFUND,
+        "MILESTONE_REFUND",
+        &milestones.payer_main,
+        &milestones.payer_temp,
+        escrow_pda,
+    )
+}
+
+pub fn vesting_offer(
+    program_id: &Pubkey,
+    grantor: &Pubkey,
+    grantor_temp: &Pubkey,
+    vesting_data: VestingData,
+    index_seed: &[u8; INDEX_SEED_LEN],
+) -> Instruction {
+    let escrow_pda = pda_address(program_id, &vesting_data.get_seed(), index_seed);
+    let data = InstructionType::VESTING_OFFER {
+        vesting_data,
+        index_seed: get_seed(index_seed),
+    };
+    escrow_offer(
+        program_id,
+        data,
+        "VESTING_OFFER",
+        ("grantor", grantor),
+        ("grantor_temp", grantor_temp),
+        escrow_pda,
+    )
+}
+
+// signed by vesting_data.beneficiary
+pub fn vesting_withdraw(
+    program_id: &Pubkey,
+    beneficiary_receive: &Pubkey,
+    escrow_pda: &Pubkey,
+    vesting: &VestingPDA,
+) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::VESTING_WITHDRAW,
+        "VESTING_WITHDRAW",
+        &[
+            ("beneficiary", vesting.vesting_data.beneficiary),
+            ("beneficiary_receive", *beneficiary_receive),
+            ("escrow_pda", *escrow_pda),
+            ("grantor", vesting.grantor_main),
+            ("grantor_temp", vesting.grantor_temp),
+            ("system_program", system_program::id()),
+            ("token_program", spl_token::id()),
+            ("config", config_address(program_id)),
+        ],
+    )
+}
+
+pub fn multisig_create(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    multisig_data: MultisigData,
+    index_seed: &[u8; INDEX_SEED_LEN],
+) -> Instruction {
+    let multisig_pda = pda_address(program_id, &multisig_data.get_seed(), index_seed);
+    let data = InstructionType::MULTISIG_CREATE {
+        multisig_data,
+        index_seed: get_seed(index_seed),
+    };
+    build(
+        program_id,
+        &data,
+        "MULTISIG_CREATE",
+        &[
+            ("payer", *payer),
+            ("multisig_pda", multisig_pda),
+            ("system_program", system_program::id()),
+        ],
+    )
+}
+
+// approves action, an instruction the multisig authority signs as MULTISIG_EXECUTE passes it on
+pub fn multisig_approve(
+    program_id: &Pubkey,
+    signer: &Pubkey,
+    multisig_pda: &Pubkey,
+    action: &Instruction,
+) -> Instruction {
+    let data = InstructionType::MULTISIG_APPROVE {
+        action_hash: MultisigPDA::action_hash(action),
+    };
+    build(
+        program_id,
+        &data,
+        "MULTISIG_APPROVE",
+        &[("signer", *signer), ("multisig_pda", *multisig_pda)],
+    )
+}
+
+// followed by the accounts of action, with the authority's signature left to the program
+pub fn multisig_execute(
+    program_id: &Pubkey,
+    multisig_pda: &Pubkey,
+    action: &Instruction,
+) -> Instruction {
+    let (authority, _bump) = MultisigPDA::authority(multisig_pda, program_id);
+    let mut metas = account_metas(
+        program_id,
+        "MULTISIG_EXECUTE",
+        &[
+            ("multisig_pda", *multisig_pda),
+            ("authority", authority),
+            ("target_program", action.program_id),
+        ],
+    );
+    metas.extend(action.accounts.iter().map(|meta| AccountMeta {
+        is_signer: meta.is_signer && meta.pubkey != authority,
+        ..meta.clone()
+    }));
+    let data = InstructionType::MULTISIG_EXECUTE {
+        data: action.data.clone(),
+    };
+    Instruction::new_with_bytes(*program_id, &data.try_to_vec().unwrap(), metas)
+}
+
+// admin instructions
+
+pub fn config_init(program_id: &Pubkey, admin: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::CONFIG_INIT,
+        "CONFIG_INIT",
+        &[
+            ("admin", *admin),
+            ("config", config_address(program_id)),
+            ("system_program", system_program::id()),
+            ("program_data", program_data_address(program_id)),
+        ],
+    )
+}
+
+pub fn set_paused(program_id: &Pubkey, admin: &Pubkey, paused: bool) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::SET_PAUSED { paused },
+        "SET_PAUSED",
+        &[("admin", *admin), ("config", config_address(program_id))],
+    )
+}
+
+pub fn propose_admin(program_id: &Pubkey, admin: &Pubkey, new_admin: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::PROPOSE_ADMIN { admin: *new_admin },
+        "PROPOSE_ADMIN",
+        &[("admin", *admin), ("config", config_address(program_id))],
+    )
+}
+
+pub fn accept_admin(program_id: &Pubkey, new_admin: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::ACCEPT_ADMIN,
+        "ACCEPT_ADMIN",
+        &[
+            ("new_admin", *new_admin),
+            ("config", config_address(program_id)),
+        ],
+    )
+}
+
+pub fn set_allow_list(program_id: &Pubkey, admin: &Pubkey, enabled: bool) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::SET_ALLOW_LIST { enabled },
+        "SET_ALLOW_LIST",
+        &[("admin", *admin), ("config", config_address(program_id))],
+    )
+}
+
+pub fn allow_mint(program_id: &Pubkey, admin: &Pubkey, mint: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::ALLOW_MINT { mint: *mint },
+        "ALLOW_MINT",
+        &[
+            ("admin", *admin),
+            ("config", config_address(program_id)),
+            ("allowed_mint", allowed_mint_address(program_id, mint)),
+            ("system_program", system_program::id()),
+        ],
+    )
+}
+
+pub fn disallow_mint(program_id: &Pubkey, admin: &Pubkey, mint: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        &InstructionType::DISALLOW_MINT,
+        "DISALLOW_MINT",
+        &[
+            ("admin", *admin),
+            ("config", config_address(program_id)),
+            ("allowed_mint", allowed_mint_address(program_id, mint)),
+        ],
+    )
 }
@@ -1,4 +1,12 @@
 pub mod entrypoint;
+pub mod events;
+#[cfg(feature = "idl")]
+pub mod idl;
+#[cfg(feature = "indexer")]
+pub mod indexer;
 pub mod instruction;
+pub mod math;
 pub mod processor;
+#[cfg(feature = "client")]
+pub mod simulator;
 pub mod state;
@@ -0,0 +1,93 @@
+use solana_program::program_error::ProgramError;
+
+// amount arithmetic used by the processor. every helper is exact or returns
+// ProgramError::ArithmeticOverflow, or MathError::DivideByZero for a zero denominator, never a
+// wrapped or saturated value. nothing is logged, so the helpers also run in clients outside of
+// a transaction
+
+// returned as ProgramError::Custom(code), ProgramError has no variant of its own for these
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MathError {
+    DivideByZero,
+}
+
+impl From<MathError> for ProgramError {
+    fn from(error: MathError) -> Self {
+        ProgramError::Custom(error as u32)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Rounding {
+    Down,
+    Up,
+    // half way rounds up
+    Nearest,
+}
+
+// a * b / denominator on u64 amounts. the product is formed in u128 so it never overflows,
+// only a quotient above u64::MAX or a zero denominator is an error
+pub fn mul_div(a: u64, b: u64, denominator: u64, rounding: Rounding) -> Result<u64, ProgramError> {
+    let quotient = mul_div_wide(a as u128, b as u128, denominator as u128, rounding)?;
+    to_u64(quotient)
+}
+
+// a * b / denominator for intermediate values already widened to u128, e.g. decimal scaling
+pub fn mul_div_wide(
+    a: u128,
+    b: u128,
+    denominator: u128,
+    rounding: Rounding,
+) -> Result<u128, ProgramError> {
+    div(mul(a, b)?, denominator, rounding)
+}
+
+pub fn mul(a: u128, b: u128) -> Result<u128, ProgramError> {
+    a.checked_mul(b).ok_or_else(overflow)
+}
+
+pub fn div(numerator: u128, denominator: u128, rounding: Rounding) -> Result<u128, ProgramError> {
+    if denominator == 0 {
+        return Err(MathError::DivideByZero.into());
+    }
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+    let round_up = match rounding {
+        Rounding::Down => false,
+        Rounding::Up => remainder != 0,
+        Rounding::Nearest => remainder != 0 && remainder >= denominator - remainder,
+    };
+    if round_up {
+        quotient.checked_add(1).ok_or_else(overflow)
+    } else {
+        Ok(quotient)
+    }
+}
+
+pub fn pow10(exponent: u8) -> Result<u128, ProgramError> {
+    10u128.checked_pow(exponent as u32).ok_or_else(overflow)
+}
+
+pub fn to_u64(value: u128) -> Result<u64, ProgramError> {
+    u64::try_from(value).map_err(|_| overflow())
+}
+
+pub fn checked_add(a: u64, b: u64) -> Result<u64, ProgramError> {
+    a.checked_add(b).ok_or_else(overflow)
+}
+
+pub fn checked_sub(a: u64, b: u64) -> Result<u64, ProgramError> {
+    a.checked_sub(b).ok_or_else(overflow)
+}
+
+// seconds from one unix timestamp to a later one, an error if to is earlier or the span
+// overflows
+pub fn elapsed(from: i64, to: i64) -> Result<u64, ProgramError> {
+    to.checked_sub(from)
+        .and_then(|seconds| u64::try_from(seconds).ok())
+        .ok_or_else(overflow)
+}
+
+fn overflow() -> ProgramError {
+    ProgramError::ArithmeticOverflow
+}
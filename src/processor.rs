@@ -1,15 +1,33 @@
-use crate::state::{EscrowPDA, OfferData};
+use crate::events::EscrowEvent;
+use crate::math::{self, Rounding};
+use crate::state::{
+    create_address, find_address, get_seed, AllowedMintPDA, ArbiterData, ArbiterPDA, ConfigPDA,
+    EscrowPDA, EscrowView, HtlcData, HtlcPDA, LimitOrder, MilestoneData, MilestonePDA,
+    MultisigData, MultisigPDA, OfferData, OraclePrice, PeggedData, PeggedPDA, VestingData,
+    VestingPDA,
+};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
     msg,
     program::{invoke, invoke_signed},
     program_error::ProgramError,
     program_pack::*,
+    program_utils::limited_deserialize,
     pubkey::Pubkey,
     system_instruction, system_program,
     sysvar::{rent, Sysvar},
 };
-use spl_token::{instruction, state::Account};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account,
+};
+use spl_token::{
+    instruction,
+    state::{Account, Mint},
+};
 
 pub fn create_offer(
     program_id: &Pubkey,
@@ -24,12 +42,9 @@ pub fn create_offer(
     let escrow_pda = next_account_info(accounts)?;
     let sys_program = next_account_info(accounts)?;
     let token_program = next_account_info(accounts)?;
-
-    let temp_info = Account::unpack_from_slice(*seller_temp.try_borrow_data()?)?;
-    let receive_info = Account::unpack_from_slice(*seller_receive.try_borrow_data()?)?;
-    let seed = offer_data.get_seed();
-    let (pda, bump) = Pubkey::find_program_address(&[&seed, &index_seed], program_id);
-    let min_rent = rent::Rent::get()?.minimum_balance(EscrowPDA::LEN);
+    let operator = next_optional_account(accounts, program_id);
+    let rent_payer = next_optional_account(accounts, program_id);
+    let config = next_account_info(accounts)?;
 
     msg!("asserting validity...");
     if !system_program::check_id(sys_program.key) {
@@ -38,71 +53,190 @@ pub fn create_offer(
     if !spl_token::check_id(token_program.key) {
         return Err(ProgramError::IncorrectProgramId);
     }
-    if !seller.is_signer {
-        return Err(ProgramError::MissingRequiredSignature);
+    check_offer_config(
+        program_id,
+        config,
+        &[offer_data.token_type, offer_data.strike_type],
+        accounts.as_slice(),
+    )?;
+    let offer = NewOffer {
+        seller,
+        seller_temp,
+        seller_receive,
+        escrow_pda,
+        operator,
+        rent_payer,
+    };
+    let checked = validate_offer(program_id, &offer, &offer_data, &index_seed)?;
+    open_offer(
+        program_id,
+        &offer,
+        sys_program,
+        token_program,
+        offer_data,
+        index_seed,
+        checked,
+    )
+}
+
+pub fn create_limit_offer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    limit_order: LimitOrder,
+    index_seed: [u8; 32],
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let seller = next_account_info(accounts)?;
+    let seller_temp = next_account_info(accounts)?;
+    let seller_receive = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let token_mint = next_account_info(accounts)?;
+    let strike_mint = next_account_info(accounts)?;
+    let operator = next_optional_account(accounts, program_id);
+    let rent_payer = next_optional_account(accounts, program_id);
+    let config = next_account_info(accounts)?;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
     }
-    if !escrow_pda.try_data_is_empty()? {
-        return Err(ProgramError::AccountAlreadyInitialized);
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
     }
-    if pda != *escrow_pda.key {
-        msg!("INVALID PDA ACCOUNT");
+    check_offer_config(
+        program_id,
+        config,
+        &[limit_order.token_type, limit_order.strike_type],
+        accounts.as_slice(),
+    )?;
+    if *token_mint.key != limit_order.token_type
+        || *strike_mint.key != limit_order.strike_type
+        || !spl_token::check_id(token_mint.owner)
+        || !spl_token::check_id(strike_mint.owner)
+    {
+        msg!("INVALID MINT ACCOUNT");
         return Err(ProgramError::InvalidArgument);
     }
-    if temp_info.mint != offer_data.token_type {
-        msg!("INVALID TEMP TYPE");
+    let token_decimals = Mint::unpack(*token_mint.try_borrow_data()?)?.decimals;
+    let strike_decimals = Mint::unpack(*strike_mint.try_borrow_data()?)?.decimals;
+    let offer_data = limit_order.offer_data(token_decimals, strike_decimals)?;
+    if offer_data.strike_qty == 0 {
+        msg!("INVALID LIMIT PRICE");
         return Err(ProgramError::InvalidArgument);
     }
-    if temp_info.amount != offer_data.token_qty {
-        msg!("INVALID TEMP BALANCE");
-        return Err(ProgramError::InvalidArgument);
+    let offer = NewOffer {
+        seller,
+        seller_temp,
+        seller_receive,
+        escrow_pda,
+        operator,
+        rent_payer,
+    };
+    let checked = validate_offer(program_id, &offer, &offer_data, &index_seed)?;
+    open_offer(
+        program_id,
+        &offer,
+        sys_program,
+        token_program,
+        offer_data,
+        index_seed,
+        checked,
+    )
+}
+
+// bounded by transaction size, each entry adds 112 bytes of data (80 byte offer and 32 byte
+// index seed) and two 32 byte account keys. a full batch with a separate fee payer is 1153 of
+// the 1232 bytes a transaction may take, a fifth entry would not fit
+pub const MAX_CREATE_BATCH: usize = 4;
+
+pub fn create_many(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    offers: Vec<(OfferData, [u8; 32])>,
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let seller = next_account_info(accounts)?;
+    let funding = next_account_info(accounts)?;
+    let seller_receive = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
     }
-    if receive_info.mint != offer_data.strike_type {
-        msg!("INVALID RECEIVE ACCOUNT");
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if offers.is_empty() || offers.len() > MAX_CREATE_BATCH {
+        msg!("INVALID BATCH SIZE");
         return Err(ProgramError::InvalidArgument);
     }
+    let mut pairs = Vec::with_capacity(offers.len());
+    for _ in 0..offers.len() {
+        let seller_temp = next_account_info(accounts)?;
+        let escrow_pda = next_account_info(accounts)?;
+        pairs.push((seller_temp, escrow_pda));
+    }
+    let operator = next_optional_account(accounts, program_id);
+    let rent_payer = next_optional_account(accounts, program_id);
+    let config = next_account_info(accounts)?;
+    let mints: Vec<Pubkey> = offers
+        .iter()
+        .flat_map(|(offer_data, _)| [offer_data.token_type, offer_data.strike_type])
+        .collect();
+    check_offer_config(program_id, config, &mints, accounts.as_slice())?;
 
-    msg!("creating escrow PDA");
-
-    let create_ix = system_instruction::create_account(
-        seller.key,
-        escrow_pda.key,
-        min_rent,
-        EscrowPDA::LEN as u64,
-        program_id,
-    );
-    invoke_signed(
-        &create_ix,
-        &[seller.clone(), escrow_pda.clone(), sys_program.clone()],
-        &[&[&seed, &index_seed, &[bump]]],
-    )?;
-
-    msg!("transferring temp ownership to PDA...");
-    let transfer_ix = instruction::set_authority(
-        &spl_token::id(),
-        seller_temp.key,
-        Some(escrow_pda.key),
-        instruction::AuthorityType::AccountOwner,
-        seller.key,
-        &[seller.key],
-    )?;
-    invoke(
-        &transfer_ix,
-        &[seller_temp.clone(), seller.clone(), token_program.clone()],
-    )?;
+    for ((offer_data, index_seed), (seller_temp, escrow_pda)) in offers.into_iter().zip(pairs) {
+        msg!("funding seller temp...");
+        let fund_ix = instruction::transfer(
+            &spl_token::id(),
+            funding.key,
+            seller_temp.key,
+            seller.key,
+            &[seller.key],
+            offer_data.token_qty,
+        )?;
+        invoke(
+            &fund_ix,
+            &[
+                funding.clone(),
+                seller_temp.clone(),
+                seller.clone(),
+                token_program.clone(),
+            ],
+        )?;
 
-    msg!("updating PDA data...");
-    let escrow_data = EscrowPDA {
-        offer_data,
-        seller_main: seller.key.clone(),
-        seller_temp: seller_temp.key.clone(),
-        seller_receive: seller_receive.key.clone(),
-        index_seed,
-    };
-    escrow_data.pack_into_slice(*escrow_pda.data.borrow_mut());
+        let offer = NewOffer {
+            seller,
+            seller_temp,
+            seller_receive,
+            escrow_pda,
+            operator,
+            rent_payer,
+        };
+        let checked = validate_offer(program_id, &offer, &offer_data, &index_seed)?;
+        open_offer(
+            program_id,
+            &offer,
+            sys_program,
+            token_program,
+            offer_data,
+            index_seed,
+            checked,
+        )?;
+    }
     Ok(())
 }
 
-pub fn accept_offer(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+pub fn accept_offer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    expected: Option<OfferData>,
+) -> Result<(), ProgramError> {
+    let all_accounts = accounts;
     let accounts = &mut accounts.iter();
     let buyer = next_account_info(accounts)?;
     let strike_acc = next_account_info(accounts)?;
@@ -113,12 +247,140 @@ pub fn accept_offer(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(),
     let seller_receive = next_account_info(accounts)?;
     let sys_program = next_account_info(accounts)?;
     let token_program = next_account_info(accounts)?;
+    // the optional ata_program is followed by token_mint, strike_mint and seller_wallet
+    let ata = match next_optional_account(accounts, program_id) {
+        Some(ata_program) => Some((
+            ata_program,
+            next_account_info(accounts)?,
+            next_account_info(accounts)?,
+            next_optional_account(accounts, program_id).unwrap_or(seller),
+        )),
+        None => None,
+    };
+    let config = next_account_info(accounts)?;
+
+    if let Some((ata_program, token_mint, strike_mint, seller_wallet)) = ata {
+        if !spl_associated_token_account::check_id(ata_program.key) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        msg!("creating receive accounts...");
+        create_ata(
+            buyer,
+            buyer_receive,
+            buyer,
+            token_mint,
+            sys_program,
+            token_program,
+        )?;
+        create_ata(
+            buyer,
+            seller_receive,
+            seller_wallet,
+            strike_mint,
+            sys_program,
+            token_program,
+        )?;
+    }
+
+    let (escrow_data, bump) = check_accept(
+        program_id,
+        &AcceptAccounts {
+            buyer,
+            strike_acc,
+            buyer_receive,
+            escrow_pda,
+            seller,
+            seller_temp,
+            seller_receive,
+            sys_program,
+            token_program,
+            config,
+        },
+        expected,
+    )?;
+    let seed = escrow_data.offer_seed();
+
+    msg!("transferring asset to buyer");
+    transfer_from_pda(
+        seller_temp,
+        buyer_receive,
+        escrow_pda,
+        escrow_data.token_qty(),
+        &[&seed, &escrow_data.index_seed, &[bump]],
+    )?;
+
+    msg!("transferring strike to seller");
+    let strike_ix = instruction::transfer(
+        &spl_token::id(),
+        strike_acc.key,
+        seller_receive.key,
+        buyer.key,
+        &[buyer.key],
+        escrow_data.strike_qty(),
+    )?;
+    invoke(
+        &strike_ix,
+        &[strike_acc.clone(), seller_receive.clone(), buyer.clone()],
+    )?;
+
+    msg!("closing temp");
+    close_temp(
+        seller_temp,
+        seller,
+        escrow_pda,
+        &[&seed, &escrow_data.index_seed, &[bump]],
+    )?;
+    msg!("closing PDA");
+    close_pda(
+        escrow_pda,
+        rent_payer_account(&escrow_data, seller, all_accounts)?,
+    )?;
 
+    EscrowEvent::OfferAccepted {
+        escrow_pda: *escrow_pda.key,
+        seller: *seller.key,
+        buyer: *buyer.key,
+        buyer_receive: *buyer_receive.key,
+        offer_data: escrow_data.offer_data(),
+    }
+    .emit();
+    Ok(())
+}
+
+pub struct AcceptAccounts<'a, 'b> {
+    pub buyer: &'a AccountInfo<'b>,
+    pub strike_acc: &'a AccountInfo<'b>,
+    pub buyer_receive: &'a AccountInfo<'b>,
+    pub escrow_pda: &'a AccountInfo<'b>,
+    pub seller: &'a AccountInfo<'b>,
+    pub seller_temp: &'a AccountInfo<'b>,
+    pub seller_receive: &'a AccountInfo<'b>,
+    pub sys_program: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+}
+
+// every check accept_offer makes before moving funds, shared with the off-chain simulator
+pub fn check_accept(
+    program_id: &Pubkey,
+    accounts: &AcceptAccounts,
+    expected: Option<OfferData>,
+) -> Result<(EscrowView, u8), ProgramError> {
+    let AcceptAccounts {
+        buyer,
+        strike_acc,
+        buyer_receive,
+        escrow_pda,
+        seller,
+        seller_temp,
+        seller_receive,
+        sys_program,
+        token_program,
+        config,
+    } = *accounts;
     let token_info = Account::unpack_from_slice(*strike_acc.try_borrow_data()?)?;
     let receive_info = Account::unpack_from_slice(*buyer_receive.try_borrow_data()?)?;
-    let escrow_data = EscrowPDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
-    let seed = escrow_data.offer_data.get_seed();
-    let (_pda, bump) = Pubkey::find_program_address(&[&seed, &escrow_data.index_seed], program_id);
+    let escrow_data = EscrowView::read(*escrow_pda.try_borrow_data()?)?;
 
     msg!("asserting validity...");
     if !system_program::check_id(sys_program.key) {
@@ -127,18 +389,28 @@ pub fn accept_offer(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(),
     if !spl_token::check_id(token_program.key) {
         return Err(ProgramError::IncorrectProgramId);
     }
+    check_not_paused(program_id, config)?;
     if !buyer.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
-    if token_info.mint != escrow_data.offer_data.strike_type {
+    if escrow_pda.owner != program_id {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let bump = escrow_bump(program_id, escrow_pda, &escrow_data)?;
+    if expected.is_some_and(|expected| expected != escrow_data.offer_data()) {
+        msg!("OFFER TERMS MISMATCH");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if token_info.mint != escrow_data.strike_type {
         msg!("INCORRECT TOKEN ACCOUNT TYPE");
         return Err(ProgramError::InvalidArgument);
     }
-    if token_info.amount < escrow_data.offer_data.strike_qty {
+    if token_info.amount < escrow_data.strike_qty() {
         msg!("INCORRECT TOKEN ACCOUNT BALANCE");
         return Err(ProgramError::InvalidArgument);
     }
-    if receive_info.mint != escrow_data.offer_data.token_type {
+    if receive_info.mint != escrow_data.token_type {
         msg!("INCORRECT RECEIVE ACCOUNT TYPE");
         return Err(ProgramError::InvalidArgument);
     }
@@ -154,61 +426,11 @@ pub fn accept_offer(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(),
         msg!("INCORRECT SELLER_RECEIVE ACCOUNT");
         return Err(ProgramError::InvalidArgument);
     }
-
-    msg!("transferring asset to buyer");
-    let asset_ix = instruction::transfer(
-        &spl_token::id(),
-        seller_temp.key,
-        buyer_receive.key,
-        escrow_pda.key,
-        &[escrow_pda.key],
-        escrow_data.offer_data.token_qty,
-    )?;
-    invoke_signed(
-        &asset_ix,
-        &[
-            seller_temp.clone(),
-            buyer_receive.clone(),
-            escrow_pda.clone(),
-        ],
-        &[&[&seed, &escrow_data.index_seed, &[bump]]],
-    )?;
-
-    msg!("transferring strike to seller");
-    let strike_ix = instruction::transfer(
-        &spl_token::id(),
-        strike_acc.key,
-        seller_receive.key,
-        buyer.key,
-        &[buyer.key],
-        escrow_data.offer_data.strike_qty,
-    )?;
-    invoke(
-        &strike_ix,
-        &[strike_acc.clone(), seller_receive.clone(), buyer.clone()],
-    )?;
-
-    msg!("closing temp");
-    let close_ix = instruction::close_account(
-        &spl_token::id(),
-        seller_temp.key,
-        seller.key,
-        escrow_pda.key,
-        &[escrow_pda.key],
-    )?;
-    invoke_signed(
-        &close_ix,
-        &[seller_temp.clone(), seller.clone(), escrow_pda.clone()],
-        &[&[&seed, &escrow_data.index_seed, &[bump]]],
-    )?;
-    msg!("closing PDA");
-    *escrow_pda.data.borrow_mut() = &mut [];
-    **seller.try_borrow_mut_lamports()? += escrow_pda.try_lamports()?;
-    **escrow_pda.try_borrow_mut_lamports()? = 0;
-    Ok(())
+    Ok((escrow_data, bump))
 }
 
 pub fn cancel_offer(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let all_accounts = accounts;
     let accounts = &mut accounts.iter();
     let seller = next_account_info(accounts)?;
     let seller_temp = next_account_info(accounts)?;
@@ -216,10 +438,81 @@ pub fn cancel_offer(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(),
     let escrow_pda = next_account_info(accounts)?;
     let sys_program = next_account_info(accounts)?;
     let token_program = next_account_info(accounts)?;
+    let operator = next_optional_account(accounts, program_id);
+
+    let (escrow_data, bump) = check_cancel_offer(
+        program_id,
+        &CancelAccounts {
+            seller,
+            seller_temp,
+            seller_receive,
+            escrow_pda,
+            sys_program,
+            token_program,
+        },
+        operator,
+    )?;
+    cancel_escrow(
+        seller,
+        seller_temp,
+        escrow_pda,
+        &escrow_data,
+        bump,
+        all_accounts,
+    )
+}
 
-    let escrow_data = EscrowPDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
-    let seed = escrow_data.offer_data.get_seed();
-    let (_pda, bump) = Pubkey::find_program_address(&[&seed, &escrow_data.index_seed], program_id);
+pub struct CancelAccounts<'a, 'b> {
+    pub seller: &'a AccountInfo<'b>,
+    pub seller_temp: &'a AccountInfo<'b>,
+    pub seller_receive: &'a AccountInfo<'b>,
+    pub escrow_pda: &'a AccountInfo<'b>,
+    pub sys_program: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+}
+
+// every check cancel_offer makes before returning the temp, shared with the off-chain simulator
+pub fn check_cancel_offer(
+    program_id: &Pubkey,
+    accounts: &CancelAccounts,
+    operator: Option<&AccountInfo>,
+) -> Result<(EscrowView, u8), ProgramError> {
+    msg!("asserting validity...");
+    if !system_program::check_id(accounts.sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(accounts.token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let (escrow_data, bump) = check_cancel(
+        program_id,
+        accounts.seller,
+        accounts.seller_temp,
+        accounts.escrow_pda,
+        operator,
+    )?;
+    if *accounts.seller_receive.key != escrow_data.seller_receive {
+        msg!("INCORRECT SELLER_RECEIVE ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok((escrow_data, bump))
+}
+
+// compute budget caps how many set_authority CPIs fit in a single instruction. natively a cancel
+// costs under 4.5k units of CPIs, syscalls and derivations (tests/bench.rs), 8 leaves 25k per cancel
+// under the default limit until an sbf run of a full batch measures the rest
+pub const MAX_CANCEL_BATCH: usize = 8;
+
+pub fn cancel_many(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    count: u8,
+) -> Result<(), ProgramError> {
+    let all_accounts = accounts;
+    let accounts = &mut accounts.iter();
+    let seller = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
 
     msg!("asserting validity...");
     if !system_program::check_id(sys_program.key) {
@@ -228,39 +521,2302 @@ pub fn cancel_offer(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(),
     if !spl_token::check_id(token_program.key) {
         return Err(ProgramError::IncorrectProgramId);
     }
-    if *seller.key != escrow_data.seller_main {
-        msg!("INCORRECT SELLER ACCOUNT");
+    if count == 0 || count as usize > MAX_CANCEL_BATCH {
+        msg!("INVALID BATCH SIZE");
         return Err(ProgramError::InvalidArgument);
     }
-    if *seller_temp.key != escrow_data.seller_temp {
-        msg!("INCORRECT SELLER_TEMP ACCOUNT");
-        return Err(ProgramError::InvalidArgument);
+    let mut pairs = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let escrow_pda = next_account_info(accounts)?;
+        let seller_temp = next_account_info(accounts)?;
+        pairs.push((escrow_pda, seller_temp));
     }
-    if *seller_receive.key != escrow_data.seller_receive {
-        msg!("INCORRECT SELLER_RECEIVE ACCOUNT");
+    let operator = next_optional_account(accounts, program_id);
+
+    for (escrow_pda, seller_temp) in pairs {
+        let (escrow_data, bump) =
+            check_cancel(program_id, seller, seller_temp, escrow_pda, operator)?;
+        cancel_escrow(
+            seller,
+            seller_temp,
+            escrow_pda,
+            &escrow_data,
+            bump,
+            all_accounts,
+        )?;
+    }
+    Ok(())
+}
+
+pub fn set_operator(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let seller = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let operator = next_optional_account(accounts, program_id);
+
+    let escrow_data = EscrowView::read(*escrow_pda.try_borrow_data()?)?;
+
+    msg!("asserting validity...");
+    escrow_bump(program_id, escrow_pda, &escrow_data)?;
+    if *seller.key != escrow_data.seller_main {
+        msg!("INCORRECT SELLER ACCOUNT");
         return Err(ProgramError::InvalidArgument);
     }
     if !seller.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
-    msg!("transferring temp ownership back to seller");
-    let ix = instruction::set_authority(
-        &spl_token::id(),
-        seller_temp.key,
-        Some(seller.key),
-        instruction::AuthorityType::AccountOwner,
-        escrow_pda.key,
-        &[escrow_pda.key],
-    )?;
-    invoke_signed(
-        &ix,
-        &[seller_temp.clone(), seller.clone(), escrow_pda.clone()],
-        &[&[&seed, &escrow_data.index_seed, &[bump]]],
-    )?;
+    if escrow_pda.data_len() == EscrowPDA::V1_LEN {
+        msg!("V1 ESCROW ACCOUNT HAS NO OPERATOR FIELD");
+        return Err(ProgramError::InvalidArgument);
+    }
 
-    msg!("closing PDA");
+    msg!("updating operator");
+    let operator = operator.map(|operator| *operator.key);
+    let mut data = escrow_pda.try_borrow_mut_data()?;
+    if data.len() == EscrowPDA::LEN {
+        EscrowView::load_mut(*data)?.set_operator(operator);
+    } else {
+        // v2 accounts are too short to view, and are repacked instead
+        EscrowPDA {
+            operator,
+            ..EscrowPDA::from(&escrow_data)
+        }
+        .pack_into_slice(*data);
+    }
+    Ok(())
+}
+
+// moves an offer to the escrow address of its new strike_qty. the temp, seller_receive and every
+// other term are kept, so an operator can reprice but not redirect proceeds. older format
+// escrows are rewritten in the current one, with the extra rent paid by whoever signed
+pub fn amend_offer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    strike_qty: u64,
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let seller = next_account_info(accounts)?;
+    let seller_temp = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let new_escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let operator = next_optional_account(accounts, program_id);
+    let config = next_account_info(accounts)?;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_not_paused(program_id, config)?;
+    let (escrow_data, bump) = check_cancel(program_id, seller, seller_temp, escrow_pda, operator)?;
+    let offer_data = OfferData {
+        strike_qty,
+        ..escrow_data.offer_data()
+    };
+    let new_seed = offer_data.get_seed();
+    let (pda, new_bump) = find_address(&[&new_seed, &escrow_data.index_seed], program_id);
+    if pda != *new_escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !new_escrow_pda.try_data_is_empty()? {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    let funder = match operator {
+        Some(operator) if !seller.is_signer => operator,
+        _ => seller,
+    };
+
+    msg!("transferring temp ownership to new PDA");
+    let seed = escrow_data.offer_seed();
+    let transfer_ix = instruction::set_authority(
+        &spl_token::id(),
+        seller_temp.key,
+        Some(new_escrow_pda.key),
+        instruction::AuthorityType::AccountOwner,
+        escrow_pda.key,
+        &[escrow_pda.key],
+    )?;
+    invoke_signed(
+        &transfer_ix,
+        &[seller_temp.clone(), escrow_pda.clone()],
+        &[&[&seed, &escrow_data.index_seed, &[bump]]],
+    )?;
+
+    msg!("moving PDA");
+    let min_rent = rent::Rent::get()?.minimum_balance(EscrowPDA::LEN);
+    let shortfall = min_rent.saturating_sub(escrow_pda.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(funder.key, new_escrow_pda.key, shortfall),
+            &[funder.clone(), new_escrow_pda.clone(), sys_program.clone()],
+        )?;
+    }
+    let new_seeds: &[&[u8]] = &[&new_seed, &escrow_data.index_seed, &[new_bump]];
+    invoke_signed(
+        &system_instruction::allocate(new_escrow_pda.key, EscrowPDA::LEN as u64),
+        &[new_escrow_pda.clone(), sys_program.clone()],
+        &[new_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(new_escrow_pda.key, program_id),
+        &[new_escrow_pda.clone(), sys_program.clone()],
+        &[new_seeds],
+    )?;
+    let amended = EscrowPDA {
+        offer_data,
+        bump: new_bump,
+        ..EscrowPDA::from(&escrow_data)
+    };
+    amended.pack_into_slice(*new_escrow_pda.data.borrow_mut());
+    // the old PDA's rent moves over after the last CPI
+    close_pda(escrow_pda, new_escrow_pda)?;
+
+    // indexers see the old offer cancelled and the amended one created
+    EscrowEvent::OfferCancelled {
+        escrow_pda: *escrow_pda.key,
+        seller: *seller.key,
+        offer_data: escrow_data.offer_data(),
+    }
+    .emit();
+    EscrowEvent::OfferCreated {
+        escrow_pda: *new_escrow_pda.key,
+        seller: *seller.key,
+        seller_temp: *seller_temp.key,
+        seller_receive: amended.seller_receive,
+        offer_data,
+    }
+    .emit();
+    Ok(())
+}
+
+pub fn create_htlc(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    htlc_data: HtlcData,
+    index_seed: [u8; 32],
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let seller = next_account_info(accounts)?;
+    let seller_temp = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let temp_info = Account::unpack_from_slice(*seller_temp.try_borrow_data()?)?;
+    let seed = htlc_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &index_seed], program_id);
+    let min_rent = rent::Rent::get()?.minimum_balance(HtlcPDA::LEN);
+    let now = Clock::get()?.unix_timestamp;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_offer_config(
+        program_id,
+        config,
+        &[htlc_data.token_type],
+        accounts.as_slice(),
+    )?;
+    if !seller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !escrow_pda.try_data_is_empty()? {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if temp_info.mint != htlc_data.token_type {
+        msg!("INVALID TEMP TYPE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if temp_info.amount != htlc_data.token_qty {
+        msg!("INVALID TEMP BALANCE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if htlc_data.expiry <= now {
+        msg!("INVALID EXPIRY");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("creating HTLC PDA");
+    let create_ix = system_instruction::create_account(
+        seller.key,
+        escrow_pda.key,
+        min_rent,
+        HtlcPDA::LEN as u64,
+        program_id,
+    );
+    invoke_signed(
+        &create_ix,
+        &[seller.clone(), escrow_pda.clone(), sys_program.clone()],
+        &[&[&seed, &index_seed, &[bump]]],
+    )?;
+
+    msg!("transferring temp ownership to PDA...");
+    let transfer_ix = instruction::set_authority(
+        &spl_token::id(),
+        seller_temp.key,
+        Some(escrow_pda.key),
+        instruction::AuthorityType::AccountOwner,
+        seller.key,
+        &[seller.key],
+    )?;
+    invoke(
+        &transfer_ix,
+        &[seller_temp.clone(), seller.clone(), token_program.clone()],
+    )?;
+
+    msg!("updating PDA data...");
+    let htlc = HtlcPDA {
+        htlc_data,
+        seller_main: *seller.key,
+        seller_temp: *seller_temp.key,
+        index_seed,
+    };
+    htlc.pack_into_slice(*escrow_pda.data.borrow_mut());
+    Ok(())
+}
+
+pub fn claim_htlc(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    preimage: [u8; 32],
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let buyer = next_account_info(accounts)?;
+    let buyer_receive = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let seller = next_account_info(accounts)?;
+    let seller_temp = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let receive_info = Account::unpack_from_slice(*buyer_receive.try_borrow_data()?)?;
+    let htlc = HtlcPDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
+    let seed = htlc.htlc_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &htlc.index_seed], program_id);
+    let now = Clock::get()?.unix_timestamp;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_not_paused(program_id, config)?;
+    if !buyer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *buyer.key != htlc.htlc_data.buyer {
+        msg!("INCORRECT BUYER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if receive_info.mint != htlc.htlc_data.token_type {
+        msg!("INCORRECT RECEIVE ACCOUNT TYPE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *seller.key != htlc.seller_main {
+        msg!("INCORRECT SELLER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *seller_temp.key != htlc.seller_temp {
+        msg!("INCORRECT SELLER_TEMP ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if now >= htlc.htlc_data.expiry {
+        msg!("HTLC EXPIRED");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if get_seed(&preimage) != htlc.htlc_data.hashlock {
+        msg!("INVALID PREIMAGE");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("transferring asset to buyer");
+    transfer_from_pda(
+        seller_temp,
+        buyer_receive,
+        escrow_pda,
+        htlc.htlc_data.token_qty,
+        &[&seed, &htlc.index_seed, &[bump]],
+    )?;
+
+    msg!("closing temp");
+    close_temp(
+        seller_temp,
+        seller,
+        escrow_pda,
+        &[&seed, &htlc.index_seed, &[bump]],
+    )?;
+    msg!("closing PDA");
+    close_pda(escrow_pda, seller)?;
+    Ok(())
+}
+
+pub fn refund_htlc(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let seller = next_account_info(accounts)?;
+    let seller_temp = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+
+    let htlc = HtlcPDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
+    let seed = htlc.htlc_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &htlc.index_seed], program_id);
+    let now = Clock::get()?.unix_timestamp;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *seller.key != htlc.seller_main {
+        msg!("INCORRECT SELLER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *seller_temp.key != htlc.seller_temp {
+        msg!("INCORRECT SELLER_TEMP ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !seller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if now < htlc.htlc_data.expiry {
+        msg!("HTLC NOT EXPIRED");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("transferring temp ownership back to seller");
+    return_temp(
+        seller_temp,
+        seller,
+        escrow_pda,
+        &[&seed, &htlc.index_seed, &[bump]],
+    )?;
+
+    msg!("closing PDA");
+    close_pda(escrow_pda, seller)?;
+    Ok(())
+}
+
+pub fn create_arbiter(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    arbiter_data: ArbiterData,
+    index_seed: [u8; 32],
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let buyer = next_account_info(accounts)?;
+    let buyer_temp = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let temp_info = Account::unpack_from_slice(*buyer_temp.try_borrow_data()?)?;
+    let seed = arbiter_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &index_seed], program_id);
+    let min_rent = rent::Rent::get()?.minimum_balance(ArbiterPDA::LEN);
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_offer_config(
+        program_id,
+        config,
+        &[arbiter_data.token_type],
+        accounts.as_slice(),
+    )?;
+    if !buyer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !escrow_pda.try_data_is_empty()? {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if temp_info.mint != arbiter_data.token_type {
+        msg!("INVALID TEMP TYPE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if temp_info.amount != arbiter_data.token_qty {
+        msg!("INVALID TEMP BALANCE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if arbiter_data.arbiter == *buyer.key || arbiter_data.arbiter == arbiter_data.seller {
+        msg!("INVALID ARBITER");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("creating arbiter PDA");
+    let create_ix = system_instruction::create_account(
+        buyer.key,
+        escrow_pda.key,
+        min_rent,
+        ArbiterPDA::LEN as u64,
+        program_id,
+    );
+    invoke_signed(
+        &create_ix,
+        &[buyer.clone(), escrow_pda.clone(), sys_program.clone()],
+        &[&[&seed, &index_seed, &[bump]]],
+    )?;
+
+    msg!("transferring temp ownership to PDA...");
+    let transfer_ix = instruction::set_authority(
+        &spl_token::id(),
+        buyer_temp.key,
+        Some(escrow_pda.key),
+        instruction::AuthorityType::AccountOwner,
+        buyer.key,
+        &[buyer.key],
+    )?;
+    invoke(
+        &transfer_ix,
+        &[buyer_temp.clone(), buyer.clone(), token_program.clone()],
+    )?;
+
+    msg!("updating PDA data...");
+    let arbiter = ArbiterPDA {
+        arbiter_data,
+        buyer_main: *buyer.key,
+        buyer_temp: *buyer_temp.key,
+        index_seed,
+        disputed: false,
+    };
+    arbiter.pack_into_slice(*escrow_pda.data.borrow_mut());
+    Ok(())
+}
+
+pub fn release_arbiter(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let buyer = next_account_info(accounts)?;
+    let buyer_temp = next_account_info(accounts)?;
+    let seller_receive = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let receive_info = Account::unpack_from_slice(*seller_receive.try_borrow_data()?)?;
+    let arbiter = ArbiterPDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
+    let seed = arbiter.arbiter_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &arbiter.index_seed], program_id);
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_not_paused(program_id, config)?;
+    if !buyer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *buyer.key != arbiter.buyer_main {
+        msg!("INCORRECT BUYER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *buyer_temp.key != arbiter.buyer_temp {
+        msg!("INCORRECT BUYER_TEMP ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if receive_info.mint != arbiter.arbiter_data.token_type
+        || receive_info.owner != arbiter.arbiter_data.seller
+    {
+        msg!("INCORRECT SELLER_RECEIVE ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let signer_seeds: &[&[u8]] = &[&seed, &arbiter.index_seed, &[bump]];
+    msg!("transferring deposit to seller");
+    transfer_from_pda(
+        buyer_temp,
+        seller_receive,
+        escrow_pda,
+        arbiter.arbiter_data.token_qty,
+        signer_seeds,
+    )?;
+
+    msg!("closing temp");
+    close_temp(buyer_temp, buyer, escrow_pda, signer_seeds)?;
+    msg!("closing PDA");
+    close_pda(escrow_pda, buyer)?;
+    Ok(())
+}
+
+pub fn refund_arbiter(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let seller = next_account_info(accounts)?;
+    let buyer = next_account_info(accounts)?;
+    let buyer_temp = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+
+    let arbiter = ArbiterPDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
+    let seed = arbiter.arbiter_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &arbiter.index_seed], program_id);
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !seller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *seller.key != arbiter.arbiter_data.seller {
+        msg!("INCORRECT SELLER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *buyer.key != arbiter.buyer_main {
+        msg!("INCORRECT BUYER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *buyer_temp.key != arbiter.buyer_temp {
+        msg!("INCORRECT BUYER_TEMP ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("transferring temp ownership back to buyer");
+    return_temp(
+        buyer_temp,
+        buyer,
+        escrow_pda,
+        &[&seed, &arbiter.index_seed, &[bump]],
+    )?;
+
+    msg!("closing PDA");
+    close_pda(escrow_pda, buyer)?;
+    Ok(())
+}
+
+pub fn dispute_arbiter(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let party = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+
+    let mut arbiter = ArbiterPDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
+    let seed = arbiter.arbiter_data.get_seed();
+    let (pda, _bump) = find_address(&[&seed, &arbiter.index_seed], program_id);
+
+    msg!("asserting validity...");
+    if !party.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *party.key != arbiter.buyer_main && *party.key != arbiter.arbiter_data.seller {
+        msg!("INCORRECT PARTY ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if arbiter.disputed {
+        msg!("ALREADY DISPUTED");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("marking escrow disputed");
+    arbiter.disputed = true;
+    arbiter.pack_into_slice(*escrow_pda.data.borrow_mut());
+    Ok(())
+}
+
+pub fn resolve_arbiter(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    seller_bps: u16,
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let arbiter_main = next_account_info(accounts)?;
+    let buyer = next_account_info(accounts)?;
+    let buyer_receive = next_account_info(accounts)?;
+    let seller_receive = next_account_info(accounts)?;
+    let buyer_temp = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let buyer_info = Account::unpack_from_slice(*buyer_receive.try_borrow_data()?)?;
+    let seller_info = Account::unpack_from_slice(*seller_receive.try_borrow_data()?)?;
+    let arbiter = ArbiterPDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
+    let seed = arbiter.arbiter_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &arbiter.index_seed], program_id);
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_not_paused(program_id, config)?;
+    if !arbiter_main.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *arbiter_main.key != arbiter.arbiter_data.arbiter {
+        msg!("INCORRECT ARBITER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !arbiter.disputed {
+        msg!("ESCROW NOT DISPUTED");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if seller_bps > 10_000 {
+        msg!("INVALID SELLER SHARE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *buyer.key != arbiter.buyer_main {
+        msg!("INCORRECT BUYER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *buyer_temp.key != arbiter.buyer_temp {
+        msg!("INCORRECT BUYER_TEMP ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if buyer_info.mint != arbiter.arbiter_data.token_type || buyer_info.owner != arbiter.buyer_main
+    {
+        msg!("INCORRECT BUYER_RECEIVE ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if seller_info.mint != arbiter.arbiter_data.token_type
+        || seller_info.owner != arbiter.arbiter_data.seller
+    {
+        msg!("INCORRECT SELLER_RECEIVE ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let seller_qty = math::mul_div(
+        arbiter.arbiter_data.token_qty,
+        seller_bps as u64,
+        10_000,
+        Rounding::Down,
+    )?;
+    let buyer_qty = math::checked_sub(arbiter.arbiter_data.token_qty, seller_qty)?;
+    let signer_seeds: &[&[u8]] = &[&seed, &arbiter.index_seed, &[bump]];
+
+    if seller_qty > 0 {
+        msg!("transferring seller share");
+        transfer_from_pda(
+            buyer_temp,
+            seller_receive,
+            escrow_pda,
+            seller_qty,
+            signer_seeds,
+        )?;
+    }
+    if buyer_qty > 0 {
+        msg!("transferring buyer share");
+        transfer_from_pda(
+            buyer_temp,
+            buyer_receive,
+            escrow_pda,
+            buyer_qty,
+            signer_seeds,
+        )?;
+    }
+
+    msg!("closing temp");
+    close_temp(buyer_temp, buyer, escrow_pda, signer_seeds)?;
+    msg!("closing PDA");
+    close_pda(escrow_pda, buyer)?;
+    Ok(())
+}
+
+pub fn create_milestone(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    milestone_data: MilestoneData,
+    index_seed: [u8; 32],
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let payer = next_account_info(accounts)?;
+    let payer_temp = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let temp_info = Account::unpack_from_slice(*payer_temp.try_borrow_data()?)?;
+    let seed = milestone_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &index_seed], program_id);
+    let count = milestone_data.milestones.len();
+    let pda_len = MilestonePDA::len(count);
+    let min_rent = rent::Rent::get()?.minimum_balance(pda_len);
+    let now = Clock::get()?.unix_timestamp;
+    let total = milestone_data
+        .milestones
+        .iter()
+        .try_fold(0u64, |total, m| math::checked_add(total, m.amount))?;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_offer_config(
+        program_id,
+        config,
+        &[milestone_data.token_type],
+        accounts.as_slice(),
+    )?;
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !escrow_pda.try_data_is_empty()? {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if count == 0 || count > MilestoneData::MAX_MILESTONES {
+        msg!("INVALID MILESTONE COUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if milestone_data.milestones.iter().any(|m| m.amount == 0) {
+        msg!("INVALID MILESTONE AMOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if milestone_data
+        .milestones
+        .iter()
+        .any(|m| m.deadline.is_some_and(|deadline| deadline <= now))
+    {
+        msg!("INVALID MILESTONE DEADLINE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if temp_info.mint != milestone_data.token_type {
+        msg!("INVALID TEMP TYPE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if temp_info.amount != total {
+        msg!("INVALID TEMP BALANCE");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("creating milestone PDA");
+    let create_ix = system_instruction::create_account(
+        payer.key,
+        escrow_pda.key,
+        min_rent,
+        pda_len as u64,
+        program_id,
+    );
+    invoke_signed(
+        &create_ix,
+        &[payer.clone(), escrow_pda.clone(), sys_program.clone()],
+        &[&[&seed, &index_seed, &[bump]]],
+    )?;
+
+    msg!("transferring temp ownership to PDA...");
+    let transfer_ix = instruction::set_authority(
+        &spl_token::id(),
+        payer_temp.key,
+        Some(escrow_pda.key),
+        instruction::AuthorityType::AccountOwner,
+        payer.key,
+        &[payer.key],
+    )?;
+    invoke(
+        &transfer_ix,
+        &[payer_temp.clone(), payer.clone(), token_program.clone()],
+    )?;
+
+    msg!("updating PDA data...");
+    let milestones = MilestonePDA {
+        milestone_data,
+        payer_main: *payer.key,
+        payer_temp: *payer_temp.key,
+        index_seed,
+        released: vec![false; count],
+    };
+    milestones.pack_into_slice(*escrow_pda.data.borrow_mut());
+    Ok(())
+}
+
+pub fn approve_milestone(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    milestone: u8,
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let payer = next_account_info(accounts)?;
+    let payer_temp = next_account_info(accounts)?;
+    let payee_receive = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let receive_info = Account::unpack_from_slice(*payee_receive.try_borrow_data()?)?;
+    let mut milestones = MilestonePDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
+    let seed = milestones.milestone_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &milestones.index_seed], program_id);
+    let index = milestone as usize;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_not_paused(program_id, config)?;
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *payer.key != milestones.payer_main {
+        msg!("INCORRECT PAYER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *payer_temp.key != milestones.payer_temp {
+        msg!("INCORRECT PAYER_TEMP ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if receive_info.mint != milestones.milestone_data.token_type
+        || receive_info.owner != milestones.milestone_data.payee
+    {
+        msg!("INCORRECT PAYEE_RECEIVE ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if index >= milestones.released.len() {
+        msg!("INVALID MILESTONE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if milestones.released[index] {
+        msg!("MILESTONE ALREADY RELEASED");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let signer_seeds: &[&[u8]] = &[&seed, &milestones.index_seed, &[bump]];
+    msg!("releasing milestone to payee");
+    transfer_from_pda(
+        payer_temp,
+        payee_receive,
+        escrow_pda,
+        milestones.milestone_data.milestones[index].amount,
+        signer_seeds,
+    )?;
+    milestones.released[index] = true;
+
+    if milestones.released.iter().all(|released| *released) {
+        msg!("closing temp");
+        close_temp(payer_temp, payer, escrow_pda, signer_seeds)?;
+        msg!("closing PDA");
+        close_pda(escrow_pda, payer)?;
+    } else {
+        msg!("updating PDA data...");
+        milestones.pack_into_slice(*escrow_pda.data.borrow_mut());
+    }
+    Ok(())
+}
+
+pub fn refund_milestone(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let payer = next_account_info(accounts)?;
+    let payer_temp = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+
+    let milestones = MilestonePDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
+    let seed = milestones.milestone_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &milestones.index_seed], program_id);
+    let final_deadline = milestones
+        .milestone_data
+        .milestones
+        .iter()
+        .filter_map(|m| m.deadline)
+        .max();
+    let now = Clock::get()?.unix_timestamp;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *payer.key != milestones.payer_main {
+        msg!("INCORRECT PAYER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *payer_temp.key != milestones.payer_temp {
+        msg!("INCORRECT PAYER_TEMP ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    match final_deadline {
+        None => {
+            msg!("NO MILESTONE DEADLINE");
+            return Err(ProgramError::InvalidArgument);
+        }
+        Some(deadline) if now < deadline => {
+            msg!("FINAL DEADLINE NOT PASSED");
+            return Err(ProgramError::InvalidArgument);
+        }
+        _ => {}
+    }
+
+    msg!("transferring temp ownership back to payer");
+    return_temp(
+        payer_temp,
+        payer,
+        escrow_pda,
+        &[&seed, &milestones.index_seed, &[bump]],
+    )?;
+
+    msg!("closing PDA");
+    close_pda(escrow_pda, payer)?;
+    Ok(())
+}
+
+pub fn create_vesting(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    vesting_data: VestingData,
+    index_seed: [u8; 32],
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let grantor = next_account_info(accounts)?;
+    let grantor_temp = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let temp_info = Account::unpack_from_slice(*grantor_temp.try_borrow_data()?)?;
+    let seed = vesting_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &index_seed], program_id);
+    let min_rent = rent::Rent::get()?.minimum_balance(VestingPDA::LEN);
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_offer_config(
+        program_id,
+        config,
+        &[vesting_data.token_type],
+        accounts.as_slice(),
+    )?;
+    if !grantor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !escrow_pda.try_data_is_empty()? {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if temp_info.mint != vesting_data.token_type {
+        msg!("INVALID TEMP TYPE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if vesting_data.token_qty == 0 || temp_info.amount != vesting_data.token_qty {
+        msg!("INVALID TEMP BALANCE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    // end - start must also fit a u64 for vested_amount
+    if vesting_data.start >= vesting_data.end
+        || math::elapsed(vesting_data.start, vesting_data.end).is_err()
+        || vesting_data.cliff < vesting_data.start
+        || vesting_data.cliff > vesting_data.end
+    {
+        msg!("INVALID VESTING SCHEDULE");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("creating vesting PDA");
+    let create_ix = system_instruction::create_account(
+        grantor.key,
+        escrow_pda.key,
+        min_rent,
+        VestingPDA::LEN as u64,
+        program_id,
+    );
+    invoke_signed(
+        &create_ix,
+        &[grantor.clone(), escrow_pda.clone(), sys_program.clone()],
+        &[&[&seed, &index_seed, &[bump]]],
+    )?;
+
+    msg!("transferring temp ownership to PDA...");
+    let transfer_ix = instruction::set_authority(
+        &spl_token::id(),
+        grantor_temp.key,
+        Some(escrow_pda.key),
+        instruction::AuthorityType::AccountOwner,
+        grantor.key,
+        &[grantor.key],
+    )?;
+    invoke(
+        &transfer_ix,
+        &[grantor_temp.clone(), grantor.clone(), token_program.clone()],
+    )?;
+
+    msg!("updating PDA data...");
+    let vesting = VestingPDA {
+        vesting_data,
+        grantor_main: *grantor.key,
+        grantor_temp: *grantor_temp.key,
+        index_seed,
+        withdrawn: 0,
+    };
+    vesting.pack_into_slice(*escrow_pda.data.borrow_mut());
+    Ok(())
+}
+
+pub fn withdraw_vesting(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let beneficiary = next_account_info(accounts)?;
+    let beneficiary_receive = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let grantor = next_account_info(accounts)?;
+    let grantor_temp = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let receive_info = Account::unpack_from_slice(*beneficiary_receive.try_borrow_data()?)?;
+    let mut vesting = VestingPDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
+    let seed = vesting.vesting_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &vesting.index_seed], program_id);
+    let now = Clock::get()?.unix_timestamp;
+    let amount = math::checked_sub(vesting.vesting_data.vested_amount(now)?, vesting.withdrawn)?;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_not_paused(program_id, config)?;
+    if !beneficiary.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *beneficiary.key != vesting.vesting_data.beneficiary {
+        msg!("INCORRECT BENEFICIARY ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if receive_info.mint != vesting.vesting_data.token_type {
+        msg!("INCORRECT RECEIVE ACCOUNT TYPE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *grantor.key != vesting.grantor_main {
+        msg!("INCORRECT GRANTOR ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *grantor_temp.key != vesting.grantor_temp {
+        msg!("INCORRECT GRANTOR_TEMP ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if amount == 0 {
+        msg!("NOTHING VESTED");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let signer_seeds: &[&[u8]] = &[&seed, &vesting.index_seed, &[bump]];
+    msg!("transferring vested tokens to beneficiary");
+    transfer_from_pda(
+        grantor_temp,
+        beneficiary_receive,
+        escrow_pda,
+        amount,
+        signer_seeds,
+    )?;
+    vesting.withdrawn = math::checked_add(vesting.withdrawn, amount)?;
+
+    if vesting.withdrawn == vesting.vesting_data.token_qty {
+        msg!("closing temp");
+        close_temp(grantor_temp, grantor, escrow_pda, signer_seeds)?;
+        msg!("closing PDA");
+        close_pda(escrow_pda, grantor)?;
+    } else {
+        msg!("updating PDA data...");
+        vesting.pack_into_slice(*escrow_pda.data.borrow_mut());
+    }
+    Ok(())
+}
+
+pub fn create_multisig(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    multisig_data: MultisigData,
+    index_seed: [u8; 32],
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let payer = next_account_info(accounts)?;
+    let multisig_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+
+    let signers = &multisig_data.signers;
+    // checked before hashing, the packed format has room for MAX_SIGNERS only
+    if signers.len() > MultisigData::MAX_SIGNERS {
+        msg!("TOO MANY SIGNERS");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let seed = multisig_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &index_seed], program_id);
+    let min_rent = rent::Rent::get()?.minimum_balance(MultisigPDA::LEN);
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !multisig_pda.try_data_is_empty()? {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if pda != *multisig_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if multisig_data.m == 0 || multisig_data.m as usize > signers.len() {
+        msg!("INVALID SIGNER THRESHOLD");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if (1..signers.len()).any(|i| signers[..i].contains(&signers[i])) {
+        msg!("DUPLICATE SIGNER");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("creating multisig PDA");
+    let create_ix = system_instruction::create_account(
+        payer.key,
+        multisig_pda.key,
+        min_rent,
+        MultisigPDA::LEN as u64,
+        program_id,
+    );
+    invoke_signed(
+        &create_ix,
+        &[payer.clone(), multisig_pda.clone(), sys_program.clone()],
+        &[&[&seed, &index_seed, &[bump]]],
+    )?;
+
+    msg!("updating PDA data...");
+    let multisig = MultisigPDA {
+        multisig_data,
+        index_seed,
+        pending: [0; 32],
+        approvals: 0,
+    };
+    multisig.pack_into_slice(*multisig_pda.data.borrow_mut());
+    Ok(())
+}
+
+pub fn approve_multisig(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    action_hash: [u8; 32],
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let signer = next_account_info(accounts)?;
+    let multisig_pda = next_account_info(accounts)?;
+
+    let mut multisig = MultisigPDA::unpack_from_slice(*multisig_pda.try_borrow_data()?)?;
+    let seed = multisig.multisig_data.get_seed();
+    let (pda, _bump) = find_address(&[&seed, &multisig.index_seed], program_id);
+    let index = multisig
+        .multisig_data
+        .signers
+        .iter()
+        .position(|s| s == signer.key);
+
+    msg!("asserting validity...");
+    if !signer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if pda != *multisig_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let index = match index {
+        Some(index) => index,
+        None => {
+            msg!("NOT A MULTISIG SIGNER");
+            return Err(ProgramError::InvalidArgument);
+        }
+    };
+
+    // a signer may replace an action only they have approved, other approvals stand until it is
+    // executed
+    if multisig.pending != action_hash {
+        if multisig.approvals & !(1 << index) != 0 {
+            msg!("ANOTHER ACTION IS PENDING");
+            return Err(ProgramError::InvalidArgument);
+        }
+        msg!("replacing pending action");
+        multisig.pending = action_hash;
+        multisig.approvals = 0;
+    }
+    msg!("recording approval");
+    multisig.approvals |= 1 << index;
+    multisig.pack_into_slice(*multisig_pda.data.borrow_mut());
+    Ok(())
+}
+
+pub fn execute_multisig(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: Vec<u8>,
+) -> Result<(), ProgramError> {
+    let account_infos = accounts;
+    let accounts = &mut accounts.iter();
+    let multisig_pda = next_account_info(accounts)?;
+    let authority = next_account_info(accounts)?;
+    let target_program = next_account_info(accounts)?;
+
+    let mut multisig = MultisigPDA::unpack_from_slice(*multisig_pda.try_borrow_data()?)?;
+    let seed = multisig.multisig_data.get_seed();
+    let (pda, _bump) = find_address(&[&seed, &multisig.index_seed], program_id);
+    let (authority_key, authority_bump) = MultisigPDA::authority(multisig_pda.key, program_id);
+    let ix = Instruction {
+        program_id: *target_program.key,
+        accounts: accounts
+            .map(|a| AccountMeta {
+                pubkey: *a.key,
+                is_signer: a.is_signer || *a.key == authority_key,
+                is_writable: a.is_writable,
+            })
+            .collect(),
+        data,
+    };
+
+    msg!("asserting validity...");
+    if pda != *multisig_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if authority_key != *authority.key {
+        msg!("INCORRECT AUTHORITY ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if multisig.pending == [0; 32] || multisig.pending != MultisigPDA::action_hash(&ix) {
+        msg!("ACTION NOT PENDING");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if multisig.approvals.count_ones() < multisig.multisig_data.m as u32 {
+        msg!("INSUFFICIENT APPROVALS");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    msg!("clearing pending action");
+    multisig.pending = [0; 32];
+    multisig.approvals = 0;
+    multisig.pack_into_slice(*multisig_pda.data.borrow_mut());
+
+    msg!("executing approved action");
+    invoke_signed(
+        &ix,
+        account_infos,
+        &[&[&multisig_pda.key.to_bytes(), &[authority_bump]]],
+    )
+}
+
+pub fn create_pegged(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    pegged_data: PeggedData,
+    index_seed: [u8; 32],
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let seller = next_account_info(accounts)?;
+    let seller_temp = next_account_info(accounts)?;
+    let seller_receive = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let temp_info = Account::unpack_from_slice(*seller_temp.try_borrow_data()?)?;
+    let receive_info = Account::unpack_from_slice(*seller_receive.try_borrow_data()?)?;
+    let seed = pegged_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, &index_seed], program_id);
+    let min_rent = rent::Rent::get()?.minimum_balance(PeggedPDA::LEN);
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_offer_config(
+        program_id,
+        config,
+        &[pegged_data.token_type, pegged_data.strike_type],
+        accounts.as_slice(),
+    )?;
+    if !seller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !escrow_pda.try_data_is_empty()? {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if pda != *escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if temp_info.mint != pegged_data.token_type {
+        msg!("INVALID TEMP TYPE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if temp_info.amount != pegged_data.token_qty {
+        msg!("INVALID TEMP BALANCE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if receive_info.mint != pegged_data.strike_type {
+        msg!("INVALID RECEIVE ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if pegged_data.spread_bps <= -10_000 {
+        msg!("INVALID SPREAD");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("creating pegged PDA");
+    let create_ix = system_instruction::create_account(
+        seller.key,
+        escrow_pda.key,
+        min_rent,
+        PeggedPDA::LEN as u64,
+        program_id,
+    );
+    invoke_signed(
+        &create_ix,
+        &[seller.clone(), escrow_pda.clone(), sys_program.clone()],
+        &[&[&seed, &index_seed, &[bump]]],
+    )?;
+
+    msg!("transferring temp ownership to PDA...");
+    let transfer_ix = instruction::set_authority(
+        &spl_token::id(),
+        seller_temp.key,
+        Some(escrow_pda.key),
+        instruction::AuthorityType::AccountOwner,
+        seller.key,
+        &[seller.key],
+    )?;
+    invoke(
+        &transfer_ix,
+        &[seller_temp.clone(), seller.clone(), token_program.clone()],
+    )?;
+
+    msg!("updating PDA data...");
+    let pegged = PeggedPDA {
+        seller_main: *seller.key,
+        seller_temp: *seller_temp.key,
+        seller_receive: *seller_receive.key,
+        pegged_data,
+        index_seed,
+        bump,
+    };
+    pegged.pack_into_slice(*escrow_pda.data.borrow_mut());
+
+    EscrowEvent::OfferCreated {
+        escrow_pda: *escrow_pda.key,
+        seller: *seller.key,
+        seller_temp: *seller_temp.key,
+        seller_receive: *seller_receive.key,
+        offer_data: pegged_data.offer_data(0),
+    }
+    .emit();
+    Ok(())
+}
+
+pub fn accept_pegged(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    max_strike_qty: u64,
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let buyer = next_account_info(accounts)?;
+    let strike_acc = next_account_info(accounts)?;
+    let buyer_receive = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let seller = next_account_info(accounts)?;
+    let seller_temp = next_account_info(accounts)?;
+    let seller_receive = next_account_info(accounts)?;
+    let oracle = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let token_info = Account::unpack_from_slice(*strike_acc.try_borrow_data()?)?;
+    let receive_info = Account::unpack_from_slice(*buyer_receive.try_borrow_data()?)?;
+    let pegged = unpack_pegged(program_id, escrow_pda)?;
+    let pegged_data = pegged.pegged_data;
+    let now = Clock::get()?.unix_timestamp;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    check_not_paused(program_id, config)?;
+    if !buyer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *oracle.key != pegged_data.oracle {
+        msg!("INCORRECT ORACLE ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let price = OraclePrice::unpack(*oracle.try_borrow_data()?)?;
+    if now.saturating_sub(price.publish_time) > pegged_data.max_staleness as i64 {
+        msg!("STALE ORACLE PRICE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if price.price == 0 {
+        msg!("INVALID ORACLE PRICE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let strike_qty = pegged_data.strike_qty(&price)?;
+    if strike_qty > max_strike_qty {
+        msg!("STRIKE ABOVE TAKER LIMIT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if token_info.mint != pegged_data.strike_type {
+        msg!("INCORRECT TOKEN ACCOUNT TYPE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if token_info.amount < strike_qty {
+        msg!("INCORRECT TOKEN ACCOUNT BALANCE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if receive_info.mint != pegged_data.token_type {
+        msg!("INCORRECT RECEIVE ACCOUNT TYPE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *seller.key != pegged.seller_main {
+        msg!("INCORRECT SELLER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *seller_temp.key != pegged.seller_temp {
+        msg!("INCORRECT SELLER_TEMP ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *seller_receive.key != pegged.seller_receive {
+        msg!("INCORRECT SELLER_RECEIVE ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let seed = pegged_data.get_seed();
+    let signer_seeds: &[&[u8]] = &[&seed, &pegged.index_seed, &[pegged.bump]];
+
+    msg!("transferring asset to buyer");
+    transfer_from_pda(
+        seller_temp,
+        buyer_receive,
+        escrow_pda,
+        pegged_data.token_qty,
+        signer_seeds,
+    )?;
+
+    msg!("transferring strike to seller");
+    let strike_ix = instruction::transfer(
+        &spl_token::id(),
+        strike_acc.key,
+        seller_receive.key,
+        buyer.key,
+        &[buyer.key],
+        strike_qty,
+    )?;
+    invoke(
+        &strike_ix,
+        &[strike_acc.clone(), seller_receive.clone(), buyer.clone()],
+    )?;
+
+    msg!("closing temp");
+    close_temp(seller_temp, seller, escrow_pda, signer_seeds)?;
+    msg!("closing PDA");
+    close_pda(escrow_pda, seller)?;
+
+    EscrowEvent::OfferAccepted {
+        escrow_pda: *escrow_pda.key,
+        seller: *seller.key,
+        buyer: *buyer.key,
+        buyer_receive: *buyer_receive.key,
+        offer_data: pegged_data.offer_data(strike_qty),
+    }
+    .emit();
+    Ok(())
+}
+
+pub fn cancel_pegged(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let seller = next_account_info(accounts)?;
+    let seller_temp = next_account_info(accounts)?;
+    let escrow_pda = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let token_program = next_account_info(accounts)?;
+
+    let pegged = unpack_pegged(program_id, escrow_pda)?;
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !spl_token::check_id(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if *seller.key != pegged.seller_main {
+        msg!("INCORRECT SELLER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *seller_temp.key != pegged.seller_temp {
+        msg!("INCORRECT SELLER_TEMP ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !seller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    msg!("transferring temp ownership back to seller");
+    let seed = pegged.pegged_data.get_seed();
+    return_temp(
+        seller_temp,
+        seller,
+        escrow_pda,
+        &[&seed, &pegged.index_seed, &[pegged.bump]],
+    )?;
+
+    msg!("closing PDA");
+    close_pda(escrow_pda, seller)?;
+
+    EscrowEvent::OfferCancelled {
+        escrow_pda: *escrow_pda.key,
+        seller: *seller.key,
+        offer_data: pegged.pegged_data.offer_data(0),
+    }
+    .emit();
+    Ok(())
+}
+
+// only the program's upgrade authority can create the config and become its first admin
+pub fn init_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let admin = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+    let program_data = next_account_info(accounts)?;
+
+    let (pda, bump) = find_address(&[ConfigPDA::SEED], program_id);
+    let min_rent = rent::Rent::get()?.minimum_balance(ConfigPDA::LEN);
+
+    msg!("asserting validity...");
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !admin.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if upgrade_authority(program_id, program_data)? != Some(*admin.key) {
+        msg!("INCORRECT ADMIN ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !config.try_data_is_empty()? {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if pda != *config.key {
+        msg!("INVALID CONFIG ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("creating config PDA");
+    let create_ix = system_instruction::create_account(
+        admin.key,
+        config.key,
+        min_rent,
+        ConfigPDA::LEN as u64,
+        program_id,
+    );
+    invoke_signed(
+        &create_ix,
+        &[admin.clone(), config.clone(), sys_program.clone()],
+        &[&[ConfigPDA::SEED, &[bump]]],
+    )?;
+
+    msg!("updating config data...");
+    let config_data = ConfigPDA {
+        admin: *admin.key,
+        paused: false,
+        allow_list: false,
+        bump,
+        pending_admin: Pubkey::default(),
+    };
+    config_data.pack_into_slice(*config.data.borrow_mut());
+    Ok(())
+}
+
+pub fn set_paused(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    paused: bool,
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let admin = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let mut config_data = unpack_admin_config(program_id, admin, config)?;
+    msg!("updating config data...");
+    config_data.paused = paused;
+    config_data.pack_into_slice(*config.data.borrow_mut());
+    Ok(())
+}
+
+// the first step of an admin transfer, new_admin takes over once it accepts
+pub fn propose_admin(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_admin: Pubkey,
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let admin = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let mut config_data = unpack_admin_config(program_id, admin, config)?;
+    if new_admin == Pubkey::default() {
+        msg!("INVALID ADMIN ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("proposing admin authority");
+    config_data.pending_admin = new_admin;
+    config_data.pack_into_slice(*config.data.borrow_mut());
+    Ok(())
+}
+
+// the second step of an admin transfer, signed by the proposed admin
+pub fn accept_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let new_admin = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let mut config_data = unpack_config(program_id, config)?;
+    msg!("asserting validity...");
+    if !new_admin.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if config_data.pending_admin == Pubkey::default() || *new_admin.key != config_data.pending_admin
+    {
+        msg!("INCORRECT ADMIN ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("transferring admin authority");
+    config_data.admin = config_data.pending_admin;
+    config_data.pending_admin = Pubkey::default();
+    config_data.pack_into_slice(*config.data.borrow_mut());
+    Ok(())
+}
+
+pub fn set_allow_list(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    enabled: bool,
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let admin = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+
+    let mut config_data = unpack_admin_config(program_id, admin, config)?;
+    msg!("updating config data...");
+    config_data.allow_list = enabled;
+    config_data.pack_into_slice(*config.data.borrow_mut());
+    Ok(())
+}
+
+pub fn allow_mint(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mint: Pubkey,
+) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let admin = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+    let entry = next_account_info(accounts)?;
+    let sys_program = next_account_info(accounts)?;
+
+    let (pda, bump) = find_address(&[AllowedMintPDA::SEED, mint.as_ref()], program_id);
+    let min_rent = rent::Rent::get()?.minimum_balance(AllowedMintPDA::LEN);
+
+    if !system_program::check_id(sys_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    unpack_admin_config(program_id, admin, config)?;
+    if !entry.try_data_is_empty()? {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if pda != *entry.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("creating allowed mint PDA");
+    let create_ix = system_instruction::create_account(
+        admin.key,
+        entry.key,
+        min_rent,
+        AllowedMintPDA::LEN as u64,
+        program_id,
+    );
+    invoke_signed(
+        &create_ix,
+        &[admin.clone(), entry.clone(), sys_program.clone()],
+        &[&[AllowedMintPDA::SEED, mint.as_ref(), &[bump]]],
+    )?;
+    AllowedMintPDA { mint, bump }.pack_into_slice(*entry.data.borrow_mut());
+    Ok(())
+}
+
+// closes the entry, its rent goes to the admin
+pub fn disallow_mint(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = &mut accounts.iter();
+    let admin = next_account_info(accounts)?;
+    let config = next_account_info(accounts)?;
+    let entry = next_account_info(accounts)?;
+
+    unpack_admin_config(program_id, admin, config)?;
+    if allowed_mint(program_id, entry).is_none() {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    msg!("closing PDA");
+    close_pda(entry, admin)
+}
+
+// the upgrade authority recorded in the program's ProgramData account, None once the program
+// is immutable
+fn upgrade_authority(
+    program_id: &Pubkey,
+    program_data: &AccountInfo,
+) -> Result<Option<Pubkey>, ProgramError> {
+    let (address, _) = find_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    if *program_data.key != address || !bpf_loader_upgradeable::check_id(program_data.owner) {
+        msg!("INVALID PROGRAM DATA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    let data = program_data.try_borrow_data()?;
+    let metadata = data
+        .get(..metadata_len)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    match limited_deserialize(metadata, metadata_len as u64) {
+        Ok(UpgradeableLoaderState::ProgramData {
+            upgrade_authority_address,
+            ..
+        }) => Ok(upgrade_authority_address),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+fn unpack_config(program_id: &Pubkey, config: &AccountInfo) -> Result<ConfigPDA, ProgramError> {
+    if config.owner != program_id {
+        msg!("INVALID CONFIG ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let config_data = ConfigPDA::unpack_from_slice(*config.try_borrow_data()?)?;
+    let seeds: &[&[u8]] = &[ConfigPDA::SEED, &[config_data.bump]];
+    if create_address(seeds, program_id) != Some(*config.key) {
+        msg!("INVALID CONFIG ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(config_data)
+}
+
+fn unpack_admin_config(
+    program_id: &Pubkey,
+    admin: &AccountInfo,
+    config: &AccountInfo,
+) -> Result<ConfigPDA, ProgramError> {
+    let config_data = unpack_config(program_id, config)?;
+
+    msg!("asserting validity...");
+    if !admin.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *admin.key != config_data.admin {
+        msg!("INCORRECT ADMIN ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(config_data)
+}
+
+// config must be the initialised config PDA, checked under its stored bump. returns its data
+fn check_not_paused(program_id: &Pubkey, config: &AccountInfo) -> Result<ConfigPDA, ProgramError> {
+    let config_data = unpack_config(program_id, config)?;
+    if config_data.paused {
+        msg!("PROGRAM PAUSED");
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(config_data)
+}
+
+// checks for opening offers: not paused and, with the allow-list on, an AllowedMintPDA for
+// every mint among the accounts left after the instruction's own
+fn check_offer_config(
+    program_id: &Pubkey,
+    config: &AccountInfo,
+    mints: &[Pubkey],
+    entries: &[AccountInfo],
+) -> ProgramResult {
+    if !check_not_paused(program_id, config)?.allow_list {
+        return Ok(());
+    }
+    for mint in mints {
+        if !entries
+            .iter()
+            .any(|entry| allowed_mint(program_id, entry) == Some(*mint))
+        {
+            msg!("MINT NOT ALLOWED");
+            return Err(ProgramError::InvalidArgument);
+        }
+    }
+    Ok(())
+}
+
+// the mint an AllowedMintPDA lists, None for any other account
+fn allowed_mint(program_id: &Pubkey, entry: &AccountInfo) -> Option<Pubkey> {
+    if entry.owner != program_id {
+        return None;
+    }
+    let entry_data = AllowedMintPDA::unpack_from_slice(*entry.try_borrow_data().ok()?).ok()?;
+    let seeds: &[&[u8]] = &[
+        AllowedMintPDA::SEED,
+        entry_data.mint.as_ref(),
+        &[entry_data.bump],
+    ];
+    (create_address(seeds, program_id) == Some(*entry.key)).then_some(entry_data.mint)
+}
+
+// reads a pegged offer, checking escrow_pda is its address under the stored bump
+fn unpack_pegged(program_id: &Pubkey, escrow_pda: &AccountInfo) -> Result<PeggedPDA, ProgramError> {
+    if escrow_pda.owner != program_id {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let pegged = PeggedPDA::unpack_from_slice(*escrow_pda.try_borrow_data()?)?;
+    if pegged.address(program_id) != Some(*escrow_pda.key) {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(pegged)
+}
+
+// optional accounts are skipped by passing the program id. one may only be left off when no
+// required account follows it, and config follows them in most instructions
+fn next_optional_account<'a, 'b, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    iter: &mut I,
+    program_id: &Pubkey,
+) -> Option<&'a AccountInfo<'b>> {
+    iter.next().filter(|account| account.key != program_id)
+}
+
+// the operator may stand in for seller_main's signature when managing an offer
+fn is_operator(escrow_data: &EscrowView, operator: Option<&AccountInfo>) -> bool {
+    match (escrow_data.operator(), operator) {
+        (Some(key), Some(operator)) => operator.is_signer && *operator.key == key,
+        _ => false,
+    }
+}
+
+struct NewOffer<'a, 'b> {
+    seller: &'a AccountInfo<'b>,
+    seller_temp: &'a AccountInfo<'b>,
+    seller_receive: &'a AccountInfo<'b>,
+    escrow_pda: &'a AccountInfo<'b>,
+    operator: Option<&'a AccountInfo<'b>>,
+    rent_payer: Option<&'a AccountInfo<'b>>,
+}
+
+// checks shared by every way of posting an offer, returns the offer seed, PDA bump and the
+// receive address. seller_receive may be a strike token account, or a wallet whose strike ATA
+// is used instead
+fn validate_offer(
+    program_id: &Pubkey,
+    offer: &NewOffer,
+    offer_data: &OfferData,
+    index_seed: &[u8; 32],
+) -> Result<([u8; 32], u8, Pubkey), ProgramError> {
+    let temp_info = Account::unpack_from_slice(*offer.seller_temp.try_borrow_data()?)?;
+    let seed = offer_data.get_seed();
+    let (pda, bump) = find_address(&[&seed, index_seed], program_id);
+
+    if !offer.seller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if offer
+        .rent_payer
+        .is_some_and(|rent_payer| !rent_payer.is_signer)
+    {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !offer.escrow_pda.try_data_is_empty()? {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if pda != *offer.escrow_pda.key {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if temp_info.mint != offer_data.token_type {
+        msg!("INVALID TEMP TYPE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if temp_info.amount != offer_data.token_qty {
+        msg!("INVALID TEMP BALANCE");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !spl_token::check_id(offer.seller_receive.owner) {
+        let receive =
+            get_associated_token_address(offer.seller_receive.key, &offer_data.strike_type);
+        return Ok((seed, bump, receive));
+    }
+    let receive_info = Account::unpack_from_slice(*offer.seller_receive.try_borrow_data()?)?;
+    if receive_info.mint != offer_data.strike_type {
+        msg!("INVALID RECEIVE ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok((seed, bump, *offer.seller_receive.key))
+}
+
+fn open_offer<'a>(
+    program_id: &Pubkey,
+    offer: &NewOffer<'_, 'a>,
+    sys_program: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    offer_data: OfferData,
+    index_seed: [u8; 32],
+    (seed, bump, seller_receive): ([u8; 32], u8, Pubkey),
+) -> ProgramResult {
+    let NewOffer {
+        seller,
+        seller_temp,
+        escrow_pda,
+        operator,
+        rent_payer,
+        ..
+    } = *offer;
+    let rent_payer = rent_payer.unwrap_or(seller);
+    let min_rent = rent::Rent::get()?.minimum_balance(EscrowPDA::LEN);
+
+    msg!("creating escrow PDA");
+
+    let create_ix = system_instruction::create_account(
+        rent_payer.key,
+        escrow_pda.key,
+        min_rent,
+        EscrowPDA::LEN as u64,
+        program_id,
+    );
+    invoke_signed(
+        &create_ix,
+        &[rent_payer.clone(), escrow_pda.clone(), sys_program.clone()],
+        &[&[&seed, &index_seed, &[bump]]],
+    )?;
+
+    msg!("transferring temp ownership to PDA...");
+    let transfer_ix = instruction::set_authority(
+        &spl_token::id(),
+        seller_temp.key,
+        Some(escrow_pda.key),
+        instruction::AuthorityType::AccountOwner,
+        seller.key,
+        &[seller.key],
+    )?;
+    invoke(
+        &transfer_ix,
+        &[seller_temp.clone(), seller.clone(), token_program.clone()],
+    )?;
+
+    msg!("updating PDA data...");
+    let escrow_data = EscrowPDA {
+        offer_data,
+        seller_main: *seller.key,
+        seller_temp: *seller_temp.key,
+        seller_receive,
+        index_seed,
+        operator: operator.map(|operator| *operator.key),
+        rent_payer: *rent_payer.key,
+        bump,
+    };
+    escrow_data.pack_into_slice(*escrow_pda.data.borrow_mut());
+
+    EscrowEvent::OfferCreated {
+        escrow_pda: *escrow_pda.key,
+        seller: *seller.key,
+        seller_temp: *seller_temp.key,
+        seller_receive,
+        offer_data,
+    }
+    .emit();
+    Ok(())
+}
+
+// creates wallet's ATA for mint at ata if it does not exist yet, paid by funder
+fn create_ata<'a>(
+    funder: &AccountInfo<'a>,
+    ata: &AccountInfo<'a>,
+    wallet: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    sys_program: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+) -> ProgramResult {
+    if !ata.try_data_is_empty()? {
+        return Ok(());
+    }
+    let create_ix =
+        create_associated_token_account(funder.key, wallet.key, mint.key, &spl_token::id());
+    invoke(
+        &create_ix,
+        &[
+            funder.clone(),
+            ata.clone(),
+            wallet.clone(),
+            mint.clone(),
+            sys_program.clone(),
+            token_program.clone(),
+        ],
+    )
+}
+
+// checks escrow_pda is the offer's address and returns its bump
+fn escrow_bump(
+    program_id: &Pubkey,
+    escrow_pda: &AccountInfo,
+    escrow_data: &EscrowView,
+) -> Result<u8, ProgramError> {
+    match escrow_data.address(program_id, escrow_pda.data_len()) {
+        Some((pda, bump)) if pda == *escrow_pda.key => Ok(bump),
+        _ => {
+            msg!("INVALID PDA ACCOUNT");
+            Err(ProgramError::InvalidArgument)
+        }
+    }
+}
+
+// checks that seller, or the operator on their behalf, may cancel escrow_pda
+pub fn check_cancel(
+    program_id: &Pubkey,
+    seller: &AccountInfo,
+    seller_temp: &AccountInfo,
+    escrow_pda: &AccountInfo,
+    operator: Option<&AccountInfo>,
+) -> Result<(EscrowView, u8), ProgramError> {
+    if escrow_pda.owner != program_id {
+        msg!("INVALID PDA ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let escrow_data = EscrowView::read(*escrow_pda.try_borrow_data()?)?;
+    let bump = escrow_bump(program_id, escrow_pda, &escrow_data)?;
+
+    if *seller.key != escrow_data.seller_main {
+        msg!("INCORRECT SELLER ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *seller_temp.key != escrow_data.seller_temp {
+        msg!("INCORRECT SELLER_TEMP ACCOUNT");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !seller.is_signer && !is_operator(&escrow_data, operator) {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok((escrow_data, bump))
+}
+
+// returns seller_temp to seller_main and closes the offer, any failure aborts the whole instruction
+fn cancel_escrow<'a>(
+    seller: &AccountInfo<'a>,
+    seller_temp: &AccountInfo<'a>,
+    escrow_pda: &AccountInfo<'a>,
+    escrow_data: &EscrowView,
+    bump: u8,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let seed = escrow_data.offer_seed();
+    msg!("transferring temp ownership back to seller");
+    return_temp(
+        seller_temp,
+        seller,
+        escrow_pda,
+        &[&seed, &escrow_data.index_seed, &[bump]],
+    )?;
+
+    msg!("closing PDA");
+    close_pda(
+        escrow_pda,
+        rent_payer_account(escrow_data, seller, accounts)?,
+    )?;
+
+    EscrowEvent::OfferCancelled {
+        escrow_pda: *escrow_pda.key,
+        seller: *seller.key,
+        offer_data: escrow_data.offer_data(),
+    }
+    .emit();
+    Ok(())
+}
+
+// PDA rent goes back to whoever funded it, passed anywhere in the accounts if not seller_main
+pub fn rent_payer_account<'a, 'b>(
+    escrow_data: &EscrowView,
+    seller: &'a AccountInfo<'b>,
+    accounts: &'a [AccountInfo<'b>],
+) -> Result<&'a AccountInfo<'b>, ProgramError> {
+    if escrow_data.rent_payer == *seller.key {
+        return Ok(seller);
+    }
+    accounts
+        .iter()
+        .find(|account| *account.key == escrow_data.rent_payer)
+        .ok_or_else(|| {
+            msg!("MISSING RENT PAYER ACCOUNT");
+            ProgramError::NotEnoughAccountKeys
+        })
+}
+
+fn transfer_from_pda<'a>(
+    temp: &AccountInfo<'a>,
+    dest: &AccountInfo<'a>,
+    escrow_pda: &AccountInfo<'a>,
+    amount: u64,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let ix = instruction::transfer(
+        &spl_token::id(),
+        temp.key,
+        dest.key,
+        escrow_pda.key,
+        &[escrow_pda.key],
+        amount,
+    )?;
+    invoke_signed(
+        &ix,
+        &[temp.clone(), dest.clone(), escrow_pda.clone()],
+        &[signer_seeds],
+    )
+}
+
+fn close_temp<'a>(
+    temp: &AccountInfo<'a>,
+    dest: &AccountInfo<'a>,
+    escrow_pda: &AccountInfo<'a>,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let ix = instruction::close_account(
+        &spl_token::id(),
+        temp.key,
+        dest.key,
+        escrow_pda.key,
+        &[escrow_pda.key],
+    )?;
+    invoke_signed(
+        &ix,
+        &[temp.clone(), dest.clone(), escrow_pda.clone()],
+        &[signer_seeds],
+    )
+}
+
+fn return_temp<'a>(
+    temp: &AccountInfo<'a>,
+    owner: &AccountInfo<'a>,
+    escrow_pda: &AccountInfo<'a>,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let ix = instruction::set_authority(
+        &spl_token::id(),
+        temp.key,
+        Some(owner.key),
+        instruction::AuthorityType::AccountOwner,
+        escrow_pda.key,
+        &[escrow_pda.key],
+    )?;
+    invoke_signed(
+        &ix,
+        &[temp.clone(), owner.clone(), escrow_pda.clone()],
+        &[signer_seeds],
+    )
+}
+
+fn close_pda(escrow_pda: &AccountInfo, dest: &AccountInfo) -> ProgramResult {
     *escrow_pda.data.borrow_mut() = &mut [];
-    **seller.try_borrow_mut_lamports()? += escrow_pda.try_lamports()?;
+    let lamports = math::checked_add(dest.try_lamports()?, escrow_pda.try_lamports()?)?;
+    **dest.try_borrow_mut_lamports()? = lamports;
     **escrow_pda.try_borrow_mut_lamports()? = 0;
     Ok(())
 }
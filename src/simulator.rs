@@ -0,0 +1,324 @@
+use crate::processor::{
+    check_accept, check_cancel_offer, rent_payer_account, AcceptAccounts, CancelAccounts,
+};
+use crate::state::{EscrowPDA, OfferData};
+use solana_program::{
+    account_info::AccountInfo, bpf_loader, program_error::ProgramError, program_option::COption,
+    program_pack::Pack, pubkey::Pubkey, system_program,
+};
+use spl_token::{error::TokenError, state::Account};
+
+// off-chain dry run of accept_offer and cancel_offer: runs the processor's own checks against
+// fetched account states, then predicts the token program's checks for the CPIs that follow
+
+// an account as fetched from RPC
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimAccount {
+    pub key: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+}
+
+impl SimAccount {
+    pub fn wallet(key: Pubkey, lamports: u64) -> Self {
+        SimAccount {
+            key,
+            lamports,
+            data: vec![],
+            owner: system_program::id(),
+        }
+    }
+
+    pub fn token(key: Pubkey, lamports: u64, state: &Account) -> Self {
+        let mut data = vec![0; Account::LEN];
+        state.pack_into_slice(&mut data);
+        SimAccount {
+            key,
+            lamports,
+            data,
+            owner: spl_token::id(),
+        }
+    }
+
+    pub fn escrow(program_id: &Pubkey, key: Pubkey, lamports: u64, escrow: &EscrowPDA) -> Self {
+        let mut data = vec![0; EscrowPDA::LEN];
+        escrow.pack_into_slice(&mut data);
+        SimAccount {
+            key,
+            lamports,
+            data,
+            owner: *program_id,
+        }
+    }
+
+    fn program(key: Pubkey) -> Self {
+        SimAccount {
+            key,
+            lamports: 1,
+            data: vec![],
+            owner: bpf_loader::id(),
+        }
+    }
+
+    fn info(&mut self, is_signer: bool) -> AccountInfo<'_> {
+        let executable = self.owner == bpf_loader::id();
+        AccountInfo::new(
+            &self.key,
+            is_signer,
+            !executable,
+            &mut self.lamports,
+            &mut self.data,
+            &self.owner,
+            executable,
+            0,
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BalanceChange {
+    Token {
+        account: Pubkey,
+        mint: Pubkey,
+        delta: i128,
+    },
+    Lamports {
+        account: Pubkey,
+        delta: i128,
+    },
+}
+
+pub struct AcceptState {
+    pub buyer: SimAccount,
+    pub strike_acc: SimAccount,
+    pub buyer_receive: SimAccount,
+    pub escrow_pda: SimAccount,
+    pub seller: SimAccount,
+    pub seller_temp: SimAccount,
+    pub seller_receive: SimAccount,
+    // the initialised config PDA
+    pub config: SimAccount,
+    // only needed when the escrow's rent payer is not seller_main
+    pub rent_payer: Option<SimAccount>,
+}
+
+pub struct CancelState {
+    pub seller: SimAccount,
+    pub seller_temp: SimAccount,
+    pub seller_receive: SimAccount,
+    pub escrow_pda: SimAccount,
+    // signs in place of seller when set
+    pub operator: Option<SimAccount>,
+    pub rent_payer: Option<SimAccount>,
+}
+
+// predicts accept_offer signed by state.buyer, returning the error it would fail with
+// or every token and lamport balance it would change
+pub fn simulate_accept(
+    program_id: &Pubkey,
+    state: &AcceptState,
+    expected: Option<OfferData>,
+) -> Result<Vec<BalanceChange>, ProgramError> {
+    let mut accounts = vec![
+        state.buyer.clone(),
+        state.strike_acc.clone(),
+        state.buyer_receive.clone(),
+        state.escrow_pda.clone(),
+        state.seller.clone(),
+        state.seller_temp.clone(),
+        state.seller_receive.clone(),
+        SimAccount::program(system_program::id()),
+        SimAccount::program(spl_token::id()),
+        state.config.clone(),
+    ];
+    accounts.extend(state.rent_payer.clone());
+    let mut accounts = accounts.iter_mut();
+    let mut infos = vec![accounts.next().unwrap().info(true)];
+    infos.extend(accounts.map(|account| account.info(false)));
+
+    let (escrow_data, _bump) = check_accept(
+        program_id,
+        &AcceptAccounts {
+            buyer: &infos[0],
+            strike_acc: &infos[1],
+            buyer_receive: &infos[2],
+            escrow_pda: &infos[3],
+            seller: &infos[4],
+            seller_temp: &infos[5],
+            seller_receive: &infos[6],
+            sys_program: &infos[7],
+            token_program: &infos[8],
+            config: &infos[9],
+        },
+        expected,
+    )?;
+    let offer_data = escrow_data.offer_data();
+
+    let mut temp = token_account(&state.seller_temp)?;
+    let receive = token_account(&state.buyer_receive)?;
+    check_transfer(&temp, &receive, &state.escrow_pda.key, offer_data.token_qty)?;
+    temp.amount -= offer_data.token_qty;
+
+    let strike = token_account(&state.strike_acc)?;
+    let seller_receive = token_account(&state.seller_receive)?;
+    check_transfer(
+        &strike,
+        &seller_receive,
+        &state.buyer.key,
+        offer_data.strike_qty,
+    )?;
+
+    if !temp.is_native() && temp.amount != 0 {
+        return Err(TokenError::NonNativeHasBalance.into());
+    }
+    check_authority(
+        &temp.close_authority.unwrap_or(temp.owner),
+        &state.escrow_pda.key,
+    )?;
+    let rent_payer = *rent_payer_account(&escrow_data, &infos[4], &infos)?.key;
+
+    let mut changes = vec![
+        BalanceChange::Token {
+            account: state.seller_temp.key,
+            mint: offer_data.token_type,
+            delta: -(offer_data.token_qty as i128),
+        },
+        BalanceChange::Token {
+            account: state.buyer_receive.key,
+            mint: offer_data.token_type,
+            delta: offer_data.token_qty as i128,
+        },
+        BalanceChange::Token {
+            account: state.strike_acc.key,
+            mint: offer_data.strike_type,
+            delta: -(offer_data.strike_qty as i128),
+        },
+        BalanceChange::Token {
+            account: state.seller_receive.key,
+            mint: offer_data.strike_type,
+            delta: offer_data.strike_qty as i128,
+        },
+    ];
+    move_lamports(
+        &mut changes,
+        &state.seller_temp.key,
+        &state.seller.key,
+        state.seller_temp.lamports,
+    );
+    move_lamports(
+        &mut changes,
+        &state.escrow_pda.key,
+        &rent_payer,
+        state.escrow_pda.lamports,
+    );
+    Ok(changes)
+}
+
+// predicts cancel_offer signed by the seller, or by state.operator when set
+pub fn simulate_cancel(
+    program_id: &Pubkey,
+    state: &CancelState,
+) -> Result<Vec<BalanceChange>, ProgramError> {
+    let mut seller = state.seller.clone();
+    let mut fixed = [
+        state.seller_temp.clone(),
+        state.seller_receive.clone(),
+        state.escrow_pda.clone(),
+        SimAccount::program(system_program::id()),
+        SimAccount::program(spl_token::id()),
+    ];
+    let mut operator = state.operator.clone();
+    let mut rent_payer = state.rent_payer.clone();
+    let mut infos = vec![seller.info(operator.is_none())];
+    infos.extend(fixed.iter_mut().map(|account| account.info(false)));
+    infos.extend(operator.as_mut().map(|operator| operator.info(true)));
+    infos.extend(rent_payer.as_mut().map(|rent_payer| rent_payer.info(false)));
+
+    let operator_info = state.operator.as_ref().map(|_| &infos[6]);
+    let (escrow_data, _bump) = check_cancel_offer(
+        program_id,
+        &CancelAccounts {
+            seller: &infos[0],
+            seller_temp: &infos[1],
+            seller_receive: &infos[2],
+            escrow_pda: &infos[3],
+            sys_program: &infos[4],
+            token_program: &infos[5],
+        },
+        operator_info,
+    )?;
+
+    let temp = token_account(&state.seller_temp)?;
+    if temp.is_frozen() {
+        return Err(TokenError::AccountFrozen.into());
+    }
+    check_authority(&temp.owner, &state.escrow_pda.key)?;
+    let rent_payer = *rent_payer_account(&escrow_data, &infos[0], &infos)?.key;
+
+    let mut changes = vec![];
+    move_lamports(
+        &mut changes,
+        &state.escrow_pda.key,
+        &rent_payer,
+        state.escrow_pda.lamports,
+    );
+    Ok(changes)
+}
+
+fn token_account(account: &SimAccount) -> Result<Account, ProgramError> {
+    if account.owner != spl_token::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Account::unpack(&account.data)
+}
+
+// the checks spl-token's transfer makes, in the order it makes them
+fn check_transfer(
+    source: &Account,
+    dest: &Account,
+    authority: &Pubkey,
+    amount: u64,
+) -> Result<(), ProgramError> {
+    if source.is_frozen() || dest.is_frozen() {
+        return Err(TokenError::AccountFrozen.into());
+    }
+    if source.amount < amount {
+        return Err(TokenError::InsufficientFunds.into());
+    }
+    if source.mint != dest.mint {
+        return Err(TokenError::MintMismatch.into());
+    }
+    match source.delegate {
+        COption::Some(delegate) if delegate == *authority => {
+            if source.delegated_amount < amount {
+                return Err(TokenError::InsufficientFunds.into());
+            }
+            Ok(())
+        }
+        _ => check_authority(&source.owner, authority),
+    }
+}
+
+fn check_authority(expected: &Pubkey, authority: &Pubkey) -> Result<(), ProgramError> {
+    if expected != authority {
+        return Err(TokenError::OwnerMismatch.into());
+    }
+    Ok(())
+}
+
+fn move_lamports(changes: &mut Vec<BalanceChange>, from: &Pubkey, to: &Pubkey, lamports: u64) {
+    for (key, delta) in [(from, -(lamports as i128)), (to, lamports as i128)] {
+        let existing = changes.iter_mut().find_map(|change| match change {
+            BalanceChange::Lamports { account, delta } if account == key => Some(delta),
+            _ => None,
+        });
+        match existing {
+            Some(existing) => *existing += delta,
+            None => changes.push(BalanceChange::Lamports {
+                account: *key,
+                delta,
+            }),
+        }
+    }
+}
@@ -1,9 +1,20 @@
+use crate::math::{self, Rounding};
 use arrayref::{array_refs, mut_array_refs};
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
 use sha2::{Digest, Sha256};
-use solana_program::{program_error::ProgramError, program_pack::*, pubkey::Pubkey};
+use solana_program::{
+    instruction::Instruction, program_error::ProgramError, program_pack::*, pubkey::Pubkey,
+};
 use std::convert::TryInto;
+use std::io::{Read, Write};
+#[cfg(not(target_os = "solana"))]
+use std::sync::atomic::{AtomicU64, Ordering};
 
-#[derive(Debug, PartialEq)]
+// offer data format (borsh): [0..32 = token_type, 32..40 = token_qty, 40..72 = strike_type,
+// 72..80 = strike_qty]
+#[cfg_attr(feature = "idl", derive(borsh::BorshSchema))]
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone, Copy)]
 pub struct OfferData {
     pub token_type: Pubkey,
     pub token_qty: u64,
@@ -11,87 +22,1073 @@ pub struct OfferData {
     pub strike_qty: u64,
 }
 
+#[cfg_attr(feature = "idl", derive(borsh::BorshSchema))]
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct HtlcData {
+    pub token_type: Pubkey,
+    pub token_qty: u64,
+    pub buyer: Pubkey,
+    pub hashlock: [u8; 32],
+    pub expiry: i64,
+}
+
+#[cfg_attr(feature = "idl", derive(borsh::BorshSchema))]
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ArbiterData {
+    pub token_type: Pubkey,
+    pub token_qty: u64,
+    pub seller: Pubkey,
+    pub arbiter: Pubkey,
+}
+
+// milestones keep their fixed pre-borsh format, which milestone PDAs and their seeds are built
+// from, and implement the borsh traits with it. see the impls below
+#[derive(Debug, PartialEq)]
+pub struct Milestone {
+    pub amount: u64,
+    pub deadline: Option<i64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MilestoneData {
+    pub token_type: Pubkey,
+    pub payee: Pubkey,
+    pub milestones: Vec<Milestone>,
+}
+
+#[cfg_attr(feature = "idl", derive(borsh::BorshSchema))]
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct VestingData {
+    pub token_type: Pubkey,
+    pub token_qty: u64,
+    pub beneficiary: Pubkey,
+    pub start: i64,
+    pub cliff: i64,
+    pub end: i64,
+}
+
+// multisig data keeps a fixed size, which the PDA and its seed are built from, and implements the
+// borsh traits with it like MilestoneData
+#[derive(Debug, PartialEq)]
+pub struct MultisigData {
+    pub m: u8,
+    pub signers: Vec<Pubkey>,
+}
+
+// limit order (borsh): offer terms with the strike given as a price per whole token, in whole
+// strike tokens with LimitOrder::PRICE_DECIMALS decimal places. converted to OfferData with
+// both mints' decimals when the offer is created
+#[cfg_attr(feature = "idl", derive(borsh::BorshSchema))]
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone, Copy)]
+pub struct LimitOrder {
+    pub token_type: Pubkey,
+    pub token_qty: u64,
+    pub strike_type: Pubkey,
+    pub price: u64,
+}
+
+// oracle pegged offer (borsh): token_qty of token_type for strike_type at the oracle price
+// adjusted by spread_bps (+100 = 1% above), priced when accepted from a feed at most
+// max_staleness seconds old
+#[cfg_attr(feature = "idl", derive(borsh::BorshSchema))]
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone, Copy)]
+pub struct PeggedData {
+    pub token_type: Pubkey,
+    pub token_qty: u64,
+    pub strike_type: Pubkey,
+    pub oracle: Pubkey,
+    pub spread_bps: i16,
+    pub max_staleness: u32,
+}
+
+// escrow account format: [0..32 = seller_main, 32..64 = seller_temp,
+// 64..96 = seller_receive, 96..176 = offer_data, 176..208 = index_seed,
+// 208 = operator option tag, 209..241 = operator (absent, zero padded, if none),
+// 241..273 = rent_payer, 273 = bump]. the operator is always padded to 33 bytes so the later
+// fields keep fixed offsets. older formats are prefixes: v1 accounts are the first 208 bytes
+// only, with no operator and rent paid by seller_main, later v1 accounts add the operator in
+// 241 bytes, and v2 accounts are the first 273 bytes, with no stored bump
 #[derive(Debug, PartialEq)]
 pub struct EscrowPDA {
-    pub offer_data: OfferData,
     pub seller_main: Pubkey,
     pub seller_temp: Pubkey,
     pub seller_receive: Pubkey,
+    pub offer_data: OfferData,
     pub index_seed: [u8; 32],
+    pub operator: Option<Pubkey>,
+    pub rent_payer: Pubkey,
+    // canonical bump of the escrow address, 0 for v1 and v2 accounts
+    pub bump: u8,
 }
 
 impl OfferData {
     pub const LEN: usize = 80;
     pub fn from_bytes(src: &[u8; 80]) -> Self {
-        let (token_type, token_qty, strike_type, strike_qty) = array_refs![src, 32, 8, 32, 8];
-        let token_type = Pubkey::new_from_array(*token_type);
-        let token_qty = u64::from_le_bytes(*token_qty);
-        let strike_type = Pubkey::new_from_array(*strike_type);
-        let strike_qty = u64::from_le_bytes(*strike_qty);
+        OfferData::try_from_slice(src).unwrap()
+    }
 
-        OfferData {
+    pub fn to_bytes(&self) -> [u8; 80] {
+        let mut dst = [0; 80];
+        self.serialize(&mut dst.as_mut_slice()).unwrap();
+        dst
+    }
+
+    pub fn get_seed(&self) -> [u8; 32] {
+        get_seed(&self.to_bytes())
+    }
+
+    // price per whole token of any offer in LimitOrder::PRICE_DECIMALS fixed point, rounded
+    // down, as shown to takers. errors if token_qty is zero or the price does not fit in a u64
+    pub fn limit_price(
+        &self,
+        token_decimals: u8,
+        strike_decimals: u8,
+    ) -> Result<u64, ProgramError> {
+        let scale = math::mul(
+            math::pow10(token_decimals)?,
+            math::pow10(LimitOrder::PRICE_DECIMALS)?,
+        )?;
+        let denominator = math::mul(self.token_qty as u128, math::pow10(strike_decimals)?)?;
+        let price =
+            math::mul_div_wide(self.strike_qty as u128, scale, denominator, Rounding::Down)?;
+        math::to_u64(price)
+    }
+}
+
+impl HtlcData {
+    pub const LEN: usize = 112;
+    pub fn from_bytes(src: &[u8; 112]) -> Self {
+        HtlcData::try_from_slice(src).unwrap()
+    }
+
+    pub fn to_bytes(&self) -> [u8; 112] {
+        let mut dst = [0; 112];
+        self.serialize(&mut dst.as_mut_slice()).unwrap();
+        dst
+    }
+
+    pub fn get_seed(&self) -> [u8; 32] {
+        get_seed(&self.to_bytes())
+    }
+}
+
+impl ArbiterData {
+    pub const LEN: usize = 104;
+    pub fn from_bytes(src: &[u8; 104]) -> Self {
+        ArbiterData::try_from_slice(src).unwrap()
+    }
+
+    pub fn to_bytes(&self) -> [u8; 104] {
+        let mut dst = [0; 104];
+        self.serialize(&mut dst.as_mut_slice()).unwrap();
+        dst
+    }
+
+    pub fn get_seed(&self) -> [u8; 32] {
+        get_seed(&self.to_bytes())
+    }
+}
+
+// milestone format: [0..8 = amount, 8..16 = deadline, 0 if none]
+impl Milestone {
+    pub const LEN: usize = 16;
+}
+
+impl BorshSerialize for Milestone {
+    fn serialize<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.amount.serialize(writer)?;
+        self.deadline.unwrap_or(0).serialize(writer)
+    }
+}
+
+impl BorshDeserialize for Milestone {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let amount = u64::deserialize_reader(reader)?;
+        let deadline = i64::deserialize_reader(reader)?;
+        Ok(Milestone {
+            amount,
+            deadline: (deadline != 0).then_some(deadline),
+        })
+    }
+}
+
+// milestone data format: [0..32 = token_type, 32..64 = payee, 64 = milestone count,
+// 65.. = count milestones]
+impl MilestoneData {
+    pub const HEADER_LEN: usize = 65;
+    pub const MAX_MILESTONES: usize = 32;
+
+    pub fn len(milestone_count: usize) -> usize {
+        MilestoneData::HEADER_LEN + milestone_count * Milestone::LEN
+    }
+
+    pub fn get_seed(&self) -> [u8; 32] {
+        get_seed(&self.try_to_vec().unwrap())
+    }
+}
+
+impl BorshSerialize for MilestoneData {
+    fn serialize<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let count = u8::try_from(self.milestones.len())
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+        self.token_type.serialize(writer)?;
+        self.payee.serialize(writer)?;
+        count.serialize(writer)?;
+        self.milestones
+            .iter()
+            .try_for_each(|milestone| milestone.serialize(writer))
+    }
+}
+
+impl BorshDeserialize for MilestoneData {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let token_type = Pubkey::deserialize_reader(reader)?;
+        let payee = Pubkey::deserialize_reader(reader)?;
+        let count = u8::deserialize_reader(reader)?;
+        let milestones = (0..count)
+            .map(|_| Milestone::deserialize_reader(reader))
+            .collect::<std::io::Result<_>>()?;
+        Ok(MilestoneData {
             token_type,
-            token_qty,
-            strike_type,
-            strike_qty,
+            payee,
+            milestones,
+        })
+    }
+}
+
+// describes the format above, a u8 count ahead of the milestones where a borsh Vec has a u32
+#[cfg(feature = "idl")]
+mod milestone_schema {
+    use super::{Milestone, MilestoneData};
+    use borsh::schema::{Declaration, Definition, Fields};
+    use borsh::BorshSchema;
+    use solana_program::pubkey::Pubkey;
+    use std::collections::HashMap;
+
+    impl BorshSchema for Milestone {
+        fn add_definitions_recursively(definitions: &mut HashMap<Declaration, Definition>) {
+            let fields = vec![
+                ("amount".to_string(), u64::declaration()),
+                ("deadline".to_string(), i64::declaration()),
+            ];
+            Self::add_definition(
+                Self::declaration(),
+                Definition::Struct {
+                    fields: Fields::NamedFields(fields),
+                },
+                definitions,
+            );
+        }
+
+        fn declaration() -> Declaration {
+            "Milestone".to_string()
         }
     }
 
-    pub fn to_bytes(&self) -> [u8; 80] {
-        let mut dst = [0; 80];
-        let (token_type, token_qty, strike_type, strike_qty) =
-            mut_array_refs![&mut dst, 32, 8, 32, 8];
-        *token_type = self.token_type.to_bytes();
-        *token_qty = self.token_qty.to_le_bytes();
-        *strike_type = self.strike_type.to_bytes();
-        *strike_qty = self.strike_qty.to_le_bytes();
+    impl BorshSchema for MilestoneData {
+        fn add_definitions_recursively(definitions: &mut HashMap<Declaration, Definition>) {
+            let fields = vec![
+                ("token_type".to_string(), Pubkey::declaration()),
+                ("payee".to_string(), Pubkey::declaration()),
+                ("milestone_count".to_string(), u8::declaration()),
+                (
+                    "milestones".to_string(),
+                    "Array<Milestone, milestone_count>".to_string(),
+                ),
+            ];
+            Self::add_definition(
+                Self::declaration(),
+                Definition::Struct {
+                    fields: Fields::NamedFields(fields),
+                },
+                definitions,
+            );
+            Pubkey::add_definitions_recursively(definitions);
+            Milestone::add_definitions_recursively(definitions);
+        }
+
+        fn declaration() -> Declaration {
+            "MilestoneData".to_string()
+        }
+    }
+}
+
+impl VestingData {
+    pub const LEN: usize = 96;
+    pub fn from_bytes(src: &[u8; 96]) -> Self {
+        VestingData::try_from_slice(src).unwrap()
+    }
+
+    pub fn to_bytes(&self) -> [u8; 96] {
+        let mut dst = [0; 96];
+        self.serialize(&mut dst.as_mut_slice()).unwrap();
         dst
     }
 
     pub fn get_seed(&self) -> [u8; 32] {
         get_seed(&self.to_bytes())
     }
+
+    // nothing vests before the cliff, then token_qty vests linearly from start until end
+    pub fn vested_amount(&self, now: i64) -> Result<u64, ProgramError> {
+        if now < self.cliff {
+            Ok(0)
+        } else if now >= self.end {
+            Ok(self.token_qty)
+        } else {
+            let elapsed = math::elapsed(self.start, now)?;
+            let duration = math::elapsed(self.start, self.end)?;
+            math::mul_div(self.token_qty, elapsed, duration, Rounding::Down)
+        }
+    }
+}
+
+// multisig data format: [0 = m, 1 = n, 2.. = MAX_SIGNERS signer pubkeys, zeroed after the first n]
+impl MultisigData {
+    pub const LEN: usize = 354;
+    pub const MAX_SIGNERS: usize = 11;
+
+    pub fn to_bytes(&self) -> [u8; 354] {
+        assert!(
+            self.signers.len() <= MultisigData::MAX_SIGNERS,
+            "multisig has more than MAX_SIGNERS signers"
+        );
+        let mut dst = [0; 354];
+        self.serialize(&mut dst.as_mut_slice()).unwrap();
+        dst
+    }
+
+    pub fn get_seed(&self) -> [u8; 32] {
+        get_seed(&self.to_bytes())
+    }
+}
+
+impl BorshSerialize for MultisigData {
+    fn serialize<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        if self.signers.len() > MultisigData::MAX_SIGNERS {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        }
+        self.m.serialize(writer)?;
+        (self.signers.len() as u8).serialize(writer)?;
+        self.signers
+            .iter()
+            .chain(std::iter::repeat(&Pubkey::default()))
+            .take(MultisigData::MAX_SIGNERS)
+            .try_for_each(|signer| signer.serialize(writer))
+    }
+}
+
+impl BorshDeserialize for MultisigData {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let m = u8::deserialize_reader(reader)?;
+        let n = u8::deserialize_reader(reader)? as usize;
+        if n > MultisigData::MAX_SIGNERS {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+        let mut signers = (0..MultisigData::MAX_SIGNERS)
+            .map(|_| Pubkey::deserialize_reader(reader))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        signers.truncate(n);
+        Ok(MultisigData { m, signers })
+    }
+}
+
+// describes the format above, MAX_SIGNERS slots after a u8 count where a borsh Vec has a u32
+#[cfg(feature = "idl")]
+mod multisig_schema {
+    use super::MultisigData;
+    use borsh::schema::{Declaration, Definition, Fields};
+    use borsh::BorshSchema;
+    use solana_program::pubkey::Pubkey;
+    use std::collections::HashMap;
+
+    impl BorshSchema for MultisigData {
+        fn add_definitions_recursively(definitions: &mut HashMap<Declaration, Definition>) {
+            let fields = vec![
+                ("m".to_string(), u8::declaration()),
+                ("n".to_string(), u8::declaration()),
+                ("signers".to_string(), <[Pubkey; 11]>::declaration()),
+            ];
+            Self::add_definition(
+                Self::declaration(),
+                Definition::Struct {
+                    fields: Fields::NamedFields(fields),
+                },
+                definitions,
+            );
+            <[Pubkey; 11]>::add_definitions_recursively(definitions);
+        }
+
+        fn declaration() -> Declaration {
+            "MultisigData".to_string()
+        }
+    }
 }
 
 impl Sealed for EscrowPDA {}
 
+// byte offsets of fields in the packed EscrowPDA, for memcmp queries over program accounts
+impl EscrowPDA {
+    pub const SELLER_MAIN_OFFSET: usize = 0;
+    pub const SELLER_TEMP_OFFSET: usize = 32;
+    pub const SELLER_RECEIVE_OFFSET: usize = 64;
+    pub const TOKEN_TYPE_OFFSET: usize = 96;
+    pub const TOKEN_QTY_OFFSET: usize = 128;
+    pub const STRIKE_TYPE_OFFSET: usize = 136;
+    pub const STRIKE_QTY_OFFSET: usize = 168;
+    pub const INDEX_SEED_OFFSET: usize = 176;
+    pub const OPERATOR_OFFSET: usize = 208;
+    pub const RENT_PAYER_OFFSET: usize = 241;
+    pub const BUMP_OFFSET: usize = 273;
+
+    pub const V1_LEN: usize = 208;
+    pub const V1_OPERATOR_LEN: usize = 241;
+    pub const V2_LEN: usize = 273;
+    // every escrow account size still in use, oldest first
+    pub const DATA_LENS: [usize; 4] = [
+        EscrowPDA::V1_LEN,
+        EscrowPDA::V1_OPERATOR_LEN,
+        EscrowPDA::V2_LEN,
+        EscrowPDA::LEN,
+    ];
+
+    // the escrow's address and bump for an account of data_len bytes, see EscrowView::address
+    pub fn address(&self, program_id: &Pubkey, data_len: usize) -> Option<(Pubkey, u8)> {
+        EscrowView::from(self).address(program_id, data_len)
+    }
+}
+
 impl Pack for EscrowPDA {
+    const LEN: usize = 274;
+
+    // any format in DATA_LENS, see EscrowView::read
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(EscrowPDA::from(&EscrowView::read(src)?))
+    }
+
+    // writes the leading fields only when dst is sized for an older format
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let view = EscrowView::from(self);
+        dst.copy_from_slice(&bytemuck::bytes_of(&view)[..dst.len()]);
+    }
+}
+
+// zero-copy view of a current format EscrowPDA, field for field the layout above.
+// every field has alignment 1 so it can be cast from account data at any offset
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct EscrowView {
+    pub seller_main: Pubkey,
+    pub seller_temp: Pubkey,
+    pub seller_receive: Pubkey,
+    pub token_type: Pubkey,
+    token_qty: [u8; 8],
+    pub strike_type: Pubkey,
+    strike_qty: [u8; 8],
+    pub index_seed: [u8; 32],
+    operator_tag: u8,
+    operator: Pubkey,
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+impl EscrowView {
+    pub fn load(src: &[u8]) -> Result<&Self, ProgramError> {
+        let view: &Self =
+            bytemuck::try_from_bytes(src).map_err(|_| ProgramError::InvalidAccountData)?;
+        if view.operator_tag > 1 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(view)
+    }
+
+    // a copy of an escrow account of any size in EscrowPDA::DATA_LENS. older formats are read as
+    // the current one with the missing fields zeroed, then rent is owed to seller_main if the
+    // account predates rent_payer
+    pub fn read(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() == EscrowPDA::LEN {
+            return Ok(*Self::load(src)?);
+        }
+        if !EscrowPDA::DATA_LENS.contains(&src.len()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut data = [0; EscrowPDA::LEN];
+        data[..src.len()].copy_from_slice(src);
+        let mut view = *Self::load(&data)?;
+        if src.len() < EscrowPDA::V2_LEN {
+            view.rent_payer = view.seller_main;
+        }
+        Ok(view)
+    }
+
+    pub fn load_mut(src: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        let view: &mut Self =
+            bytemuck::try_from_bytes_mut(src).map_err(|_| ProgramError::InvalidAccountData)?;
+        if view.operator_tag > 1 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(view)
+    }
+
+    pub fn token_qty(&self) -> u64 {
+        u64::from_le_bytes(self.token_qty)
+    }
+
+    pub fn set_token_qty(&mut self, token_qty: u64) {
+        self.token_qty = token_qty.to_le_bytes();
+    }
+
+    pub fn strike_qty(&self) -> u64 {
+        u64::from_le_bytes(self.strike_qty)
+    }
+
+    pub fn set_strike_qty(&mut self, strike_qty: u64) {
+        self.strike_qty = strike_qty.to_le_bytes();
+    }
+
+    pub fn offer_data(&self) -> OfferData {
+        OfferData {
+            token_type: self.token_type,
+            token_qty: self.token_qty(),
+            strike_type: self.strike_type,
+            strike_qty: self.strike_qty(),
+        }
+    }
+
+    // offer_data().get_seed() without the copy, the offer fields are laid out as OfferData
+    pub fn offer_seed(&self) -> [u8; 32] {
+        get_seed(
+            &bytemuck::bytes_of(self)[EscrowPDA::TOKEN_TYPE_OFFSET..EscrowPDA::INDEX_SEED_OFFSET],
+        )
+    }
+
+    // the escrow's address and bump for an account of data_len bytes, None if the stored bump
+    // gives no address. current accounts store the bump, older ones predate it and search for it
+    pub fn address(&self, program_id: &Pubkey, data_len: usize) -> Option<(Pubkey, u8)> {
+        let seed = self.offer_seed();
+        if data_len == EscrowPDA::LEN {
+            let seeds: &[&[u8]] = &[&seed, &self.index_seed, &[self.bump]];
+            let pda = create_address(seeds, program_id)?;
+            return Some((pda, self.bump));
+        }
+        Some(find_address(&[&seed, &self.index_seed], program_id))
+    }
+
+    pub fn operator(&self) -> Option<Pubkey> {
+        (self.operator_tag == 1).then_some(self.operator)
+    }
+
+    // keeps the zero padding after a none tag
+    pub fn set_operator(&mut self, operator: Option<Pubkey>) {
+        self.operator_tag = operator.is_some() as u8;
+        self.operator = operator.unwrap_or_default();
+    }
+}
+
+impl From<&EscrowView> for EscrowPDA {
+    fn from(view: &EscrowView) -> Self {
+        EscrowPDA {
+            seller_main: view.seller_main,
+            seller_temp: view.seller_temp,
+            seller_receive: view.seller_receive,
+            offer_data: view.offer_data(),
+            index_seed: view.index_seed,
+            operator: view.operator(),
+            rent_payer: view.rent_payer,
+            bump: view.bump,
+        }
+    }
+}
+
+impl From<&EscrowPDA> for EscrowView {
+    fn from(escrow: &EscrowPDA) -> Self {
+        let mut view = EscrowView {
+            seller_main: escrow.seller_main,
+            seller_temp: escrow.seller_temp,
+            seller_receive: escrow.seller_receive,
+            token_type: escrow.offer_data.token_type,
+            token_qty: [0; 8],
+            strike_type: escrow.offer_data.strike_type,
+            strike_qty: [0; 8],
+            index_seed: escrow.index_seed,
+            operator_tag: 0,
+            operator: Pubkey::default(),
+            rent_payer: escrow.rent_payer,
+            bump: escrow.bump,
+        };
+        view.set_token_qty(escrow.offer_data.token_qty);
+        view.set_strike_qty(escrow.offer_data.strike_qty);
+        view.set_operator(escrow.operator);
+        view
+    }
+}
+
+// hash time-locked swap: released to htlc_data.buyer against the sha256 preimage of
+// hashlock before expiry (unix timestamp), refundable to seller_main from expiry onwards
+#[derive(Debug, PartialEq)]
+pub struct HtlcPDA {
+    pub htlc_data: HtlcData,
+    pub seller_main: Pubkey,
+    pub seller_temp: Pubkey,
+    pub index_seed: [u8; 32],
+}
+
+impl Sealed for HtlcPDA {}
+
+impl Pack for HtlcPDA {
     const LEN: usize = 208;
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src: &[u8; 208] = src.try_into().unwrap();
-        let (seller_main, seller_temp, seller_receive, offer_data, index_seed) =
-            array_refs![src, 32, 32, 32, 80, 32];
-        let seller_main = Pubkey::new_from_array(*seller_main);
-        let seller_temp = Pubkey::new_from_array(*seller_temp);
-        let seller_receive = Pubkey::new_from_array(*seller_receive);
-        let offer_data = OfferData::from_bytes(offer_data);
-
-        Ok(EscrowPDA {
-            offer_data,
-            seller_main,
-            seller_temp,
-            seller_receive,
+        let src: &[u8; 208] = src
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        let (seller_main, seller_temp, htlc_data, index_seed) = array_refs![src, 32, 32, 112, 32];
+
+        Ok(HtlcPDA {
+            htlc_data: HtlcData::from_bytes(htlc_data),
+            seller_main: Pubkey::new_from_array(*seller_main),
+            seller_temp: Pubkey::new_from_array(*seller_temp),
             index_seed: *index_seed,
         })
     }
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
         let dst: &mut [u8; 208] = dst.try_into().unwrap();
-        let (seller_main, seller_temp, seller_receive, offer_data, index_seed) =
-            mut_array_refs![dst, 32, 32, 32, 80, 32];
+        let (seller_main, seller_temp, htlc_data, index_seed) =
+            mut_array_refs![dst, 32, 32, 112, 32];
 
         seller_main.copy_from_slice(&self.seller_main.to_bytes());
         seller_temp.copy_from_slice(&self.seller_temp.to_bytes());
-        seller_receive.copy_from_slice(&self.seller_receive.to_bytes());
-        offer_data.copy_from_slice(&self.offer_data.to_bytes());
+        htlc_data.copy_from_slice(&self.htlc_data.to_bytes());
+        index_seed.copy_from_slice(&self.index_seed);
+    }
+}
+
+// arbiter-mediated escrow: the buyer's deposit in buyer_temp is released to arbiter_data.seller
+// by the buyer, refunded to the buyer by the seller, or split by the arbiter once disputed
+#[derive(Debug, PartialEq)]
+pub struct ArbiterPDA {
+    pub arbiter_data: ArbiterData,
+    pub buyer_main: Pubkey,
+    pub buyer_temp: Pubkey,
+    pub index_seed: [u8; 32],
+    pub disputed: bool,
+}
+
+impl Sealed for ArbiterPDA {}
+
+impl Pack for ArbiterPDA {
+    const LEN: usize = 201;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src: &[u8; 201] = src
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        let (buyer_main, buyer_temp, arbiter_data, index_seed, disputed) =
+            array_refs![src, 32, 32, 104, 32, 1];
+
+        Ok(ArbiterPDA {
+            arbiter_data: ArbiterData::from_bytes(arbiter_data),
+            buyer_main: Pubkey::new_from_array(*buyer_main),
+            buyer_temp: Pubkey::new_from_array(*buyer_temp),
+            index_seed: *index_seed,
+            disputed: disputed[0] != 0,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst: &mut [u8; 201] = dst.try_into().unwrap();
+        let (buyer_main, buyer_temp, arbiter_data, index_seed, disputed) =
+            mut_array_refs![dst, 32, 32, 104, 32, 1];
+
+        buyer_main.copy_from_slice(&self.buyer_main.to_bytes());
+        buyer_temp.copy_from_slice(&self.buyer_temp.to_bytes());
+        arbiter_data.copy_from_slice(&self.arbiter_data.to_bytes());
+        index_seed.copy_from_slice(&self.index_seed);
+        disputed[0] = self.disputed as u8;
+    }
+}
+
+// staged release: the payer approves milestones one at a time, releasing each amount to
+// milestone_data.payee; whatever remains is refundable once the latest deadline has passed
+#[derive(Debug, PartialEq)]
+pub struct MilestonePDA {
+    pub milestone_data: MilestoneData,
+    pub payer_main: Pubkey,
+    pub payer_temp: Pubkey,
+    pub index_seed: [u8; 32],
+    pub released: Vec<bool>,
+}
+
+impl MilestonePDA {
+    // [0..32 = payer_main, 32..64 = payer_temp, 64..96 = index_seed, milestone data, released flags]
+    pub fn len(milestone_count: usize) -> usize {
+        96 + MilestoneData::len(milestone_count) + milestone_count
+    }
+
+    pub fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < MilestonePDA::len(0) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let (header, data) = src.split_at(96);
+        let header: &[u8; 96] = header.try_into().unwrap();
+        let (payer_main, payer_temp, index_seed) = array_refs![header, 32, 32, 32];
+        let milestone_data = MilestoneData::deserialize(&mut &data[..])
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        let count = milestone_data.milestones.len();
+        if src.len() != MilestonePDA::len(count) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let released = data[MilestoneData::len(count)..]
+            .iter()
+            .map(|r| *r != 0)
+            .collect();
+
+        Ok(MilestonePDA {
+            milestone_data,
+            payer_main: Pubkey::new_from_array(*payer_main),
+            payer_temp: Pubkey::new_from_array(*payer_temp),
+            index_seed: *index_seed,
+            released,
+        })
+    }
+
+    pub fn pack_into_slice(&self, dst: &mut [u8]) {
+        let (header, data) = dst.split_at_mut(96);
+        let header: &mut [u8; 96] = header.try_into().unwrap();
+        let (payer_main, payer_temp, index_seed) = mut_array_refs![header, 32, 32, 32];
+        payer_main.copy_from_slice(&self.payer_main.to_bytes());
+        payer_temp.copy_from_slice(&self.payer_temp.to_bytes());
+        index_seed.copy_from_slice(&self.index_seed);
+
+        let milestone_data = self.milestone_data.try_to_vec().unwrap();
+        let (milestones, released) = data.split_at_mut(milestone_data.len());
+        milestones.copy_from_slice(&milestone_data);
+        for (dst, released) in released.iter_mut().zip(&self.released) {
+            *dst = *released as u8;
+        }
+    }
+}
+
+// linear vesting: vesting_data.beneficiary withdraws whatever has vested from grantor_temp,
+// withdrawn tracks the running total released so far
+#[derive(Debug, PartialEq)]
+pub struct VestingPDA {
+    pub vesting_data: VestingData,
+    pub grantor_main: Pubkey,
+    pub grantor_temp: Pubkey,
+    pub index_seed: [u8; 32],
+    pub withdrawn: u64,
+}
+
+impl Sealed for VestingPDA {}
+
+impl Pack for VestingPDA {
+    const LEN: usize = 200;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src: &[u8; 200] = src
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        let (grantor_main, grantor_temp, vesting_data, index_seed, withdrawn) =
+            array_refs![src, 32, 32, 96, 32, 8];
+
+        Ok(VestingPDA {
+            vesting_data: VestingData::from_bytes(vesting_data),
+            grantor_main: Pubkey::new_from_array(*grantor_main),
+            grantor_temp: Pubkey::new_from_array(*grantor_temp),
+            index_seed: *index_seed,
+            withdrawn: u64::from_le_bytes(*withdrawn),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst: &mut [u8; 200] = dst.try_into().unwrap();
+        let (grantor_main, grantor_temp, vesting_data, index_seed, withdrawn) =
+            mut_array_refs![dst, 32, 32, 96, 32, 8];
+
+        grantor_main.copy_from_slice(&self.grantor_main.to_bytes());
+        grantor_temp.copy_from_slice(&self.grantor_temp.to_bytes());
+        vesting_data.copy_from_slice(&self.vesting_data.to_bytes());
+        index_seed.copy_from_slice(&self.index_seed);
+        *withdrawn = self.withdrawn.to_le_bytes();
+    }
+}
+
+// M-of-N signer set: members approve the hash of a pending instruction, which can be executed
+// once m approvals are recorded, signed by the PDA derived from the multisig address
+#[derive(Debug, PartialEq)]
+pub struct MultisigPDA {
+    pub multisig_data: MultisigData,
+    pub index_seed: [u8; 32],
+    pub pending: [u8; 32],
+    pub approvals: u16,
+}
+
+impl MultisigPDA {
+    pub fn authority(multisig: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        find_address(&[&multisig.to_bytes()], program_id)
+    }
+
+    // hash of program id, each account (pubkey, is_signer, is_writable) and instruction data
+    pub fn action_hash(ix: &Instruction) -> [u8; 32] {
+        let mut bytes = ix.program_id.to_bytes().to_vec();
+        for meta in &ix.accounts {
+            bytes.extend_from_slice(&meta.pubkey.to_bytes());
+            bytes.push(meta.is_signer as u8);
+            bytes.push(meta.is_writable as u8);
+        }
+        bytes.extend_from_slice(&ix.data);
+        get_seed(&bytes)
+    }
+}
+
+impl Sealed for MultisigPDA {}
+
+impl Pack for MultisigPDA {
+    const LEN: usize = 420;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src: &[u8; 420] = src
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        let (multisig_data, index_seed, pending, approvals) = array_refs![src, 354, 32, 32, 2];
+
+        Ok(MultisigPDA {
+            multisig_data: MultisigData::try_from_slice(multisig_data)
+                .map_err(|_| ProgramError::InvalidAccountData)?,
+            index_seed: *index_seed,
+            pending: *pending,
+            approvals: u16::from_le_bytes(*approvals),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst: &mut [u8; 420] = dst.try_into().unwrap();
+        let (multisig_data, index_seed, pending, approvals) = mut_array_refs![dst, 354, 32, 32, 2];
+
+        multisig_data.copy_from_slice(&self.multisig_data.to_bytes());
         index_seed.copy_from_slice(&self.index_seed);
+        pending.copy_from_slice(&self.pending);
+        *approvals = self.approvals.to_le_bytes();
+    }
+}
+
+impl LimitOrder {
+    pub const PRICE_DECIMALS: u8 = 9;
+
+    // the offer create_offer stores for this order, strike rounded up in the seller's favour.
+    // errors if the strike does not fit in a u64, a zero strike is left to the caller
+    pub fn offer_data(
+        &self,
+        token_decimals: u8,
+        strike_decimals: u8,
+    ) -> Result<OfferData, ProgramError> {
+        let denominator = math::mul(
+            math::pow10(token_decimals)?,
+            math::pow10(LimitOrder::PRICE_DECIMALS)?,
+        )?;
+        let strike_qty = math::mul_div_wide(
+            self.token_qty as u128 * self.price as u128,
+            math::pow10(strike_decimals)?,
+            denominator,
+            Rounding::Up,
+        )?;
+        Ok(OfferData {
+            token_type: self.token_type,
+            token_qty: self.token_qty,
+            strike_type: self.strike_type,
+            strike_qty: math::to_u64(strike_qty)?,
+        })
+    }
+}
+
+// renders a LimitOrder::PRICE_DECIMALS fixed point price for display, e.g. 1500000000 as "1.5"
+pub fn format_price(price: u64) -> String {
+    let scale = 10u64.pow(LimitOrder::PRICE_DECIMALS as u32);
+    let fraction = format!(
+        "{:0width$}",
+        price % scale,
+        width = LimitOrder::PRICE_DECIMALS as usize
+    );
+    match fraction.trim_end_matches('0') {
+        "" => format!("{}", price / scale),
+        fraction => format!("{}.{}", price / scale, fraction),
+    }
+}
+
+impl PeggedData {
+    pub const LEN: usize = 110;
+
+    pub fn get_seed(&self) -> [u8; 32] {
+        get_seed(&self.try_to_vec().unwrap())
+    }
+
+    // strike owed for token_qty at the oracle price plus spread, rounded up in the seller's
+    // favour. errors if it does not fit in a u64
+    pub fn strike_qty(&self, oracle: &OraclePrice) -> Result<u64, ProgramError> {
+        let spread = u128::try_from(10_000 + self.spread_bps as i128)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        let denominator = math::mul(math::pow10(oracle.expo)?, 10_000)?;
+        let strike = math::mul_div_wide(
+            self.token_qty as u128 * oracle.price as u128,
+            spread,
+            denominator,
+            Rounding::Up,
+        )?;
+        math::to_u64(strike)
+    }
+
+    // the offer as events report it, strike_qty is 0 until an accept prices it
+    pub fn offer_data(&self, strike_qty: u64) -> OfferData {
+        OfferData {
+            token_type: self.token_type,
+            token_qty: self.token_qty,
+            strike_type: self.strike_type,
+            strike_qty,
+        }
+    }
+}
+
+// oracle account format (borsh, leading bytes only): [0..8 = price (u64), 8 = expo (u8),
+// 9..17 = publish_time (i64 unix timestamp)]. price is strike base units per token base unit
+// scaled by 10^expo. the program only reads the account, any feed keeping this layout works
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone, Copy)]
+pub struct OraclePrice {
+    pub price: u64,
+    pub expo: u8,
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    pub const LEN: usize = 17;
+
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        src.get(..OraclePrice::LEN)
+            .and_then(|src| OraclePrice::try_from_slice(src).ok())
+            .ok_or(ProgramError::InvalidAccountData)
+    }
+}
+
+// oracle pegged escrow: seller_temp is released against the strike priced at accept time,
+// or returned to seller_main on cancel. format (borsh): [0..32 = seller_main,
+// 32..64 = seller_temp, 64..96 = seller_receive, 96..206 = pegged_data, 206..238 = index_seed,
+// 238 = bump]
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct PeggedPDA {
+    pub seller_main: Pubkey,
+    pub seller_temp: Pubkey,
+    pub seller_receive: Pubkey,
+    pub pegged_data: PeggedData,
+    pub index_seed: [u8; 32],
+    pub bump: u8,
+}
+
+impl PeggedPDA {
+    // the offer fields sit at the same offsets as in EscrowPDA
+    pub const SELLER_MAIN_OFFSET: usize = 0;
+    pub const TOKEN_TYPE_OFFSET: usize = 96;
+    pub const STRIKE_TYPE_OFFSET: usize = 136;
+
+    // the escrow address under the stored bump, None if the seeds are off the curve
+    pub fn address(&self, program_id: &Pubkey) -> Option<Pubkey> {
+        let seeds: &[&[u8]] = &[&self.pegged_data.get_seed(), &self.index_seed, &[self.bump]];
+        create_address(seeds, program_id)
+    }
+}
+
+impl Sealed for PeggedPDA {}
+
+impl Pack for PeggedPDA {
+    const LEN: usize = 239;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        PeggedPDA::try_from_slice(src).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        self.serialize(&mut &mut dst[..]).unwrap();
+    }
+}
+
+// program wide settings at [ConfigPDA::SEED], initialised before any offer. paused stops new
+// escrows and payouts while refunds stay open, allow_list limits offers to AllowedMintPDA mints,
+// pending_admin is the proposed admin or Pubkey::default().
+// format (borsh): [0..32 = admin, 32 = paused, 33 = allow_list, 34 = bump, 35..67 = pending_admin]
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ConfigPDA {
+    pub admin: Pubkey,
+    pub paused: bool,
+    pub allow_list: bool,
+    pub bump: u8,
+    pub pending_admin: Pubkey,
+}
+
+impl ConfigPDA {
+    pub const SEED: &'static [u8] = b"config";
+}
+
+impl Sealed for ConfigPDA {}
+
+impl Pack for ConfigPDA {
+    const LEN: usize = 67;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        ConfigPDA::try_from_slice(src).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        self.serialize(&mut &mut dst[..]).unwrap();
+    }
+}
+
+// an admin listed mint, at [AllowedMintPDA::SEED, mint]. format (borsh): [0..32 = mint, 32 = bump]
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct AllowedMintPDA {
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
+impl AllowedMintPDA {
+    pub const SEED: &'static [u8] = b"allowed_mint";
+}
+
+impl Sealed for AllowedMintPDA {}
+
+impl Pack for AllowedMintPDA {
+    const LEN: usize = 33;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        AllowedMintPDA::try_from_slice(src).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        self.serialize(&mut &mut dst[..]).unwrap();
     }
 }
 
 pub fn get_seed(bytes: &[u8]) -> [u8; 32] {
     let mut hasher = Sha256::new();
     hasher.update(bytes);
-    let seed: [u8; 32] = hasher.finalize().try_into().unwrap();
+    let seed: [u8; 32] = hasher.finalize().into();
     seed
 }
+
+// every address the program derives goes through create_address or find_address. the sbf
+// runtime charges create_program_address_units for each address tried, one for a known bump and
+// 256 - bump for a search, and native builds count those tries for tests/bench.rs
+#[cfg(not(target_os = "solana"))]
+pub static ADDRESS_ATTEMPTS: AtomicU64 = AtomicU64::new(0);
+
+pub fn create_address(seeds: &[&[u8]], program_id: &Pubkey) -> Option<Pubkey> {
+    #[cfg(not(target_os = "solana"))]
+    ADDRESS_ATTEMPTS.fetch_add(1, Ordering::Relaxed);
+    Pubkey::create_program_address(seeds, program_id).ok()
+}
+
+pub fn find_address(seeds: &[&[u8]], program_id: &Pubkey) -> (Pubkey, u8) {
+    let (address, bump) = Pubkey::find_program_address(seeds, program_id);
+    #[cfg(not(target_os = "solana"))]
+    ADDRESS_ATTEMPTS.fetch_add(256 - u64::from(bump), Ordering::Relaxed);
+    (address, bump)
+}
@@ -0,0 +1,902 @@
+#![cfg(feature = "test-bpf")]
+
+// compute unit budgets per instruction. `cargo test --features test-bpf --test bench` runs the
+// native processor, which only meters syscalls and the CPIs into the (sbf) token and system
+// programs. the addresses the program tries while deriving are counted and added at the
+// CREATE_PROGRAM_ADDRESS_UNITS sbf charges for each, the rest of the program is not measured.
+// `cargo test-sbf --features test-bpf --test bench` loads target/deploy/sol_escrow.so and meters
+// the whole program against the sbf column. the report of the last run is written to
+// bench_output.txt in the cargo target tmpdir
+
+use sol_escrow::{
+    entrypoint::process_instruction,
+    instruction,
+    processor::{MAX_CANCEL_BATCH, MAX_CREATE_BATCH},
+    state::{
+        get_seed, ArbiterData, ArbiterPDA, EscrowPDA, HtlcData, HtlcPDA, LimitOrder, Milestone,
+        MilestoneData, MilestonePDA, MultisigData, MultisigPDA, OfferData, OraclePrice, PeggedData,
+        PeggedPDA, VestingData, VestingPDA, ADDRESS_ATTEMPTS,
+    },
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account as SolanaAccount,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::Clock,
+    instruction::Instruction,
+    packet::PACKET_DATA_SIZE,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signer::{
+        keypair::{keypair_from_seed, Keypair},
+        Signer,
+    },
+    transaction::{Transaction, TransactionError},
+};
+use spl_token::state::{Account, AccountState, Mint};
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
+
+const REPORT_PATH: &str = concat!(env!("CARGO_TARGET_TMPDIR"), "/bench_output.txt");
+
+// the compute limit of an instruction sent without a compute budget request
+const DEFAULT_UNIT_LIMIT: u64 = 200_000;
+
+// ComputeBudget::create_program_address_units, charged per address tried
+const CREATE_PROGRAM_ADDRESS_UNITS: u64 = 1_500;
+
+// (instruction, native budget, sbf budget). new instructions get a row and a measurement below.
+// the native budgets are the measured units rounded up to 500. no sbf run has been recorded yet,
+// an unset sbf budget fails that run, and its report gives the units to set the budget from
+const BUDGETS: &[(&str, u64, Option<u64>)] = &[
+    ("OFFER", 6_500, None),
+    ("ACCEPT", 16_000, None),
+    ("CANCEL", 4_500, None),
+    ("ACCEPT_V2", 17_500, None),
+    ("CANCEL_V2", 6_000, None),
+    ("CONFIG_INIT", 3_500, None),
+    ("SET_PAUSED", 2_000, None),
+    ("PROPOSE_ADMIN", 2_000, None),
+    ("ACCEPT_ADMIN", 2_000, None),
+    ("SET_ALLOW_LIST", 2_000, None),
+    ("ALLOW_MINT", 6_500, None),
+    ("DISALLOW_MINT", 3_500, None),
+    ("OFFER_ALLOWED", 11_000, None),
+    ("SET_OPERATOR", 2_000, None),
+    ("AMEND", 8_000, None),
+    // full batches, which have to fit the default limit under sbf
+    ("CANCEL_MANY", 35_500, Some(DEFAULT_UNIT_LIMIT)),
+    ("CREATE_MANY", 51_000, Some(DEFAULT_UNIT_LIMIT)),
+    ("LIMIT_OFFER", 9_500, None),
+    ("PEGGED_OFFER", 6_500, None),
+    ("PEGGED_ACCEPT", 16_000, None),
+    ("PEGGED_CANCEL", 4_500, None),
+    ("HTLC_OFFER", 6_500, None),
+    ("HTLC_CLAIM", 11_000, None),
+    ("HTLC_REFUND", 5_000, None),
+    ("ARBITER_OFFER", 9_500, None),
+    ("ARBITER_RELEASE", 14_000, None),
+    ("ARBITER_REFUND", 7_500, None),
+    ("ARBITER_DISPUTE", 3_500, None),
+    ("ARBITER_RESOLVE", 17_500, None),
+    ("MILESTONE_OFFER", 6_500, None),
+    ("MILESTONE_APPROVE", 11_000, None),
+    ("MILESTONE_REFUND", 5_000, None),
+    ("VESTING_OFFER", 6_500, None),
+    ("VESTING_WITHDRAW", 11_000, None),
+    ("MULTISIG_CREATE", 5_000, None),
+    ("MULTISIG_APPROVE", 5_000, None),
+    ("MULTISIG_EXECUTE", 11_000, None),
+];
+
+struct BenchEnv {
+    ctx: ProgramTestContext,
+    program_id: Pubkey,
+    seller: Keypair,
+    buyer: Keypair,
+    admin: Keypair,
+    arbiter: Keypair,
+    mint_1: Pubkey,
+    mint_2: Pubkey,
+}
+
+#[tokio::test]
+async fn test_compute_budgets() {
+    let sbf = std::env::var("BPF_OUT_DIR").is_ok() || std::env::var("SBF_OUT_DIR").is_ok();
+    let mut env = init_env().await;
+    let (program_id, seller, buyer) = (env.program_id, env.seller.pubkey(), env.buyer.pubkey());
+    let seller_receive = add_token_account(&mut env.ctx, env.mint_2, seller, 0);
+    let buyer_strike = add_token_account(&mut env.ctx, env.mint_2, buyer, 100);
+    let buyer_receive = add_token_account(&mut env.ctx, env.mint_1, buyer, 0);
+    let mut measured = vec![];
+
+    // offers need an initialised config
+    let ix = instruction::config_init(&program_id, &env.admin.pubkey());
+    let admin = copy(&env.admin);
+    measured.push(("CONFIG_INIT", send(&mut env, ix, &[&admin]).await));
+
+    let (pda, escrow, units) = open_offer(&mut env, seller_receive, 0).await;
+    measured.push(("OFFER", units));
+    let ix = instruction::accept(
+        &program_id,
+        &buyer,
+        &buyer_strike,
+        &buyer_receive,
+        &pda,
+        &escrow,
+    );
+    let signer = copy(&env.buyer);
+    let accept = send_counting(&mut env, ix, &[&signer]).await;
+    measured.push(("ACCEPT", accept.0));
+
+    let (pda, escrow, _) = open_offer(&mut env, seller_receive, 1).await;
+    let ix = instruction::cancel(&program_id, &pda, &escrow);
+    let signer = copy(&env.seller);
+    let cancel = send_counting(&mut env, ix, &[&signer]).await;
+    measured.push(("CANCEL", cancel.0));
+
+    // the same instructions against v2 accounts, which search for the bump instead of storing it
+    // and are zero padded to the current size before EscrowView reads them. their bumps are below
+    // 255, so the search tries more than the one address a stored bump does
+    let contract_no = searched_contract_no(&env, 100);
+    let (pda, escrow, _) = open_offer(&mut env, seller_receive, contract_no).await;
+    write_v2(&mut env, pda, &escrow).await;
+    let ix = instruction::accept(
+        &program_id,
+        &buyer,
+        &buyer_strike,
+        &buyer_receive,
+        &pda,
+        &escrow,
+    );
+    let signer = copy(&env.buyer);
+    let accept_v2 = send_counting(&mut env, ix, &[&signer]).await;
+    measured.push(("ACCEPT_V2", accept_v2.0));
+    let accept_saving = 255 - u64::from(escrow.bump);
+
+    let contract_no = searched_contract_no(&env, contract_no + 1);
+    let (pda, escrow, _) = open_offer(&mut env, seller_receive, contract_no).await;
+    write_v2(&mut env, pda, &escrow).await;
+    let ix = instruction::cancel(&program_id, &pda, &escrow);
+    let signer = copy(&env.seller);
+    let cancel_v2 = send_counting(&mut env, ix, &[&signer]).await;
+    measured.push(("CANCEL_V2", cancel_v2.0));
+    let cancel_saving = 255 - u64::from(escrow.bump);
+
+    // stored bump rejects it
+    // stored bump rejects it. this holds natively too, where the search is not metered
+    let (pda, escrow, _) = open_offer(&mut env, seller_receive, 4).await;
+    let mut account = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data[EscrowPDA::BUMP_OFFSET] = escrow.bump.wrapping_sub(1);
+    env.ctx.set_account(&pda, &account.into());
+    let ix = instruction::cancel(&program_id, &pda, &escrow);
+    let signer = copy(&env.seller);
+    assert!(
+        try_send(&mut env, ix, &[&signer]).await.is_err(),
+        "wrong stored bump was not rejected"
+    );
+
+    measure_admin(&mut env, seller_receive, &mut measured).await;
+    measure_batches(&mut env, seller_receive, &mut measured).await;
+    measure_offer_types(&mut env, seller_receive, &mut measured).await;
+    measure_escrow_types(&mut env, &mut measured).await;
+
+    let mut report = if sbf {
+        "compute units (sbf)\n".to_string()
+    } else {
+        "compute units (native cpis and syscalls, addresses tried)\n".to_string()
+    };
+    let (mut over, mut unset) = (vec![], vec![]);
+    for (name, units) in &measured {
+        let &(_, native, sbf_budget) = BUDGETS.iter().find(|(n, _, _)| n == name).unwrap();
+        let budget = if sbf { sbf_budget } else { Some(native) };
+        let Some(budget) = budget else {
+            writeln!(report, "{:<18} {:>8} / {:>8}", name, units, "unset").unwrap();
+            unset.push(*name);
+            continue;
+        };
+        writeln!(report, "{:<18} {:>8} / {:>8}", name, units, budget).unwrap();
+        if *units > budget {
+            over.push(*name);
+        }
+    }
+    // v2 -> current. under sbf the units are compared, natively the addresses tried, which have
+    // to drop by exactly the tries the v2 search made past the stored bump
+    let mut no_saving = vec![];
+    if sbf {
+        writeln!(report, "\nstored bump saving (units)").unwrap();
+    } else {
+        writeln!(
+            report,
+            "\nstored bump saving (addresses tried, {} sbf units each)",
+            CREATE_PROGRAM_ADDRESS_UNITS
+        )
+        .unwrap();
+    }
+    let pairs = [
+        ("ACCEPT", accept, accept_v2, accept_saving),
+        ("CANCEL", cancel, cancel_v2, cancel_saving),
+    ];
+    for (name, (units, tries), (v2_units, v2_tries), saving) in pairs {
+        if sbf {
+            writeln!(report, "{:<18} {:>8} -> {:>8}", name, v2_units, units).unwrap();
+            if units >= v2_units {
+                no_saving.push(name);
+            }
+            continue;
+        }
+        writeln!(
+            report,
+            "{:<18} {:>8} -> {:>8} ({} units)",
+            name,
+            v2_tries,
+            tries,
+            (v2_tries - tries) * CREATE_PROGRAM_ADDRESS_UNITS
+        )
+        .unwrap();
+        if saving == 0 || v2_tries != tries + saving {
+            no_saving.push(name);
+        }
+    }
+    std::fs::write(REPORT_PATH, &report).unwrap();
+    print!("{}", report);
+    assert!(over.is_empty(), "over compute budget: {:?}", over);
+    assert!(unset.is_empty(), "no sbf budget: {:?}", unset);
+    assert!(
+        no_saving.is_empty(),
+        "stored bump saved nothing: {:?}",
+        no_saving
+    );
+    assert_eq!(
+        measured.len(),
+        BUDGETS.len(),
+        "every budget needs a measurement"
+    );
+}
+
+// the config instructions, and an offer made while the allow-list is on
+async fn measure_admin(
+    env: &mut BenchEnv,
+    seller_receive: Pubkey,
+    measured: &mut Vec<(&str, u64)>,
+) {
+    let program_id = env.program_id;
+    let admin = copy(&env.admin);
+    let ix = instruction::set_paused(&program_id, &admin.pubkey(), true);
+    measured.push(("SET_PAUSED", send(env, ix, &[&admin]).await));
+    let ix = instruction::set_paused(&program_id, &admin.pubkey(), false);
+    send(env, ix, &[&admin]).await;
+
+    let new_admin = keypair_from_seed(&[5; 32]).unwrap();
+    let ix = instruction::propose_admin(&program_id, &admin.pubkey(), &new_admin.pubkey());
+    measured.push(("PROPOSE_ADMIN", send(env, ix, &[&admin]).await));
+    let ix = instruction::accept_admin(&program_id, &new_admin.pubkey());
+    measured.push(("ACCEPT_ADMIN", send(env, ix, &[&new_admin]).await));
+    let admin = new_admin;
+
+    let ix = instruction::set_allow_list(&program_id, &admin.pubkey(), true);
+    measured.push(("SET_ALLOW_LIST", send(env, ix, &[&admin]).await));
+    // the admin pays the AllowedMintPDA rent
+    let fund = solana_sdk::system_instruction::transfer(
+        &env.ctx.payer.pubkey(),
+        &admin.pubkey(),
+        100_000_000,
+    );
+    let payer = copy(&env.ctx.payer);
+    send(env, fund, &[&payer]).await;
+    let ix = instruction::allow_mint(&program_id, &admin.pubkey(), &env.mint_1);
+    measured.push(("ALLOW_MINT", send(env, ix, &[&admin]).await));
+    let ix = instruction::allow_mint(&program_id, &admin.pubkey(), &env.mint_2);
+    send(env, ix, &[&admin]).await;
+
+    let mints = [env.mint_1, env.mint_2];
+    let (_, _, units) = open_offer_with(env, seller_receive, 5, &mints).await;
+    measured.push(("OFFER_ALLOWED", units));
+
+    let ix = instruction::disallow_mint(&program_id, &admin.pubkey(), &env.mint_2);
+    measured.push(("DISALLOW_MINT", send(env, ix, &[&admin]).await));
+    let ix = instruction::set_allow_list(&program_id, &admin.pubkey(), false);
+    send(env, ix, &[&admin]).await;
+    env.admin = admin;
+}
+
+// the operator instructions and full CANCEL_MANY and CREATE_MANY batches
+async fn measure_batches(
+    env: &mut BenchEnv,
+    seller_receive: Pubkey,
+    measured: &mut Vec<(&str, u64)>,
+) {
+    let program_id = env.program_id;
+    let seller = copy(&env.seller);
+    let operator = copy(&env.arbiter);
+
+    let (pda, escrow, _) = open_offer(env, seller_receive, 6).await;
+    let ix = instruction::set_operator(&program_id, &pda, &escrow, Some(&operator.pubkey()));
+    measured.push(("SET_OPERATOR", send(env, ix, &[&seller]).await));
+    let escrow = EscrowPDA {
+        operator: Some(operator.pubkey()),
+        ..escrow
+    };
+    let ix = instruction::amend(&program_id, &pda, &escrow, 12, Some(&operator.pubkey()));
+    measured.push(("AMEND", send(env, ix, &[&operator]).await));
+
+    let mut escrows = vec![];
+    for contract_no in 0..MAX_CANCEL_BATCH as u64 {
+        let (pda, escrow, _) = open_offer(env, seller_receive, 10 + contract_no).await;
+        escrows.push((pda, escrow));
+    }
+    let batch: Vec<_> = escrows.iter().map(|(pda, escrow)| (pda, escrow)).collect();
+    let ix = instruction::cancel_many(&program_id, &batch);
+    measured.push(("CANCEL_MANY", send(env, ix, &[&seller]).await));
+
+    let token_qty = 5;
+    let funding = add_token_account(
+        &mut env.ctx,
+        env.mint_1,
+        seller.pubkey(),
+        token_qty * MAX_CREATE_BATCH as u64,
+    );
+    let offers: Vec<_> = (0..MAX_CREATE_BATCH as u64)
+        .map(|contract_no| {
+            let seller_temp = add_token_account(&mut env.ctx, env.mint_1, seller.pubkey(), 0);
+            let offer_data = OfferData {
+                token_type: env.mint_1,
+                token_qty,
+                strike_type: env.mint_2,
+                strike_qty: 10 + contract_no,
+            };
+            let index_seed = instruction::index_seed(&seller.pubkey(), 0, 30 + contract_no);
+            (seller_temp, offer_data, index_seed)
+        })
+        .collect();
+    let ix = instruction::create_many(
+        &program_id,
+        &seller.pubkey(),
+        &funding,
+        &seller_receive,
+        &offers,
+    );
+    measured.push(("CREATE_MANY", send(env, ix, &[&seller]).await));
+}
+
+// the offers priced from a limit order or an oracle
+async fn measure_offer_types(
+    env: &mut BenchEnv,
+    seller_receive: Pubkey,
+    measured: &mut Vec<(&str, u64)>,
+) {
+    let program_id = env.program_id;
+    let seller = copy(&env.seller);
+    let buyer = copy(&env.buyer);
+
+    let seller_temp = add_token_account(&mut env.ctx, env.mint_1, seller.pubkey(), 5);
+    let limit_order = LimitOrder {
+        token_type: env.mint_1,
+        token_qty: 5,
+        strike_type: env.mint_2,
+        price: 2 * 10u64.pow(LimitOrder::PRICE_DECIMALS as u32),
+    };
+    let ix = instruction::limit_offer(
+        &program_id,
+        &seller.pubkey(),
+        &seller_temp,
+        &seller_receive,
+        &limit_order,
+        (1, 1),
+        &instruction::index_seed(&seller.pubkey(), 0, 40),
+    )
+    .unwrap();
+    measured.push(("LIMIT_OFFER", send(env, ix, &[&seller]).await));
+
+    let clock: Clock = env.ctx.banks_client.get_sysvar().await.unwrap();
+    let oracle = Pubkey::new_unique();
+    let data = borsh::to_vec(&OraclePrice {
+        price: 9_000,
+        expo: 4,
+        publish_time: clock.unix_timestamp,
+    })
+    .unwrap();
+    env.ctx
+        .set_account(&oracle, &packed_account(data, Pubkey::new_unique()).into());
+    let pegged_data = PeggedData {
+        token_type: env.mint_1,
+        token_qty: 5,
+        strike_type: env.mint_2,
+        oracle,
+        spread_bps: 100,
+        max_staleness: 60,
+    };
+    let mut pegged = vec![];
+    for contract_no in [41, 42] {
+        let seller_temp = add_token_account(&mut env.ctx, env.mint_1, seller.pubkey(), 5);
+        let index_seed = instruction::index_seed(&seller.pubkey(), 0, contract_no);
+        let ix = instruction::pegged_offer(
+            &program_id,
+            &seller.pubkey(),
+            &seller_temp,
+            &seller_receive,
+            &pegged_data,
+            &index_seed,
+        );
+        let units = send(env, ix, &[&seller]).await;
+        let (pda, bump) = Pubkey::find_program_address(
+            &[&pegged_data.get_seed(), &get_seed(&index_seed)],
+            &program_id,
+        );
+        let state = PeggedPDA {
+            seller_main: seller.pubkey(),
+            seller_temp,
+            seller_receive,
+            pegged_data,
+            index_seed: get_seed(&index_seed),
+            bump,
+        };
+        pegged.push((pda, state, units));
+    }
+    measured.push(("PEGGED_OFFER", pegged[0].2));
+
+    let buyer_strike = add_token_account(&mut env.ctx, env.mint_2, buyer.pubkey(), 5);
+    let buyer_receive = add_token_account(&mut env.ctx, env.mint_1, buyer.pubkey(), 0);
+    let ix = instruction::pegged_accept(
+        &program_id,
+        &buyer.pubkey(),
+        &buyer_strike,
+        &buyer_receive,
+        &pegged[0].0,
+        &pegged[0].1,
+        5,
+    );
+    measured.push(("PEGGED_ACCEPT", send(env, ix, &[&buyer]).await));
+    let ix = instruction::pegged_cancel(&program_id, &pegged[1].0, &pegged[1].1);
+    measured.push(("PEGGED_CANCEL", send(env, ix, &[&seller]).await));
+}
+
+// the htlc, arbiter, milestone, vesting and multisig instructions
+async fn measure_escrow_types(env: &mut BenchEnv, measured: &mut Vec<(&str, u64)>) {
+    let program_id = env.program_id;
+    let (seller, buyer, arbiter) = (copy(&env.seller), copy(&env.buyer), copy(&env.arbiter));
+    let seller_receive = add_token_account(&mut env.ctx, env.mint_1, seller.pubkey(), 0);
+    let buyer_receive = add_token_account(&mut env.ctx, env.mint_1, buyer.pubkey(), 0);
+    let index_seed = |contract_no| instruction::index_seed(&seller.pubkey(), 0, contract_no);
+    let clock: Clock = env.ctx.banks_client.get_sysvar().await.unwrap();
+    let now = clock.unix_timestamp;
+    let mint = env.mint_1;
+
+    let preimage = [7; 32];
+    let htlc_data = || HtlcData {
+        token_type: mint,
+        token_qty: 5,
+        buyer: buyer.pubkey(),
+        hashlock: get_seed(&preimage),
+        expiry: now + 100,
+    };
+    let mut htlcs = vec![];
+    for contract_no in [50, 51] {
+        let seller_temp = add_token_account(&mut env.ctx, env.mint_1, seller.pubkey(), 5);
+        let seed = index_seed(contract_no);
+        let ix = instruction::htlc_offer(
+            &program_id,
+            &seller.pubkey(),
+            &seller_temp,
+            htlc_data(),
+            &seed,
+        );
+        let units = send(env, ix, &[&seller]).await;
+        let htlc = HtlcPDA {
+            htlc_data: htlc_data(),
+            seller_main: seller.pubkey(),
+            seller_temp,
+            index_seed: get_seed(&seed),
+        };
+        let pda = instruction::pda_address(&program_id, &htlc.htlc_data.get_seed(), &seed);
+        htlcs.push((pda, htlc, units));
+    }
+    measured.push(("HTLC_OFFER", htlcs[0].2));
+    let ix = instruction::htlc_claim(
+        &program_id,
+        &buyer_receive,
+        &htlcs[0].0,
+        &htlcs[0].1,
+        preimage,
+    );
+    measured.push(("HTLC_CLAIM", send(env, ix, &[&buyer]).await));
+
+    let arbiter_data = || ArbiterData {
+        token_type: mint,
+        token_qty: 5,
+        seller: seller.pubkey(),
+        arbiter: arbiter.pubkey(),
+    };
+    let mut arbiters = vec![];
+    for contract_no in [52, 53, 54] {
+        let buyer_temp = add_token_account(&mut env.ctx, env.mint_1, buyer.pubkey(), 5);
+        let seed = index_seed(contract_no);
+        let ix = instruction::arbiter_offer(
+            &program_id,
+            &buyer.pubkey(),
+            &buyer_temp,
+            arbiter_data(),
+            &seed,
+        );
+        let units = send(env, ix, &[&buyer]).await;
+        let state = ArbiterPDA {
+            arbiter_data: arbiter_data(),
+            buyer_main: buyer.pubkey(),
+            buyer_temp,
+            index_seed: get_seed(&seed),
+            disputed: false,
+        };
+        let pda = instruction::pda_address(&program_id, &state.arbiter_data.get_seed(), &seed);
+        arbiters.push((pda, state, units));
+    }
+    measured.push(("ARBITER_OFFER", arbiters[0].2));
+    let ix =
+        instruction::arbiter_release(&program_id, &seller_receive, &arbiters[0].0, &arbiters[0].1);
+    measured.push(("ARBITER_RELEASE", send(env, ix, &[&buyer]).await));
+    let ix = instruction::arbiter_refund(&program_id, &arbiters[1].0, &arbiters[1].1);
+    measured.push(("ARBITER_REFUND", send(env, ix, &[&seller]).await));
+    let ix = instruction::arbiter_dispute(&program_id, &buyer.pubkey(), &arbiters[2].0);
+    measured.push(("ARBITER_DISPUTE", send(env, ix, &[&buyer]).await));
+    let ix = instruction::arbiter_resolve(
+        &program_id,
+        &buyer_receive,
+        &seller_receive,
+        &arbiters[2].0,
+        &arbiters[2].1,
+        5_000,
+    );
+    measured.push(("ARBITER_RESOLVE", send(env, ix, &[&arbiter]).await));
+
+    let milestone_data = || MilestoneData {
+        token_type: mint,
+        payee: buyer.pubkey(),
+        milestones: vec![Milestone {
+            amount: 5,
+            deadline: Some(now + 50),
+        }],
+    };
+    let mut milestones = vec![];
+    for contract_no in [55, 56] {
+        let payer_temp = add_token_account(&mut env.ctx, env.mint_1, seller.pubkey(), 5);
+        let seed = index_seed(contract_no);
+        let ix = instruction::milestone_offer(
+            &program_id,
+            &seller.pubkey(),
+            &payer_temp,
+            milestone_data(),
+            &seed,
+        );
+        let units = send(env, ix, &[&seller]).await;
+        let state = MilestonePDA {
+            milestone_data: milestone_data(),
+            payer_main: seller.pubkey(),
+            payer_temp,
+            index_seed: get_seed(&seed),
+            released: vec![false],
+        };
+        let pda = instruction::pda_address(&program_id, &state.milestone_data.get_seed(), &seed);
+        milestones.push((pda, state, units));
+    }
+    measured.push(("MILESTONE_OFFER", milestones[0].2));
+    let ix = instruction::milestone_approve(
+        &program_id,
+        &buyer_receive,
+        &milestones[0].0,
+        &milestones[0].1,
+        0,
+    );
+    measured.push(("MILESTONE_APPROVE", send(env, ix, &[&seller]).await));
+
+    let vesting_data = || VestingData {
+        token_type: mint,
+        token_qty: 5,
+        beneficiary: buyer.pubkey(),
+        start: now,
+        cliff: now,
+        end: now + 100,
+    };
+    let grantor_temp = add_token_account(&mut env.ctx, env.mint_1, seller.pubkey(), 5);
+    let seed = index_seed(57);
+    let ix = instruction::vesting_offer(
+        &program_id,
+        &seller.pubkey(),
+        &grantor_temp,
+        vesting_data(),
+        &seed,
+    );
+    measured.push(("VESTING_OFFER", send(env, ix, &[&seller]).await));
+    let vesting = VestingPDA {
+        vesting_data: vesting_data(),
+        grantor_main: seller.pubkey(),
+        grantor_temp,
+        index_seed: get_seed(&seed),
+        withdrawn: 0,
+    };
+    let vesting_pda =
+        instruction::pda_address(&program_id, &vesting.vesting_data.get_seed(), &seed);
+
+    // past the htlc expiry, the milestone deadline and the end of the vesting schedule
+    set_unix_timestamp(env, now + 100).await;
+    let ix = instruction::htlc_refund(&program_id, &htlcs[1].0, &htlcs[1].1);
+    measured.push(("HTLC_REFUND", send(env, ix, &[&seller]).await));
+    let ix = instruction::milestone_refund(&program_id, &milestones[1].0, &milestones[1].1);
+    measured.push(("MILESTONE_REFUND", send(env, ix, &[&seller]).await));
+    let ix = instruction::vesting_withdraw(&program_id, &buyer_receive, &vesting_pda, &vesting);
+    measured.push(("VESTING_WITHDRAW", send(env, ix, &[&buyer]).await));
+
+    let multisig_data = MultisigData {
+        m: 1,
+        signers: vec![seller.pubkey()],
+    };
+    let seed = index_seed(58);
+    let multisig_pda = instruction::pda_address(&program_id, &multisig_data.get_seed(), &seed);
+    let ix = instruction::multisig_create(&program_id, &seller.pubkey(), multisig_data, &seed);
+    measured.push(("MULTISIG_CREATE", send(env, ix, &[&seller]).await));
+    // the action moves tokens held by the multisig authority
+    let (authority, _bump) = MultisigPDA::authority(&multisig_pda, &program_id);
+    let authority_tokens = add_token_account(&mut env.ctx, env.mint_1, authority, 5);
+    let action = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &authority_tokens,
+        &buyer_receive,
+        &authority,
+        &[],
+        5,
+    )
+    .unwrap();
+    let ix = instruction::multisig_approve(&program_id, &seller.pubkey(), &multisig_pda, &action);
+    measured.push(("MULTISIG_APPROVE", send(env, ix, &[&seller]).await));
+    let ix = instruction::multisig_execute(&program_id, &multisig_pda, &action);
+    measured.push(("MULTISIG_EXECUTE", send(env, ix, &[]).await));
+}
+
+// sends ix and returns the compute units it consumed, natively including the addresses tried
+async fn send(env: &mut BenchEnv, ix: Instruction, signers: &[&Keypair]) -> u64 {
+    try_send(env, ix, signers).await.unwrap()
+}
+
+// sends ix and returns the compute units it consumed and the addresses the program tried, which
+// are only counted natively
+async fn send_counting(env: &mut BenchEnv, ix: Instruction, signers: &[&Keypair]) -> (u64, u64) {
+    let before = ADDRESS_ATTEMPTS.load(Ordering::Relaxed);
+    let units = send(env, ix, signers).await;
+    (units, ADDRESS_ATTEMPTS.load(Ordering::Relaxed) - before)
+}
+
+async fn try_send(
+    env: &mut BenchEnv,
+    ix: Instruction,
+    signers: &[&Keypair],
+) -> Result<u64, TransactionError> {
+    let blockhash = env
+        .ctx
+        .banks_client
+        .get_new_latest_blockhash(&env.ctx.last_blockhash)
+        .await
+        .unwrap();
+    env.ctx.last_blockhash = blockhash;
+    let mut keypairs = vec![&env.ctx.payer];
+    keypairs.extend(signers);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&env.ctx.payer.pubkey()),
+        &keypairs,
+        blockhash,
+    );
+    // the bank does not enforce the packet size a validator would
+    let size = 1 + tx.signatures.len() * 64 + tx.message_data().len();
+    assert!(size <= PACKET_DATA_SIZE, "transaction of {} bytes", size);
+    let tries = ADDRESS_ATTEMPTS.load(Ordering::Relaxed);
+    let result = env
+        .ctx
+        .banks_client
+        .process_transaction_with_metadata(tx)
+        .await
+        .unwrap();
+    result.result?;
+    let tries = ADDRESS_ATTEMPTS.load(Ordering::Relaxed) - tries;
+    Ok(result.metadata.unwrap().compute_units_consumed + tries * CREATE_PROGRAM_ADDRESS_UNITS)
+}
+
+async fn open_offer(
+    env: &mut BenchEnv,
+    seller_receive: Pubkey,
+    contract_no: u64,
+) -> (Pubkey, EscrowPDA, u64) {
+    open_offer_with(env, seller_receive, contract_no, &[]).await
+}
+
+// passes the AllowedMintPDAs of mints, for when the allow-list is on
+async fn open_offer_with(
+    env: &mut BenchEnv,
+    seller_receive: Pubkey,
+    contract_no: u64,
+    mints: &[Pubkey],
+) -> (Pubkey, EscrowPDA, u64) {
+    let seller = env.seller.pubkey();
+    let seller_temp = add_token_account(&mut env.ctx, env.mint_1, seller, 5);
+    let offer_data = bench_offer(env);
+    let index_seed = instruction::index_seed(&seller, 0, contract_no);
+    let ix = instruction::offer(
+        &env.program_id,
+        &seller,
+        &seller_temp,
+        &seller_receive,
+        &offer_data,
+        &index_seed,
+    );
+    let ix = instruction::with_mints(&env.program_id, ix, mints);
+    let signer = copy(&env.seller);
+    let units = send(env, ix, &[&signer]).await;
+
+    let pda = instruction::escrow_address(&env.program_id, &offer_data, &index_seed);
+    let account = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    (
+        pda,
+        EscrowPDA::unpack_from_slice(&account.data).unwrap(),
+        units,
+    )
+}
+
+fn bench_offer(env: &BenchEnv) -> OfferData {
+    OfferData {
+        token_type: env.mint_1,
+        token_qty: 5,
+        strike_type: env.mint_2,
+        strike_qty: 10,
+    }
+}
+
+// the first contract_no from `from` whose open_offer escrow has a bump below 255
+fn searched_contract_no(env: &BenchEnv, from: u64) -> u64 {
+    let seed = bench_offer(env).get_seed();
+    (from..)
+        .find(|&contract_no| {
+            let index_seed = instruction::index_seed(&env.seller.pubkey(), 0, contract_no);
+            let seeds: &[&[u8]] = &[&seed, &get_seed(&index_seed)];
+            Pubkey::find_program_address(seeds, &env.program_id).1 < 255
+        })
+        .unwrap()
+}
+
+// rewrites the escrow as the v2 format wrote it: no stored bump, and the operator option padded
+// to 33 bytes before rent_payer
+async fn write_v2(env: &mut BenchEnv, pda: Pubkey, escrow: &EscrowPDA) {
+    let mut account = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut operator = [0; 33];
+    if let Some(key) = escrow.operator {
+        operator[0] = 1;
+        operator[1..].copy_from_slice(key.as_ref());
+    }
+    account.data = [
+        escrow.seller_main.as_ref(),
+        escrow.seller_temp.as_ref(),
+        escrow.seller_receive.as_ref(),
+        &escrow.offer_data.to_bytes(),
+        &escrow.index_seed,
+        &operator,
+        escrow.rent_payer.as_ref(),
+    ]
+    .concat();
+    assert_eq!(account.data.len(), EscrowPDA::V2_LEN);
+    env.ctx.set_account(&pda, &account.into());
+}
+
+async fn set_unix_timestamp(env: &mut BenchEnv, unix_timestamp: i64) {
+    let mut clock: Clock = env.ctx.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp = unix_timestamp;
+    env.ctx.set_sysvar(&clock);
+}
+
+// keypairs held by the env, for signing while it is borrowed mutably
+fn copy(keypair: &Keypair) -> Keypair {
+    Keypair::from_bytes(&keypair.to_bytes()).unwrap()
+}
+
+// token accounts are written straight into the bank so only the measured instructions run.
+// mint supply is not tracked, the token program does not check it on transfer
+fn add_token_account(
+    ctx: &mut ProgramTestContext,
+    mint: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let key = Pubkey::new_unique();
+    let state = Account {
+        mint,
+        owner,
+        amount,
+        state: AccountState::Initialized,
+        ..Account::default()
+    };
+    let mut data = vec![0; Account::LEN];
+    state.pack_into_slice(&mut data);
+    ctx.set_account(&key, &packed_account(data, spl_token::id()).into());
+    key
+}
+
+fn packed_account(data: Vec<u8>, owner: Pubkey) -> SolanaAccount {
+    SolanaAccount {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        ..SolanaAccount::default()
+    }
+}
+
+async fn init_env() -> BenchEnv {
+    let program_id = Pubkey::new_unique();
+    let mut program_test =
+        ProgramTest::new("sol_escrow", program_id, processor!(process_instruction));
+    // fixed keys, so the bumps found for their addresses and the units spent finding them are the
+    // same every run
+    let keypair = |seed| keypair_from_seed(&[seed; 32]).unwrap();
+    let (seller, buyer, admin, arbiter) = (keypair(1), keypair(2), keypair(3), keypair(4));
+    let (mint_1, mint_2) = (Pubkey::new_unique(), Pubkey::new_unique());
+    for mint in [mint_1, mint_2] {
+        let state = Mint {
+            mint_authority: COption::Some(Pubkey::new_unique()),
+            supply: 1_000,
+            decimals: 1,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        let mut data = vec![0; Mint::LEN];
+        state.pack_into_slice(&mut data);
+        program_test.add_account(mint, packed_account(data, spl_token::id()));
+    }
+    for wallet in [&seller, &buyer, &admin] {
+        program_test.add_account(
+            wallet.pubkey(),
+            SolanaAccount::new(1_000_000_000, 0, &solana_sdk::system_program::id()),
+        );
+    }
+    // processor! programs have no ProgramData account, CONFIG_INIT reads the upgrade authority
+    // from this one
+    let state = UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(admin.pubkey()),
+    };
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    let program_data = SolanaAccount::new_data(
+        Rent::default().minimum_balance(metadata_len),
+        &state,
+        &bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    program_test.add_account(instruction::program_data_address(&program_id), program_data);
+
+    let mut env = BenchEnv {
+        ctx: program_test.start_with_context().await,
+        program_id,
+        seller,
+        buyer,
+        admin,
+        arbiter,
+        mint_1,
+        mint_2,
+    };
+    // a fixed clock too, the htlc, milestone and vesting seeds hold timestamps
+    set_unix_timestamp(&mut env, 1_700_000_000).await;
+    env
+}
@@ -0,0 +1,512 @@
+#![cfg(feature = "idl")]
+
+use sol_escrow::{
+    idl::{idl, ACCOUNT_LAYOUTS},
+    instruction::{self, instruction_accounts},
+    state::{
+        AllowedMintPDA, ArbiterData, ArbiterPDA, ConfigPDA, EscrowPDA, HtlcData, HtlcPDA,
+        LimitOrder, Milestone, MilestoneData, MilestonePDA, MultisigData, MultisigPDA, OfferData,
+        PeggedData, PeggedPDA, VestingData, VestingPDA,
+    },
+};
+use solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+
+const IDL_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/idl/sol_escrow.json");
+
+// regenerate with: UPDATE_IDL=1 cargo test --features idl --test idl
+#[test]
+fn test_idl_up_to_date() {
+    let generated = serde_json::to_string_pretty(&idl()).unwrap() + "\n";
+    if std::env::var_os("UPDATE_IDL").is_some() {
+        std::fs::create_dir_all(std::path::Path::new(IDL_PATH).parent().unwrap()).unwrap();
+        std::fs::write(IDL_PATH, &generated).unwrap();
+    }
+    let committed = std::fs::read_to_string(IDL_PATH).unwrap_or_default();
+    assert!(
+        committed == generated,
+        "idl/sol_escrow.json is out of date, rerun with UPDATE_IDL=1"
+    );
+}
+
+#[test]
+fn test_idl_accounts_match_builders() {
+    let program_id = Pubkey::new_unique();
+    let seller = Pubkey::new_unique();
+    let key = Pubkey::new_unique;
+    let offer_data = OfferData {
+        token_type: key(),
+        token_qty: 5,
+        strike_type: key(),
+        strike_qty: 10,
+    };
+    let index_seed = instruction::index_seed(&seller, 0, 0);
+    let escrow = EscrowPDA {
+        seller_main: seller,
+        seller_temp: key(),
+        seller_receive: key(),
+        offer_data,
+        index_seed: [0; 32],
+        rent_payer: seller,
+        operator: None,
+        bump: 0,
+    };
+    let escrow_pda = instruction::escrow_address(&program_id, &offer_data, &index_seed);
+    let htlc = HtlcPDA {
+        htlc_data: HtlcData {
+            token_type: key(),
+            token_qty: 5,
+            buyer: key(),
+            hashlock: [0; 32],
+            expiry: 0,
+        },
+        seller_main: seller,
+        seller_temp: key(),
+        index_seed: [0; 32],
+    };
+    let arbiter = ArbiterPDA {
+        arbiter_data: ArbiterData {
+            token_type: key(),
+            token_qty: 5,
+            seller: key(),
+            arbiter: key(),
+        },
+        buyer_main: seller,
+        buyer_temp: key(),
+        index_seed: [0; 32],
+        disputed: false,
+    };
+    let milestones = MilestonePDA {
+        milestone_data: MilestoneData {
+            token_type: key(),
+            payee: key(),
+            milestones: vec![Milestone {
+                amount: 5,
+                deadline: None,
+            }],
+        },
+        payer_main: seller,
+        payer_temp: key(),
+        index_seed: [0; 32],
+        released: vec![false],
+    };
+    let vesting_data = || VestingData {
+        token_type: key(),
+        token_qty: 5,
+        beneficiary: key(),
+        start: 0,
+        cliff: 0,
+        end: 1,
+    };
+    let vesting = VestingPDA {
+        vesting_data: vesting_data(),
+        grantor_main: seller,
+        grantor_temp: key(),
+        index_seed: [0; 32],
+        withdrawn: 0,
+    };
+    let multisig_data = || MultisigData {
+        m: 1,
+        signers: vec![seller],
+    };
+    let pegged = PeggedPDA {
+        seller_main: seller,
+        seller_temp: key(),
+        seller_receive: key(),
+        pegged_data: PeggedData {
+            token_type: key(),
+            token_qty: 5,
+            strike_type: key(),
+            oracle: key(),
+            spread_bps: 0,
+            max_staleness: 60,
+        },
+        index_seed: [0; 32],
+        bump: 0,
+    };
+    let limit_order = LimitOrder {
+        token_type: key(),
+        token_qty: 5,
+        strike_type: key(),
+        price: 2_000_000_000,
+    };
+    let multisig_pda = key();
+    let action = instruction::set_paused(
+        &program_id,
+        &MultisigPDA::authority(&multisig_pda, &program_id).0,
+        true,
+    );
+    let idl = idl();
+
+    let builders = [
+        (
+            "OFFER",
+            instruction::offer(
+                &program_id,
+                &seller,
+                &escrow.seller_temp,
+                &escrow.seller_receive,
+                &offer_data,
+                &index_seed,
+            ),
+        ),
+        (
+            "ACCEPT",
+            instruction::accept(&program_id, &key(), &key(), &key(), &escrow_pda, &escrow),
+        ),
+        (
+            "CANCEL",
+            instruction::cancel(&program_id, &escrow_pda, &escrow),
+        ),
+        (
+            "HTLC_OFFER",
+            instruction::htlc_offer(
+                &program_id,
+                &seller,
+                &htlc.seller_temp,
+                HtlcData { ..htlc.htlc_data },
+                &index_seed,
+            ),
+        ),
+        (
+            "HTLC_CLAIM",
+            instruction::htlc_claim(&program_id, &key(), &escrow_pda, &htlc, [0; 32]),
+        ),
+        (
+            "HTLC_REFUND",
+            instruction::htlc_refund(&program_id, &escrow_pda, &htlc),
+        ),
+        (
+            "ARBITER_OFFER",
+            instruction::arbiter_offer(
+                &program_id,
+                &seller,
+                &arbiter.buyer_temp,
+                ArbiterData {
+                    ..arbiter.arbiter_data
+                },
+                &index_seed,
+            ),
+        ),
+        (
+            "ARBITER_RELEASE",
+            instruction::arbiter_release(&program_id, &key(), &escrow_pda, &arbiter),
+        ),
+        (
+            "ARBITER_REFUND",
+            instruction::arbiter_refund(&program_id, &escrow_pda, &arbiter),
+        ),
+        (
+            "ARBITER_DISPUTE",
+            instruction::arbiter_dispute(&program_id, &seller, &escrow_pda),
+        ),
+        (
+            "ARBITER_RESOLVE",
+            instruction::arbiter_resolve(&program_id, &key(), &key(), &escrow_pda, &arbiter, 5_000),
+        ),
+        (
+            "MILESTONE_OFFER",
+            instruction::milestone_offer(
+                &program_id,
+                &seller,
+                &milestones.payer_temp,
+                MilestoneData {
+                    milestones: vec![Milestone {
+                        amount: 5,
+                        deadline: None,
+                    }],
+                    ..milestones.milestone_data
+                },
+                &index_seed,
+            ),
+        ),
+        (
+            "MILESTONE_APPROVE",
+            instruction::milestone_approve(&program_id, &key(), &escrow_pda, &milestones, 0),
+        ),
+        (
+            "MILESTONE_REFUND",
+            instruction::milestone_refund(&program_id, &escrow_pda, &milestones),
+        ),
+        (
+            "VESTING_OFFER",
+            instruction::vesting_offer(
+                &program_id,
+                &seller,
+                &vesting.grantor_temp,
+                vesting_data(),
+                &index_seed,
+            ),
+        ),
+        (
+            "VESTING_WITHDRAW",
+            instruction::vesting_withdraw(&program_id, &key(), &escrow_pda, &vesting),
+        ),
+        (
+            "MULTISIG_CREATE",
+            instruction::multisig_create(&program_id, &seller, multisig_data(), &index_seed),
+        ),
+        (
+            "MULTISIG_APPROVE",
+            instruction::multisig_approve(&program_id, &seller, &multisig_pda, &action),
+        ),
+        (
+            "MULTISIG_EXECUTE",
+            instruction::multisig_execute(&program_id, &multisig_pda, &action),
+        ),
+        (
+            "SET_OPERATOR",
+            instruction::set_operator(&program_id, &escrow_pda, &escrow, Some(&key())),
+        ),
+        (
+            "CANCEL_MANY",
+            instruction::cancel_many(&program_id, &[(&escrow_pda, &escrow)]),
+        ),
+        (
+            "CREATE_MANY",
+            instruction::create_many(
+                &program_id,
+                &seller,
+                &key(),
+                &escrow.seller_receive,
+                &[(escrow.seller_temp, offer_data, index_seed)],
+            ),
+        ),
+        (
+            "PEGGED_OFFER",
+            instruction::pegged_offer(
+                &program_id,
+                &seller,
+                &pegged.seller_temp,
+                &pegged.seller_receive,
+                &pegged.pegged_data,
+                &index_seed,
+            ),
+        ),
+        (
+            "PEGGED_ACCEPT",
+            instruction::pegged_accept(
+                &program_id,
+                &key(),
+                &key(),
+                &key(),
+                &escrow_pda,
+                &pegged,
+                10,
+            ),
+        ),
+        (
+            "PEGGED_CANCEL",
+            instruction::pegged_cancel(&program_id, &escrow_pda, &pegged),
+        ),
+        (
+            "LIMIT_OFFER",
+            instruction::limit_offer(
+                &program_id,
+                &seller,
+                &escrow.seller_temp,
+                &escrow.seller_receive,
+                &limit_order,
+                (6, 6),
+                &index_seed,
+            )
+            .unwrap(),
+        ),
+        (
+            "CONFIG_INIT",
+            instruction::config_init(&program_id, &seller),
+        ),
+        (
+            "SET_PAUSED",
+            instruction::set_paused(&program_id, &seller, true),
+        ),
+        (
+            "PROPOSE_ADMIN",
+            instruction::propose_admin(&program_id, &seller, &key()),
+        ),
+        (
+            "SET_ALLOW_LIST",
+            instruction::set_allow_list(&program_id, &seller, true),
+        ),
+        (
+            "ALLOW_MINT",
+            instruction::allow_mint(&program_id, &seller, &offer_data.token_type),
+        ),
+        (
+            "DISALLOW_MINT",
+            instruction::disallow_mint(&program_id, &seller, &offer_data.token_type),
+        ),
+        (
+            "AMEND",
+            instruction::amend(&program_id, &escrow_pda, &escrow, 12, None),
+        ),
+        (
+            "ACCEPT_ADMIN",
+            instruction::accept_admin(&program_id, &seller),
+        ),
+    ];
+
+    let instructions = idl["instructions"].as_array().unwrap();
+    assert_eq!(builders.len(), instructions.len());
+    for ((name, ix), instruction) in builders.into_iter().zip(instructions) {
+        assert_eq!(instruction["name"], name);
+        assert_eq!(
+            ix.data[0] as u64,
+            instruction["tag"].as_u64().unwrap(),
+            "{}",
+            name
+        );
+
+        // batches of one and no allowed mints, so every account is passed at most once. an
+        // optional account is skipped by the program id, or left off at the end, along with
+        // the accounts passed only with it
+        let mut metas = ix.accounts.iter();
+        let mut skipped = vec![];
+        for account in instruction_accounts(name).unwrap() {
+            if account.with.is_some_and(|with| skipped.contains(&with)) {
+                skipped.push(account.name);
+                continue;
+            }
+            let meta = match metas.next() {
+                Some(meta) => meta,
+                None => {
+                    assert!(account.optional, "{}.{} missing", name, account.name);
+                    skipped.push(account.name);
+                    continue;
+                }
+            };
+            if account.optional && meta.pubkey == program_id {
+                skipped.push(account.name);
+                continue;
+            }
+            assert_eq!(meta.is_signer, account.signer, "{}.{}", name, account.name);
+            assert_eq!(
+                meta.is_writable, account.writable,
+                "{}.{}",
+                name, account.name
+            );
+        }
+        // only the executed action's accounts follow the listed ones
+        if name == "MULTISIG_EXECUTE" {
+            assert_eq!(metas.len(), action.accounts.len());
+        } else {
+            assert_eq!(metas.len(), 0, "{}", name);
+        }
+    }
+}
+
+fn layout(name: &str) -> &'static [(&'static str, &'static str, usize)] {
+    ACCOUNT_LAYOUTS
+        .iter()
+        .find(|(account, _)| *account == name)
+        .unwrap()
+        .1
+}
+
+fn offset(name: &str, field: &str) -> usize {
+    let layout = layout(name);
+    let index = layout.iter().position(|(f, _, _)| *f == field).unwrap();
+    layout[..index].iter().map(|(_, _, size)| size).sum()
+}
+
+fn assert_key_at(data: &[u8], name: &str, field: &str, key: &Pubkey) {
+    let offset = offset(name, field);
+    assert_eq!(
+        &data[offset..offset + 32],
+        key.as_ref(),
+        "{}.{}",
+        name,
+        field
+    );
+}
+
+fn pack<T: Pack>(state: &T) -> Vec<u8> {
+    let mut data = vec![0; T::LEN];
+    state.pack_into_slice(&mut data);
+    data
+}
+
+#[test]
+fn test_idl_account_layouts() {
+    for (name, len) in [
+        ("EscrowPDA", EscrowPDA::LEN),
+        ("HtlcPDA", HtlcPDA::LEN),
+        ("ArbiterPDA", ArbiterPDA::LEN),
+        ("VestingPDA", VestingPDA::LEN),
+        ("MultisigPDA", MultisigPDA::LEN),
+        ("PeggedPDA", PeggedPDA::LEN),
+        ("ConfigPDA", ConfigPDA::LEN),
+        ("AllowedMintPDA", AllowedMintPDA::LEN),
+        ("MilestonePDA", MilestonePDA::len(0)),
+    ] {
+        let size: usize = layout(name).iter().map(|(_, _, size)| size).sum();
+        assert_eq!(size, len, "{}", name);
+    }
+    for (field, expected) in [
+        ("seller_main", EscrowPDA::SELLER_MAIN_OFFSET),
+        ("seller_temp", EscrowPDA::SELLER_TEMP_OFFSET),
+        ("seller_receive", EscrowPDA::SELLER_RECEIVE_OFFSET),
+        ("offer_data", EscrowPDA::TOKEN_TYPE_OFFSET),
+        ("index_seed", EscrowPDA::INDEX_SEED_OFFSET),
+        ("rent_payer", EscrowPDA::RENT_PAYER_OFFSET),
+        ("operator", EscrowPDA::OPERATOR_OFFSET),
+        ("bump", EscrowPDA::BUMP_OFFSET),
+    ] {
+        assert_eq!(offset("EscrowPDA", field), expected, "EscrowPDA.{}", field);
+    }
+
+    let keys = [(); 4].map(|_| Pubkey::new_unique());
+    let htlc = HtlcPDA {
+        htlc_data: HtlcData {
+            token_type: keys[0],
+            token_qty: 1,
+            buyer: keys[1],
+            hashlock: [0; 32],
+            expiry: 0,
+        },
+        seller_main: keys[2],
+        seller_temp: keys[3],
+        index_seed: [0; 32],
+    };
+    let data = pack(&htlc);
+    assert_key_at(&data, "HtlcPDA", "seller_main", &keys[2]);
+    assert_key_at(&data, "HtlcPDA", "seller_temp", &keys[3]);
+    assert_key_at(&data, "HtlcPDA", "htlc_data", &keys[0]);
+
+    let arbiter = ArbiterPDA {
+        arbiter_data: ArbiterData {
+            token_type: keys[0],
+            token_qty: 1,
+            seller: keys[1],
+            arbiter: keys[1],
+        },
+        buyer_main: keys[2],
+        buyer_temp: keys[3],
+        index_seed: [0; 32],
+        disputed: true,
+    };
+    let data = pack(&arbiter);
+    assert_key_at(&data, "ArbiterPDA", "buyer_main", &keys[2]);
+    assert_key_at(&data, "ArbiterPDA", "buyer_temp", &keys[3]);
+    assert_key_at(&data, "ArbiterPDA", "arbiter_data", &keys[0]);
+    assert_eq!(data[offset("ArbiterPDA", "disputed")], 1);
+
+    let vesting = VestingPDA {
+        vesting_data: VestingData {
+            token_type: keys[0],
+            token_qty: 1,
+            beneficiary: keys[1],
+            start: 0,
+            cliff: 0,
+            end: 1,
+        },
+        grantor_main: keys[2],
+        grantor_temp: keys[3],
+        index_seed: [0; 32],
+        withdrawn: 7,
+    };
+    let data = pack(&vesting);
+    assert_key_at(&data, "VestingPDA", "grantor_main", &keys[2]);
+    assert_key_at(&data, "VestingPDA", "grantor_temp", &keys[3]);
+    assert_key_at(&data, "VestingPDA", "vesting_data", &keys[0]);
+    assert_eq!(data[offset("VestingPDA", "withdrawn")], 7);
+}
@@ -0,0 +1,210 @@
+#![cfg(feature = "indexer")]
+
+use sol_escrow::{
+    indexer::*,
+    state::{EscrowPDA, HtlcData, HtlcPDA, OfferData, PeggedData, PeggedPDA},
+};
+use solana_rpc_client_api::filter::RpcFilterType;
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([9; 32]);
+
+fn escrow(
+    seller: Pubkey,
+    token_type: Pubkey,
+    strike_type: Pubkey,
+    token_qty: u64,
+    strike_qty: u64,
+) -> EscrowPDA {
+    EscrowPDA {
+        offer_data: OfferData {
+            token_type,
+            token_qty,
+            strike_type,
+            strike_qty,
+        },
+        seller_main: seller,
+        seller_temp: Pubkey::new_unique(),
+        seller_receive: Pubkey::new_unique(),
+        index_seed: [7; 32],
+        operator: None,
+        rent_payer: seller,
+        bump: 0,
+    }
+}
+
+fn account<T: Pack>(state: &T) -> Account {
+    let mut data = vec![0; T::LEN];
+    state.pack_into_slice(&mut data);
+    Account {
+        data,
+        owner: PROGRAM_ID,
+        ..Account::default()
+    }
+}
+
+// the escrow at its address, packed at one of EscrowPDA::DATA_LENS. older sizes are prefixes
+// of the current layout
+fn offer_account(escrow: EscrowPDA, len: usize) -> (Pubkey, Account) {
+    let seeds: &[&[u8]] = &[&escrow.offer_data.get_seed(), &escrow.index_seed];
+    let (pda, bump) = Pubkey::find_program_address(seeds, &PROGRAM_ID);
+    let mut escrow_account = account(&EscrowPDA { bump, ..escrow });
+    escrow_account.data.truncate(len);
+    (pda, escrow_account)
+}
+
+// any one set of filters is a query that returns the account
+fn allows(filter_sets: &[Vec<RpcFilterType>], account: &AccountSharedData) -> bool {
+    filter_sets
+        .iter()
+        .any(|filters| filters.iter().all(|filter| filter.allows(account)))
+}
+
+#[test]
+fn test_order_book() {
+    let (seller, mint_1, mint_2) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let htlc = HtlcPDA {
+        htlc_data: HtlcData {
+            token_type: mint_1,
+            token_qty: 1,
+            buyer: Pubkey::new_unique(),
+            hashlock: [0; 32],
+            expiry: 0,
+        },
+        seller_main: seller,
+        seller_temp: Pubkey::new_unique(),
+        index_seed: [0; 32],
+    };
+    let mut accounts = vec![
+        offer_account(escrow(seller, mint_1, mint_2, 10, 30), EscrowPDA::LEN),
+        offer_account(escrow(seller, mint_1, mint_2, 4, 8), EscrowPDA::V2_LEN),
+        offer_account(
+            escrow(seller, mint_2, mint_1, 1, 1),
+            EscrowPDA::V1_OPERATOR_LEN,
+        ),
+        offer_account(escrow(seller, mint_1, mint_2, 2, 5), EscrowPDA::V1_LEN),
+    ];
+    let pdas: Vec<_> = accounts.iter().map(|(pda, _)| *pda).collect();
+    // an htlc is as long as a v1 escrow, and an escrow anywhere but its address is no offer
+    assert_eq!(HtlcPDA::LEN, EscrowPDA::V1_LEN);
+    accounts.push((Pubkey::new_unique(), account(&htlc)));
+    accounts.push((
+        Pubkey::new_unique(),
+        offer_account(escrow(seller, mint_1, mint_2, 1, 1), EscrowPDA::LEN).1,
+    ));
+
+    let mut book = OrderBook::from_accounts(accounts);
+    assert_eq!(book.len(), 4);
+    assert_eq!(book.markets().count(), 2);
+    let asks: Vec<_> = book
+        .offers(&mint_1, &mint_2)
+        .iter()
+        .map(|offer| offer.pda)
+        .collect();
+    assert_eq!(asks, vec![pdas[1], pdas[3], pdas[0]]);
+
+    let best = book.remove(&pdas[1]).unwrap();
+    assert_eq!(best.escrow.offer_data.strike_qty, 8);
+    assert_eq!(book.best(&mint_1, &mint_2).unwrap().pda, pdas[3]);
+    book.remove(&pdas[2]).unwrap();
+    assert_eq!(book.markets().count(), 1);
+    assert!(book.offers(&mint_2, &mint_1).is_empty());
+}
+
+#[test]
+fn test_offer_filters() {
+    let (seller, mint_1, mint_2) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let other = AccountSharedData::from(
+        offer_account(
+            escrow(Pubkey::new_unique(), mint_2, mint_1, 1, 1),
+            EscrowPDA::LEN,
+        )
+        .1,
+    );
+
+    for len in EscrowPDA::DATA_LENS {
+        let matching =
+            AccountSharedData::from(offer_account(escrow(seller, mint_1, mint_2, 1, 1), len).1);
+        for filter_sets in [
+            all_offers_filters(),
+            offers_by_seller_filters(&seller),
+            offers_by_token_type_filters(&mint_1),
+            offers_by_strike_type_filters(&mint_2),
+            offers_by_market_filters(&mint_1, &mint_2),
+        ] {
+            assert!(allows(&filter_sets, &matching), "{} byte escrow", len);
+        }
+    }
+    for filter_sets in [
+        offers_by_seller_filters(&seller),
+        offers_by_token_type_filters(&mint_1),
+        offers_by_strike_type_filters(&mint_2),
+        offers_by_market_filters(&mint_1, &mint_2),
+    ] {
+        assert!(!allows(&filter_sets, &other));
+    }
+}
+
+#[test]
+fn test_pegged_offers() {
+    let (seller, mint_1, mint_2) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let pegged_data = PeggedData {
+        token_type: mint_1,
+        token_qty: 5,
+        strike_type: mint_2,
+        oracle: Pubkey::new_unique(),
+        spread_bps: 100,
+        max_staleness: 60,
+    };
+    let index_seed = [7; 32];
+    let (pda, bump) =
+        Pubkey::find_program_address(&[&pegged_data.get_seed(), &index_seed], &PROGRAM_ID);
+    let pegged = PeggedPDA {
+        seller_main: seller,
+        seller_temp: Pubkey::new_unique(),
+        seller_receive: Pubkey::new_unique(),
+        pegged_data,
+        index_seed,
+        bump,
+    };
+    let pegged_account = account(&pegged);
+
+    let offers = OpenPegged::from_accounts(vec![
+        (pda, pegged_account.clone()),
+        (Pubkey::new_unique(), pegged_account.clone()),
+        offer_account(escrow(seller, mint_1, mint_2, 1, 1), EscrowPDA::LEN),
+    ]);
+    assert_eq!(offers, vec![OpenPegged { pda, pegged }]);
+    assert_eq!(
+        OrderBook::from_accounts(vec![(pda, pegged_account.clone())]).len(),
+        0
+    );
+
+    let matching = AccountSharedData::from(pegged_account);
+    assert!(allows(&pegged_offer_filters(None, None, None), &matching));
+    assert!(allows(
+        &pegged_offer_filters(Some(&seller), Some(&mint_1), Some(&mint_2)),
+        &matching
+    ));
+    assert!(!allows(
+        &pegged_offer_filters(None, Some(&mint_2), None),
+        &matching
+    ));
+    assert!(!allows(&all_offers_filters(), &matching));
+}
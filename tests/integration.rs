@@ -1,12 +1,26 @@
 #![cfg(feature = "test-bpf")]
 
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::{BorshDeserialize, BorshSerialize};
 use sol_escrow::{
     entrypoint::process_instruction,
-    state::{get_seed, EscrowPDA, OfferData},
+    events::{EscrowEvent, EVENT_VERSION},
+    instruction::{self, decode_instruction, InstructionType},
+    processor::MAX_CANCEL_BATCH,
+    state::{
+        format_price, get_seed, ArbiterData, ArbiterPDA, ConfigPDA, EscrowPDA, EscrowView,
+        HtlcData, HtlcPDA, LimitOrder, Milestone, MilestoneData, MilestonePDA, MultisigData,
+        MultisigPDA, OfferData, OraclePrice, PeggedData, PeggedPDA, VestingData, VestingPDA,
+    },
 };
+use solana_program_test::BanksClientError;
 use solana_program_test::*;
 use solana_sdk::{
+    account::Account as SolanaAccount,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::Clock,
     instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
     program_pack::Pack,
     pubkey::Pubkey,
     rent::Rent,
@@ -14,7 +28,9 @@ use solana_sdk::{
     system_instruction, system_program,
     transaction::Transaction,
 };
-use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account,
+};
 use spl_token::state::Account;
 
 const MINT_SIZE: u64 = 82;
@@ -27,6 +43,8 @@ struct PartyKeys {
 
 struct TestEnv {
     program_key: keypair::Keypair,
+    // upgrade authority of the program, and so the config admin
+    admin: keypair::Keypair,
     buyer: PartyKeys,
     seller: PartyKeys,
     seller_temp: keypair::Keypair,
@@ -51,270 +69,3327 @@ async fn test_offer_cancel() {
 }
 
 #[tokio::test]
-async fn test_cancel_recreate() {
+async fn test_accept_expected_terms() {
     let mut env = init_env().await;
-    let pda = create_offer(&mut env).await;
-    cancel_offer(&mut env, pda, false).await;
-    env.ctx.warp_to_slot(10).unwrap();
-    let pda = create_offer(&mut env).await;
-    accept_offer(&mut env, pda).await;
-}
+    let escrow = create_offer(&mut env).await;
+    let (pda, _bump) = Pubkey::find_program_address(
+        &[&escrow.offer_data.get_seed(), &escrow.index_seed],
+        &env.program_key.pubkey(),
+    );
 
-async fn init_env() -> TestEnv {
-    let program_key = keypair::Keypair::new();
-    let buyer = keypair::Keypair::new();
-    let seller = keypair::Keypair::new();
-    let seller_temp = keypair::Keypair::new();
-    let mint_1 = keypair::Keypair::new();
-    let mint_2 = keypair::Keypair::new();
-    let mut ctx = ProgramTest::new(
-        "sol_escrow",
-        program_key.pubkey(),
-        processor!(process_instruction),
-    )
-    .start_with_context()
-    .await;
+    let mut worse = escrow.offer_data;
+    worse.token_qty -= 1;
+    send_accept(&mut env, pda, Some(worse))
+        .await
+        .expect_err("accept with mismatched terms did not fail");
+    let mut other_mint = escrow.offer_data;
+    other_mint.strike_type = env.mint_1.pubkey();
+    send_accept(&mut env, pda, Some(other_mint))
+        .await
+        .expect_err("accept with mismatched strike mint did not fail");
 
-    let min_rent = Rent::default().minimum_balance(MINT_SIZE as usize);
+    send_accept(&mut env, pda, None).await.unwrap();
+    assert_closed(&mut env, pda).await;
+}
 
-    let c1 = system_instruction::create_account(
-        &ctx.payer.pubkey(),
-        &mint_1.pubkey(),
-        min_rent,
-        MINT_SIZE,
-        &spl_token::id(),
-    );
-    let c2 = system_instruction::create_account(
-        &ctx.payer.pubkey(),
-        &mint_2.pubkey(),
-        min_rent,
-        MINT_SIZE,
-        &spl_token::id(),
+#[tokio::test]
+async fn test_accept_creates_atas() {
+    let mut env = init_env().await;
+    let wallet = keypair::Keypair::new().pubkey();
+    let (pda, seller_receive) = create_offer_to_wallet(&mut env, wallet).await;
+    assert_eq!(
+        seller_receive,
+        get_associated_token_address(&wallet, &env.mint_2.pubkey())
     );
 
-    let i1 = spl_token::instruction::initialize_mint(
-        &spl_token::id(),
-        &mint_1.pubkey(),
-        &ctx.payer.pubkey(),
-        None,
-        1,
-    )
-    .expect("could not create initialise_mint instruction");
-
-    let i2 = spl_token::instruction::initialize_mint(
+    let close_ix = spl_token::instruction::close_account(
         &spl_token::id(),
-        &mint_2.pubkey(),
-        &ctx.payer.pubkey(),
-        None,
-        1,
+        &env.buyer.mint_1,
+        &env.buyer.main.pubkey(),
+        &env.buyer.main.pubkey(),
+        &[],
     )
-    .expect("could not create initialise_mint instruction");
-
+    .unwrap();
     let tx = Transaction::new_signed_with_payer(
-        &[c1],
-        Some(&ctx.payer.pubkey()),
-        &[&ctx.payer, &mint_1],
-        ctx.last_blockhash,
+        &[close_ix],
+        Some(&env.ctx.payer.pubkey()),
+        &[&env.ctx.payer, &env.buyer.main],
+        env.ctx.last_blockhash,
     );
-    ctx.banks_client.process_transaction(tx).await.unwrap();
+    env.ctx.banks_client.process_transaction(tx).await.unwrap();
+    let buyer_receive = env.buyer.mint_1;
+    assert_closed(&mut env, buyer_receive).await;
 
-    let tx = Transaction::new_signed_with_payer(
-        &[c2],
-        Some(&ctx.payer.pubkey()),
-        &[&ctx.payer, &mint_2],
-        ctx.last_blockhash,
-    );
-    ctx.banks_client.process_transaction(tx).await.unwrap();
+    accept_with_atas(&mut env, pda, seller_receive, wallet)
+        .await
+        .unwrap();
+    assert_closed(&mut env, pda).await;
+    assert_eq!(get_token_balance(&mut env, buyer_receive).await, 5);
+    assert_eq!(get_token_balance(&mut env, seller_receive).await, 5);
+}
 
-    let tx = Transaction::new_signed_with_payer(
-        &[i1],
-        Some(&ctx.payer.pubkey()),
-        &[&ctx.payer],
-        ctx.last_blockhash,
+#[tokio::test]
+async fn test_instruction_builders() {
+    let mut env = init_env().await;
+    let program_id = env.program_key.pubkey();
+    let offer_data = OfferData {
+        token_type: env.mint_1.pubkey(),
+        token_qty: 5,
+        strike_type: env.mint_2.pubkey(),
+        strike_qty: 5,
+    };
+    let index_seed = instruction::index_seed(&env.seller.main.pubkey(), 0, 3);
+    let pda = instruction::escrow_address(&program_id, &offer_data, &index_seed);
+    let ix = instruction::offer(
+        &program_id,
+        &env.seller.main.pubkey(),
+        &env.seller_temp.pubkey(),
+        &env.seller.mint_2,
+        &offer_data,
+        &index_seed,
     );
-    ctx.banks_client.process_transaction(tx).await.unwrap();
-
     let tx = Transaction::new_signed_with_payer(
-        &[i2],
-        Some(&ctx.payer.pubkey()),
-        &[&ctx.payer],
-        ctx.last_blockhash,
+        &[ix],
+        Some(&env.ctx.payer.pubkey()),
+        &[&env.ctx.payer, &env.seller.main],
+        env.ctx.last_blockhash,
     );
-    ctx.banks_client.process_transaction(tx).await.unwrap();
+    env.ctx.banks_client.process_transaction(tx).await.unwrap();
 
-    let split_txs = system_instruction::transfer_many(
-        &ctx.payer.pubkey(),
-        &[(buyer.pubkey(), 100000000), (seller.pubkey(), 100000000)],
+    let acc = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let escrow = EscrowPDA::unpack_from_slice(&acc.data[..]).unwrap();
+    let ix = instruction::accept(
+        &program_id,
+        &env.buyer.main.pubkey(),
+        &env.buyer.mint_2,
+        &env.buyer.mint_1,
+        &pda,
+        &escrow,
     );
     let tx = Transaction::new_signed_with_payer(
-        &split_txs[0..2],
-        Some(&ctx.payer.pubkey()),
-        &[&ctx.payer],
-        ctx.last_blockhash,
+        &[ix],
+        Some(&env.ctx.payer.pubkey()),
+        &[&env.ctx.payer, &env.buyer.main],
+        env.ctx.last_blockhash,
     );
-    ctx.banks_client.process_transaction(tx).await.unwrap();
+    env.ctx.banks_client.process_transaction(tx).await.unwrap();
+    assert_closed(&mut env, pda).await;
+    let buyer_receive = env.buyer.mint_1;
+    assert_eq!(get_token_balance(&mut env, buyer_receive).await, 5);
+}
 
-    let b1 =
-        create_associated_token_account(&ctx.payer.pubkey(), &buyer.pubkey(), &mint_1.pubkey());
-    let b2 =
-        create_associated_token_account(&ctx.payer.pubkey(), &buyer.pubkey(), &mint_2.pubkey());
-    let b3 =
-        create_associated_token_account(&ctx.payer.pubkey(), &seller.pubkey(), &mint_1.pubkey());
-    let b4 =
-        create_associated_token_account(&ctx.payer.pubkey(), &seller.pubkey(), &mint_2.pubkey());
+#[tokio::test]
+async fn test_cancel_recreate() {
+    let mut env = init_env().await;
+    let pda = create_offer(&mut env).await;
+    cancel_offer(&mut env, pda, false).await;
+    env.ctx.warp_to_slot(10).unwrap();
+    let pda = create_offer(&mut env).await;
+    accept_offer(&mut env, pda).await;
+}
 
-    let tx = Transaction::new_signed_with_payer(
-        &[b1, b2, b3, b4],
-        Some(&ctx.payer.pubkey()),
-        &[&ctx.payer],
-        ctx.last_blockhash,
-    );
-    ctx.banks_client.process_transaction(tx).await.unwrap();
+#[tokio::test]
+async fn test_operator_cancel() {
+    let mut env = init_env().await;
+    let operator = keypair::Keypair::new();
+    let next_operator = keypair::Keypair::new();
+    let escrow = create_offer_with(&mut env, Some(operator.pubkey()), None).await;
+    cancel_offer_by_operator(&mut env, &escrow, &keypair::Keypair::new())
+        .await
+        .expect_err("cancel by unknown operator did not fail");
 
-    let b1 = get_associated_token_address(&buyer.pubkey(), &mint_1.pubkey());
-    let b2 = get_associated_token_address(&buyer.pubkey(), &mint_2.pubkey());
-    let s1 = get_associated_token_address(&seller.pubkey(), &mint_1.pubkey());
-    let s2 = get_associated_token_address(&seller.pubkey(), &mint_2.pubkey());
+    set_operator(&mut env, &escrow, Some(next_operator.pubkey()))
+        .await
+        .unwrap();
+    cancel_offer_by_operator(&mut env, &escrow, &operator)
+        .await
+        .expect_err("cancel by replaced operator did not fail");
+    cancel_offer_by_operator(&mut env, &escrow, &next_operator)
+        .await
+        .unwrap();
 
-    let min_rent = Rent::default().minimum_balance(165);
+    let (pda, _bump) = Pubkey::find_program_address(
+        &[&escrow.offer_data.get_seed(), &escrow.index_seed],
+        &env.program_key.pubkey(),
+    );
+    assert_closed(&mut env, pda).await;
+    let temp = env
+        .ctx
+        .banks_client
+        .get_account(env.seller_temp.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Account::unpack_from_slice(&temp.data[..]).unwrap().owner,
+        env.seller.main.pubkey(),
+        "seller_temp not returned to seller"
+    );
+}
 
-    let st = system_instruction::create_account(
-        &seller.pubkey(),
-        &seller_temp.pubkey(),
-        min_rent,
-        165,
-        &spl_token::id(),
+#[tokio::test]
+async fn test_operator_amend() {
+    let mut env = init_env().await;
+    let program_id = env.program_key.pubkey();
+    let operator = keypair::Keypair::new();
+    let mut escrow = create_offer_with(&mut env, Some(operator.pubkey()), None).await;
+    let (mut pda, _bump) = Pubkey::find_program_address(
+        &[&escrow.offer_data.get_seed(), &escrow.index_seed],
+        &program_id,
     );
 
-    let sti = spl_token::instruction::initialize_account(
-        &spl_token::id(),
-        &seller_temp.pubkey(),
-        &mint_1.pubkey(),
-        &seller.pubkey(),
-    )
-    .unwrap();
+    // start from an offer in the 241 byte format, which amending rewrites in the current one
+    let mut account = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data = shipped_escrow_data(&escrow, EscrowPDA::V1_OPERATOR_LEN);
+    account.lamports = Rent::default().minimum_balance(EscrowPDA::V1_OPERATOR_LEN);
+    env.ctx.set_account(&pda, &account.into());
+    escrow.rent_payer = escrow.seller_main;
+    escrow.bump = 0;
 
-    let tx = Transaction::new_signed_with_payer(
-        &[st, sti],
-        Some(&ctx.payer.pubkey()),
-        &[&seller_temp, &seller, &ctx.payer],
-        ctx.last_blockhash,
-    );
-    ctx.banks_client.process_transaction(tx).await.unwrap();
+    let stranger = keypair::Keypair::new();
+    let ix = instruction::amend(&program_id, &pda, &escrow, 4, Some(&stranger.pubkey()));
+    send_signed(&mut env, ix, &stranger)
+        .await
+        .expect_err("amend by unknown operator did not fail");
 
-    let asset_mint = spl_token::instruction::mint_to(
-        &spl_token::id(),
-        &mint_1.pubkey(),
-        &seller_temp.pubkey(),
-        &ctx.payer.pubkey(),
-        &[&ctx.payer.pubkey()],
-        5,
-    )
-    .unwrap();
+    let seller = keypair::Keypair::from_bytes(&env.seller.main.to_bytes()).unwrap();
+    for (strike_qty, signer) in [(4, &seller), (3, &operator)] {
+        let operator_key = (signer.pubkey() != seller.pubkey()).then(|| signer.pubkey());
+        let ix = instruction::amend(
+            &program_id,
+            &pda,
+            &escrow,
+            strike_qty,
+            operator_key.as_ref(),
+        );
+        let new_pda = ix.accounts[3].pubkey;
+        send_signed(&mut env, ix, signer).await.unwrap();
+        assert_closed(&mut env, pda).await;
 
-    let strike_mint = spl_token::instruction::mint_to(
+        let account = env
+            .ctx
+            .banks_client
+            .get_account(new_pda)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(account.data.len(), EscrowPDA::LEN);
+        assert_eq!(
+            account.lamports,
+            Rent::default().minimum_balance(EscrowPDA::LEN)
+        );
+        let amended = EscrowPDA::unpack_from_slice(&account.data).unwrap();
+        let (_pda, bump) = Pubkey::find_program_address(
+            &[&amended.offer_data.get_seed(), &amended.index_seed],
+            &program_id,
+        );
+        assert_eq!(amended.offer_data.strike_qty, strike_qty);
+        assert_eq!(amended.offer_data.token_qty, escrow.offer_data.token_qty);
+        assert_eq!(amended.seller_receive, escrow.seller_receive);
+        assert_eq!(amended.operator, Some(operator.pubkey()));
+        assert_eq!(amended.rent_payer, escrow.seller_main);
+        assert_eq!(amended.bump, bump);
+
+        let temp = env
+            .ctx
+            .banks_client
+            .get_account(escrow.seller_temp)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Account::unpack_from_slice(&temp.data[..]).unwrap().owner,
+            new_pda
+        );
+        escrow = amended;
+        pda = new_pda;
+    }
+    accept_offer(&mut env, escrow).await;
+}
+
+#[tokio::test]
+async fn test_cancel_many() {
+    let mut env = init_env().await;
+    let mut offers = vec![];
+    for contract_no in 0..MAX_CANCEL_BATCH as u64 {
+        if contract_no > 0 {
+            env.seller_temp = new_seller_temp(&mut env, 5).await;
+        }
+        env.index_seed[33..41].copy_from_slice(&contract_no.to_le_bytes());
+        let escrow = create_offer(&mut env).await;
+        let (pda, _bump) = Pubkey::find_program_address(
+            &[&escrow.offer_data.get_seed(), &escrow.index_seed],
+            &env.program_key.pubkey(),
+        );
+        offers.push((pda, escrow.seller_temp));
+    }
+
+    let mismatched = [offers[0], (offers[1].0, offers[2].1)];
+    cancel_many(&mut env, &mismatched, 2)
+        .await
+        .expect_err("batch with mismatched seller_temp did not fail");
+    let first = env.ctx.banks_client.get_account(offers[0].0).await.unwrap();
+    assert!(first.is_some(), "failed batch partially applied");
+
+    cancel_many(&mut env, &[], MAX_CANCEL_BATCH as u8 + 1)
+        .await
+        .expect_err("oversized batch did not fail");
+
+    // a full batch, tests/bench.rs also checks its transaction size and compute units
+    cancel_many(&mut env, &offers, MAX_CANCEL_BATCH as u8)
+        .await
+        .unwrap();
+    for (pda, temp) in offers {
+        assert_closed(&mut env, pda).await;
+        let temp = env
+            .ctx
+            .banks_client
+            .get_account(temp)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Account::unpack_from_slice(&temp.data[..]).unwrap().owner,
+            env.seller.main.pubkey(),
+            "seller_temp not returned to seller"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_create_many() {
+    let mut env = init_env().await;
+    let funding = env.seller.mint_1;
+    let mint_ix = spl_token::instruction::mint_to(
         &spl_token::id(),
-        &mint_2.pubkey(),
-        &b2,
-        &ctx.payer.pubkey(),
-        &[&ctx.payer.pubkey()],
-        5,
+        &env.mint_1.pubkey(),
+        &funding,
+        &env.ctx.payer.pubkey(),
+        &[&env.ctx.payer.pubkey()],
+        6,
     )
     .unwrap();
-
     let tx = Transaction::new_signed_with_payer(
-        &[asset_mint, strike_mint],
-        Some(&ctx.payer.pubkey()),
-        &[&ctx.payer],
-        ctx.last_blockhash,
+        &[mint_ix],
+        Some(&env.ctx.payer.pubkey()),
+        &[&env.ctx.payer],
+        env.ctx.last_blockhash,
     );
-    ctx.banks_client.process_transaction(tx).await.unwrap();
+    env.ctx.banks_client.process_transaction(tx).await.unwrap();
 
-    let buyer = PartyKeys {
-        main: buyer,
-        mint_1: b1,
-        mint_2: b2,
-    };
+    let mut ladder = vec![];
+    for (contract_no, strike_qty) in [(10u64, 3u64), (11, 4), (12, 5)] {
+        let temp = new_seller_temp(&mut env, 0).await;
+        let offer_data = OfferData {
+            token_type: env.mint_1.pubkey(),
+            token_qty: 2,
+            strike_type: env.mint_2.pubkey(),
+            strike_qty,
+        };
+        let mut index_seed = env.index_seed;
+        index_seed[33..41].copy_from_slice(&contract_no.to_le_bytes());
+        ladder.push((offer_data, index_seed, temp.pubkey()));
+    }
 
-    let seller = PartyKeys {
-        main: seller,
-        mint_1: s1,
-        mint_2: s2,
-    };
+    let mut invalid = ladder.clone();
+    invalid[2].0.token_type = env.mint_2.pubkey();
+    create_many(&mut env, &invalid)
+        .await
+        .expect_err("ladder with invalid entry did not fail");
+    assert_eq!(
+        get_token_balance(&mut env, funding).await,
+        6,
+        "failed ladder partially applied"
+    );
 
-    let mut index_seed = [0; 41];
-    index_seed[..32].copy_from_slice(&seller.main.pubkey().to_bytes());
+    create_many(&mut env, &ladder).await.unwrap();
+    assert_eq!(get_token_balance(&mut env, funding).await, 0);
+    for (offer_data, index_seed, temp) in ladder {
+        let (pda, _bump) = Pubkey::find_program_address(
+            &[&offer_data.get_seed(), &get_seed(&index_seed)],
+            &env.program_key.pubkey(),
+        );
+        let acc = env
+            .ctx
+            .banks_client
+            .get_account(pda)
+            .await
+            .unwrap()
+            .expect("ladder offer not created");
+        let escrow = EscrowPDA::unpack_from_slice(&acc.data[..]).unwrap();
+        assert_eq!(escrow.offer_data, offer_data);
+        assert_eq!(escrow.seller_temp, temp);
+        assert_eq!(get_token_balance(&mut env, temp).await, 2);
+    }
+}
 
-    TestEnv {
+#[tokio::test]
+async fn test_rent_payer_refund() {
+    let mut env = init_env().await;
+    let relayer = keypair::Keypair::new();
+    let fund_ix =
+        system_instruction::transfer(&env.ctx.payer.pubkey(), &relayer.pubkey(), 100000000);
+    let tx = Transaction::new_signed_with_payer(
+        &[fund_ix],
+        Some(&env.ctx.payer.pubkey()),
+        &[&env.ctx.payer],
+        env.ctx.last_blockhash,
+    );
+    env.ctx.banks_client.process_transaction(tx).await.unwrap();
+
+    let seller = env.seller.main.pubkey();
+    let seller_lamports = get_lamports(&mut env, seller).await;
+    let escrow = create_offer_with(&mut env, None, Some(&relayer)).await;
+    assert_eq!(
+        get_lamports(&mut env, seller).await,
+        seller_lamports,
+        "seller paid PDA rent"
+    );
+    assert_eq!(
+        get_lamports(&mut env, relayer.pubkey()).await,
+        100000000 - Rent::default().minimum_balance(EscrowPDA::LEN)
+    );
+
+    cancel_offer_with_rent_payer(&mut env, &escrow, None)
+        .await
+        .expect_err("cancel without rent payer account did not fail");
+    cancel_offer_with_rent_payer(&mut env, &escrow, Some(relayer.pubkey()))
+        .await
+        .unwrap();
+    assert_eq!(get_lamports(&mut env, relayer.pubkey()).await, 100000000);
+}
+
+#[tokio::test]
+async fn test_v1_escrow_account() {
+    let mut env = init_env().await;
+    let escrow = create_offer(&mut env).await;
+    let (pda, _bump) = Pubkey::find_program_address(
+        &[&escrow.offer_data.get_seed(), &escrow.index_seed],
+        &env.program_key.pubkey(),
+    );
+
+    // rewrite the escrow as the v1 format wrote it
+    let mut account = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data = shipped_escrow_data(&escrow, EscrowPDA::V1_LEN);
+    account.lamports = Rent::default().minimum_balance(EscrowPDA::V1_LEN);
+    env.ctx.set_account(&pda, &account.into());
+
+    let seller = env.seller.main.pubkey();
+    let seller_lamports = get_lamports(&mut env, seller).await;
+    accept_offer(&mut env, escrow).await;
+    assert_eq!(
+        get_lamports(&mut env, seller).await,
+        seller_lamports
+            + Rent::default().minimum_balance(EscrowPDA::V1_LEN)
+            + Rent::default().minimum_balance(Account::LEN)
+    );
+}
+
+// v2 accounts predate the stored bump, the processor searches for it instead
+#[tokio::test]
+async fn test_v2_escrow_account() {
+    let mut env = init_env().await;
+    let escrow = create_offer(&mut env).await;
+    let (pda, bump) = Pubkey::find_program_address(
+        &[&escrow.offer_data.get_seed(), &escrow.index_seed],
+        &env.program_key.pubkey(),
+    );
+    assert_eq!(escrow.bump, bump);
+
+    let mut account = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data = shipped_escrow_data(&escrow, EscrowPDA::V2_LEN);
+    env.ctx.set_account(&pda, &account.into());
+
+    let operator = keypair::Keypair::new();
+    set_operator(&mut env, &escrow, Some(operator.pubkey()))
+        .await
+        .unwrap();
+    let account = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), EscrowPDA::V2_LEN);
+    let v2 = EscrowPDA::unpack_from_slice(&account.data).unwrap();
+    assert_eq!(v2.operator, Some(operator.pubkey()));
+    assert_eq!(v2.bump, 0);
+
+    cancel_offer_by_operator(&mut env, &escrow, &operator)
+        .await
+        .unwrap();
+    assert!(env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_borsh_layout() {
+    let offer_data = OfferData {
+        token_type: Pubkey::new_unique(),
+        token_qty: 5,
+        strike_type: Pubkey::new_unique(),
+        strike_qty: 10,
+    };
+    let escrow = EscrowPDA {
+        seller_main: Pubkey::new_unique(),
+        seller_temp: Pubkey::new_unique(),
+        seller_receive: Pubkey::new_unique(),
+        offer_data,
+        index_seed: [7; 32],
+        rent_payer: Pubkey::new_unique(),
+        operator: Some(Pubkey::new_unique()),
+        bump: 254,
+    };
+    let mut data = vec![0; EscrowPDA::LEN];
+    escrow.pack_into_slice(&mut data);
+    for (offset, bytes) in [
+        (EscrowPDA::SELLER_MAIN_OFFSET, escrow.seller_main.as_ref()),
+        (EscrowPDA::SELLER_TEMP_OFFSET, escrow.seller_temp.as_ref()),
+        (
+            EscrowPDA::SELLER_RECEIVE_OFFSET,
+            escrow.seller_receive.as_ref(),
+        ),
+        (EscrowPDA::TOKEN_TYPE_OFFSET, offer_data.token_type.as_ref()),
+        (
+            EscrowPDA::TOKEN_QTY_OFFSET,
+            &offer_data.token_qty.to_le_bytes(),
+        ),
+        (
+            EscrowPDA::STRIKE_TYPE_OFFSET,
+            offer_data.strike_type.as_ref(),
+        ),
+        (
+            EscrowPDA::STRIKE_QTY_OFFSET,
+            &offer_data.strike_qty.to_le_bytes(),
+        ),
+        (EscrowPDA::INDEX_SEED_OFFSET, &escrow.index_seed),
+        (EscrowPDA::RENT_PAYER_OFFSET, escrow.rent_payer.as_ref()),
+        (EscrowPDA::OPERATOR_OFFSET, &[1]),
+        (
+            EscrowPDA::OPERATOR_OFFSET + 1,
+            escrow.operator.unwrap().as_ref(),
+        ),
+        (EscrowPDA::BUMP_OFFSET, &[254]),
+    ] {
+        assert_eq!(&data[offset..offset + bytes.len()], bytes);
+    }
+    assert_eq!(EscrowPDA::unpack_from_slice(&data).unwrap(), escrow);
+
+    let v1 = EscrowPDA::unpack_from_slice(&data[..EscrowPDA::V1_LEN]).unwrap();
+    assert_eq!(v1.rent_payer, escrow.seller_main);
+    assert_eq!(v1.operator, None);
+    assert_eq!(v1.offer_data, offer_data);
+    assert_eq!(v1.bump, 0);
+
+    let v2 = EscrowPDA::unpack_from_slice(&data[..EscrowPDA::V2_LEN]).unwrap();
+    assert_eq!(v2.operator, escrow.operator);
+    assert_eq!(v2.bump, 0);
+    let mut packed = vec![0; EscrowPDA::V2_LEN];
+    v2.pack_into_slice(&mut packed);
+    assert_eq!(packed, data[..EscrowPDA::V2_LEN]);
+
+    // the legacy tag-0 format sends the raw index seed, borsh clients send its hash
+    let index_seed = instruction::index_seed(&escrow.seller_main, 0, 3);
+    let legacy = [&[0], &offer_data.to_bytes()[..], &index_seed[..]].concat();
+    let borsh = InstructionType::OFFER {
+        offer_data,
+        index_seed: get_seed(&index_seed),
+    };
+    assert_eq!(borsh.try_to_vec().unwrap().len(), 1 + OfferData::LEN + 32);
+    assert_eq!(decode_instruction(&legacy).unwrap(), borsh);
+    assert_eq!(
+        decode_instruction(&borsh.try_to_vec().unwrap()).unwrap(),
+        borsh
+    );
+    // baseline accept and cancel send the raw index seed too, and take no terms from it
+    let accept = InstructionType::ACCEPT {
+        expected: Some(offer_data),
+    };
+    assert_eq!(
+        decode_instruction(&accept.try_to_vec().unwrap()).unwrap(),
+        accept
+    );
+    for (tag, baseline) in [
+        (1, InstructionType::ACCEPT { expected: None }),
+        (2, InstructionType::CANCEL),
+    ] {
+        let legacy = [&[tag], &index_seed[..]].concat();
+        assert_eq!(decode_instruction(&legacy).unwrap(), baseline);
+    }
+    // other lengths and tags are borsh only, so short or pre-borsh payloads fail to decode
+    let short = [&[0], &offer_data.to_bytes()[..40]].concat();
+    assert_eq!(
+        decode_instruction(&short),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    let execute = InstructionType::MULTISIG_EXECUTE { data: vec![1; 40] };
+    let raw_execute = [&[18], &[1; 40][..]].concat();
+    assert_eq!(
+        decode_instruction(&execute.try_to_vec().unwrap()).unwrap(),
+        execute
+    );
+    assert_eq!(
+        decode_instruction(&raw_execute),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    // milestone data has a single fixed format, sent in the instruction and stored in the PDA
+    let milestone_data = MilestoneData {
+        token_type: Pubkey::new_unique(),
+        payee: Pubkey::new_unique(),
+        milestones: vec![
+            Milestone {
+                amount: 3,
+                deadline: None,
+            },
+            Milestone {
+                amount: 4,
+                deadline: Some(-9),
+            },
+        ],
+    };
+    let fixed = milestone_data.try_to_vec().unwrap();
+    assert_eq!(fixed.len(), MilestoneData::len(2));
+    assert_eq!(fixed[64], 2);
+    assert_eq!(fixed[65..73], 3u64.to_le_bytes());
+    assert_eq!(fixed[73..81], [0; 8]);
+    assert_eq!(fixed[89..97], (-9i64).to_le_bytes());
+    let borsh = InstructionType::MILESTONE_OFFER {
+        milestone_data,
+        index_seed: get_seed(&index_seed),
+    };
+    let encoded = [&[11], &fixed[..], &get_seed(&index_seed)[..]].concat();
+    assert_eq!(borsh.try_to_vec().unwrap(), encoded);
+    assert_eq!(decode_instruction(&encoded).unwrap(), borsh);
+
+    // so does multisig data, padded to MAX_SIGNERS signers
+    let multisig_data = MultisigData {
+        m: 1,
+        signers: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+    };
+    let fixed = multisig_data.try_to_vec().unwrap();
+    assert_eq!(fixed, multisig_data.to_bytes());
+    assert_eq!(fixed[1], 2);
+    assert_eq!(MultisigData::try_from_slice(&fixed).unwrap(), multisig_data);
+    let mut too_many = fixed;
+    too_many[1] = MultisigData::MAX_SIGNERS as u8 + 1;
+    assert!(MultisigData::try_from_slice(&too_many).is_err());
+}
+
+// escrow data as the shipped formats wrote it, field by field: v1 accounts are 208 bytes, or
+// 241 with the operator, and v2 accounts 273, with the operator option padded to 33 bytes
+// before rent_payer
+fn shipped_escrow_data(escrow: &EscrowPDA, len: usize) -> Vec<u8> {
+    let mut operator = [0; 33];
+    if let Some(key) = escrow.operator {
+        operator[0] = 1;
+        operator[1..].copy_from_slice(key.as_ref());
+    }
+    let offer_data = escrow.offer_data.to_bytes();
+    let fields: [&[u8]; 7] = [
+        escrow.seller_main.as_ref(),
+        escrow.seller_temp.as_ref(),
+        escrow.seller_receive.as_ref(),
+        &offer_data,
+        &escrow.index_seed,
+        &operator,
+        escrow.rent_payer.as_ref(),
+    ];
+    let count = match len {
+        EscrowPDA::V1_LEN => 5,
+        EscrowPDA::V1_OPERATOR_LEN => 6,
+        EscrowPDA::V2_LEN => 7,
+        _ => panic!("no shipped escrow format is {} bytes", len),
+    };
+    let data = fields[..count].concat();
+    assert_eq!(data.len(), len);
+    data
+}
+
+// offers posted while the operator was the last field are 241 bytes, with rent paid by the seller
+#[tokio::test]
+async fn test_v1_operator_escrow_account() {
+    let mut env = init_env().await;
+    let mut escrow = create_offer(&mut env).await;
+    let (pda, _bump) = Pubkey::find_program_address(
+        &[&escrow.offer_data.get_seed(), &escrow.index_seed],
+        &env.program_key.pubkey(),
+    );
+    let operator = keypair::Keypair::new();
+    escrow.operator = Some(operator.pubkey());
+
+    let mut account = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data = shipped_escrow_data(&escrow, EscrowPDA::V1_OPERATOR_LEN);
+    account.lamports = Rent::default().minimum_balance(EscrowPDA::V1_OPERATOR_LEN);
+    env.ctx.set_account(&pda, &account.into());
+
+    let decoded =
+        EscrowPDA::unpack_from_slice(&shipped_escrow_data(&escrow, EscrowPDA::V1_OPERATOR_LEN))
+            .unwrap();
+    assert_eq!(decoded.operator, Some(operator.pubkey()));
+    assert_eq!(decoded.rent_payer, escrow.seller_main);
+    assert_eq!(decoded.bump, 0);
+
+    let seller = env.seller.main.pubkey();
+    let seller_lamports = get_lamports(&mut env, seller).await;
+    cancel_offer_by_operator(&mut env, &escrow, &operator)
+        .await
+        .unwrap();
+    assert_closed(&mut env, pda).await;
+    assert_eq!(
+        get_lamports(&mut env, seller).await,
+        seller_lamports + Rent::default().minimum_balance(EscrowPDA::V1_OPERATOR_LEN)
+    );
+}
+
+#[test]
+fn test_v2_layout() {
+    let escrow = |bump| EscrowPDA {
+        seller_main: Pubkey::new_from_array([1; 32]),
+        seller_temp: Pubkey::new_from_array([2; 32]),
+        seller_receive: Pubkey::new_from_array([3; 32]),
+        offer_data: OfferData {
+            token_type: Pubkey::new_from_array([4; 32]),
+            token_qty: 5,
+            strike_type: Pubkey::new_from_array([6; 32]),
+            strike_qty: 7,
+        },
+        index_seed: [8; 32],
+        operator: Some(Pubkey::new_from_array([9; 32])),
+        rent_payer: Pubkey::new_from_array([10; 32]),
+        bump,
+    };
+    let data = shipped_escrow_data(&escrow(254), EscrowPDA::V2_LEN);
+    assert_eq!(EscrowPDA::unpack_from_slice(&data).unwrap(), escrow(0));
+
+    // the current format extends v2 with the bump, without moving any v2 field
+    let mut packed = vec![0; EscrowPDA::LEN];
+    escrow(254).pack_into_slice(&mut packed);
+    assert_eq!(packed[..EscrowPDA::V2_LEN], data[..]);
+    assert_eq!(packed[EscrowPDA::BUMP_OFFSET], 254);
+}
+
+#[test]
+fn test_event_logs() {
+    let program_id = Pubkey::new_unique();
+    let offer_data = OfferData {
+        token_type: Pubkey::new_unique(),
+        token_qty: 5,
+        strike_type: Pubkey::new_unique(),
+        strike_qty: 7,
+    };
+    let created = EscrowEvent::OfferCreated {
+        escrow_pda: Pubkey::new_unique(),
+        seller: Pubkey::new_unique(),
+        seller_temp: Pubkey::new_unique(),
+        seller_receive: Pubkey::new_unique(),
+        offer_data,
+    };
+    let cancelled = EscrowEvent::OfferCancelled {
+        escrow_pda: Pubkey::new_unique(),
+        seller: Pubkey::new_unique(),
+        offer_data,
+    };
+    let mut future_version = cancelled.to_bytes();
+    future_version[0] = EVENT_VERSION + 1;
+    let data_log = |data: &[u8]| format!("Program data: {}", STANDARD.encode(data));
+
+    let logs = vec![
+        format!("Program {} invoke [1]", program_id),
+        format!("Program {} invoke [2]", spl_token::id()),
+        data_log(&created.to_bytes()),
+        format!("Program {} success", spl_token::id()),
+        data_log(&created.to_bytes()),
+        data_log(&future_version),
+        "Program log: closing PDA".to_string(),
+        data_log(&cancelled.to_bytes()),
+        format!("Program {} success", program_id),
+    ];
+    assert_eq!(
+        EscrowEvent::from_logs(&program_id, &logs),
+        vec![created.clone(), cancelled]
+    );
+
+    // program logs that look like runtime lines do not change the invoke stack
+    let spoofed = vec![
+        format!("Program {} invoke [1]", program_id),
+        "Program log: success".to_string(),
+        format!("Program log: {} success", program_id),
+        data_log(&created.to_bytes()),
+        format!("Program {} success", program_id),
+    ];
+    assert_eq!(
+        EscrowEvent::from_logs(&program_id, &spoofed),
+        vec![created.clone()]
+    );
+
+    // a failed transaction is rolled back, so none of its events happened
+    let failed = vec![
+        format!("Program {} invoke [1]", program_id),
+        data_log(&created.to_bytes()),
+        format!("Program {} success", program_id),
+        format!("Program {} invoke [1]", spl_token::id()),
+        format!(
+            "Program {} failed: custom program error: 0x1",
+            spl_token::id()
+        ),
+    ];
+    assert_eq!(EscrowEvent::from_logs(&program_id, &failed), vec![]);
+}
+
+#[tokio::test]
+async fn test_htlc_claim() {
+    let mut env = init_env().await;
+    let preimage = [7; 32];
+    let htlc = create_htlc(&mut env, get_seed(&preimage)).await;
+    claim_htlc(&mut env, &htlc, [8; 32])
+        .await
+        .expect_err("claim with invalid preimage did not fail");
+
+    let buyer_receive = env.buyer.mint_1;
+    let buyer_init_balance = get_token_balance(&mut env, buyer_receive).await;
+    claim_htlc(&mut env, &htlc, preimage).await.unwrap();
+    let buyer_balance = get_token_balance(&mut env, buyer_receive).await;
+    assert_eq!(
+        buyer_balance - buyer_init_balance,
+        htlc.htlc_data.token_qty,
+        "incorrect token qty transferred to buyer"
+    );
+    let pda = htlc_pda(&env, &htlc);
+    assert_closed(&mut env, pda).await;
+}
+
+#[tokio::test]
+async fn test_htlc_refund() {
+    let mut env = init_env().await;
+    let preimage = [7; 32];
+    let htlc = create_htlc(&mut env, get_seed(&preimage)).await;
+    refund_htlc(&mut env, &htlc)
+        .await
+        .expect_err("refund before expiry did not fail");
+
+    set_unix_timestamp(&mut env, htlc.htlc_data.expiry).await;
+    claim_htlc(&mut env, &htlc, preimage)
+        .await
+        .expect_err("claim after expiry did not fail");
+    refund_htlc(&mut env, &htlc).await.unwrap();
+    let pda = htlc_pda(&env, &htlc);
+    assert_closed(&mut env, pda).await;
+
+    let temp = env
+        .ctx
+        .banks_client
+        .get_account(env.seller_temp.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Account::unpack_from_slice(&temp.data[..]).unwrap().owner,
+        env.seller.main.pubkey(),
+        "seller_temp not returned to seller"
+    );
+}
+
+#[tokio::test]
+async fn test_pegged_accept() {
+    let mut env = init_env().await;
+    let clock: Clock = env.ctx.banks_client.get_sysvar().await.unwrap();
+    // 0.9 strike per token, +1% spread: 5 tokens cost 4.545, rounded up to 5
+    let oracle = Pubkey::new_unique();
+    set_oracle(&mut env, oracle, 9_000, clock.unix_timestamp);
+    let pegged = create_pegged(&mut env, oracle).await;
+    assert_eq!(
+        pegged.pegged_data.strike_qty(&OraclePrice {
+            price: 9_000,
+            expo: 4,
+            publish_time: 0,
+        }),
+        Ok(5)
+    );
+
+    accept_pegged(&mut env, &pegged, oracle, 4)
+        .await
+        .expect_err("accept above taker limit did not fail");
+    let fake_oracle = Pubkey::new_unique();
+    set_oracle(&mut env, fake_oracle, 1, clock.unix_timestamp);
+    accept_pegged(&mut env, &pegged, fake_oracle, 5)
+        .await
+        .expect_err("accept with wrong oracle did not fail");
+    set_unix_timestamp(&mut env, clock.unix_timestamp + 61).await;
+    accept_pegged(&mut env, &pegged, oracle, 5)
+        .await
+        .expect_err("accept with stale oracle did not fail");
+
+    set_oracle(&mut env, oracle, 9_000, clock.unix_timestamp + 61);
+    env.ctx.get_new_latest_blockhash().await.unwrap();
+    let seller_receive = env.seller.mint_2;
+    let seller_init_balance = get_token_balance(&mut env, seller_receive).await;
+    accept_pegged(&mut env, &pegged, oracle, 5).await.unwrap();
+    assert_eq!(
+        get_token_balance(&mut env, seller_receive).await - seller_init_balance,
+        5,
+        "incorrect strike transferred to seller"
+    );
+    let buyer_receive = env.buyer.mint_1;
+    assert_eq!(get_token_balance(&mut env, buyer_receive).await, 5);
+    let pda = pegged_pda(&env, &pegged);
+    assert_closed(&mut env, pda).await;
+}
+
+#[tokio::test]
+async fn test_pegged_cancel() {
+    let mut env = init_env().await;
+    let oracle = Pubkey::new_unique();
+    let pegged = create_pegged(&mut env, oracle).await;
+    let program_id = env.program_key.pubkey();
+    let cancel = Instruction::new_with_bytes(
+        program_id,
+        &InstructionType::PEGGED_CANCEL.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(env.seller.main.pubkey(), true),
+            AccountMeta::new(env.seller_temp.pubkey(), false),
+            AccountMeta::new(pegged_pda(&env, &pegged), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[cancel],
+        Some(&env.ctx.payer.pubkey()),
+        &[&env.ctx.payer, &env.seller.main],
+        env.ctx.last_blockhash,
+    );
+    env.ctx.banks_client.process_transaction(tx).await.unwrap();
+
+    let pda = pegged_pda(&env, &pegged);
+    assert_closed(&mut env, pda).await;
+    let temp = env
+        .ctx
+        .banks_client
+        .get_account(env.seller_temp.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Account::unpack_from_slice(&temp.data[..]).unwrap().owner,
+        env.seller.main.pubkey(),
+        "seller_temp not returned to seller"
+    );
+}
+
+#[tokio::test]
+async fn test_limit_offer() {
+    let mut env = init_env().await;
+    // both mints have 1 decimal: 0.5 tokens at 1.0 per token is a 5 base unit strike
+    let limit_order = LimitOrder {
+        token_type: env.mint_1.pubkey(),
+        token_qty: 5,
+        strike_type: env.mint_2.pubkey(),
+        price: 1_000_000_000,
+    };
+    let mint_1 = env.mint_1.pubkey();
+    send_limit_offer(&mut env, &limit_order, mint_1)
+        .await
+        .expect_err("limit offer with wrong strike mint did not fail");
+    let zero_price = LimitOrder {
+        price: 0,
+        ..limit_order
+    };
+    let mint_2 = env.mint_2.pubkey();
+    send_limit_offer(&mut env, &zero_price, mint_2)
+        .await
+        .expect_err("limit offer with zero price did not fail");
+
+    send_limit_offer(&mut env, &limit_order, mint_2)
+        .await
+        .unwrap();
+    let offer_data = limit_order.offer_data(1, 1).unwrap();
+    assert_eq!(offer_data.strike_qty, 5);
+    let pda = instruction::escrow_address(&env.program_key.pubkey(), &offer_data, &env.index_seed);
+    let account = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let escrow = EscrowPDA::unpack_from_slice(&account.data).unwrap();
+    assert_eq!(escrow.offer_data, offer_data);
+    assert_eq!(escrow.offer_data.limit_price(1, 1), Ok(limit_order.price));
+    accept_offer(&mut env, escrow).await;
+}
+
+#[test]
+fn test_limit_price() {
+    let order = LimitOrder {
+        token_type: Pubkey::new_unique(),
+        token_qty: 2_500_000,
+        strike_type: Pubkey::new_unique(),
+        price: 1_234_500_000,
+    };
+    // 2.5 tokens of a 6 decimal mint at 1.2345 strike of a 9 decimal mint each
+    let offer_data = order.offer_data(6, 9).unwrap();
+    assert_eq!(offer_data.strike_qty, 3_086_250_000);
+    assert_eq!(offer_data.limit_price(6, 9), Ok(order.price));
+    // strike is rounded up for the seller, the displayed price down for the taker
+    let offer_data = LimitOrder { price: 1, ..order }.offer_data(6, 0).unwrap();
+    assert_eq!(offer_data.strike_qty, 1);
+    assert_eq!(offer_data.limit_price(6, 0), Ok(400_000_000));
+    let offer_data = LimitOrder { price: 0, ..order }.offer_data(6, 9).unwrap();
+    assert_eq!(offer_data.strike_qty, 0);
+    assert_eq!(
+        LimitOrder {
+            price: u64::MAX,
+            ..order
+        }
+        .offer_data(6, 9),
+        Err(ProgramError::ArithmeticOverflow)
+    );
+
+    assert_eq!(format_price(1_234_500_000), "1.2345");
+    assert_eq!(format_price(7_000_000_000), "7");
+    assert_eq!(format_price(5), "0.000000005");
+}
+
+#[tokio::test]
+async fn test_admin_pause() {
+    let mut env = init_env_without_config().await;
+    let program_id = env.program_key.pubkey();
+    let seller = keypair::Keypair::from_bytes(&env.seller.main.to_bytes()).unwrap();
+    let offer_data = OfferData {
+        token_type: env.mint_1.pubkey(),
+        token_qty: 5,
+        strike_type: env.mint_2.pubkey(),
+        strike_qty: 7,
+    };
+    let seller_temp = new_seller_temp(&mut env, 5).await;
+    let index_seed = instruction::index_seed(&env.seller.main.pubkey(), 0, 1);
+    let offer = instruction::offer(
+        &program_id,
+        &env.seller.main.pubkey(),
+        &seller_temp.pubkey(),
+        &env.seller.mint_2,
+        &offer_data,
+        &index_seed,
+    );
+    send_signed(&mut env, offer, &seller)
+        .await
+        .expect_err("offer before config init did not fail");
+
+    // only the upgrade authority can become the first admin
+    let admin = keypair::Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let next_admin = keypair::Keypair::new();
+    let init = instruction::config_init(&program_id, &seller.pubkey());
+    send_signed(&mut env, init, &seller)
+        .await
+        .expect_err("config init by non upgrade authority did not fail");
+    let init = instruction::config_init(&program_id, &admin.pubkey());
+    send_signed(&mut env, init.clone(), &admin).await.unwrap();
+    send_signed(&mut env, init, &admin)
+        .await
+        .expect_err("second config init did not fail");
+    let escrow = create_offer(&mut env).await;
+    let pda = instruction::escrow_address(&program_id, &escrow.offer_data, &env.index_seed);
+    let pause = instruction::set_paused(&program_id, &next_admin.pubkey(), true);
+    send_signed(&mut env, pause, &next_admin)
+        .await
+        .expect_err("pause by non-admin did not fail");
+    let pause = instruction::set_paused(&program_id, &admin.pubkey(), true);
+    send_signed(&mut env, pause, &admin).await.unwrap();
+
+    send_accept(&mut env, pda, Some(escrow.offer_data))
+        .await
+        .expect_err("accept while paused did not fail");
+    let offer = instruction::offer(
+        &program_id,
+        &env.seller.main.pubkey(),
+        &seller_temp.pubkey(),
+        &env.seller.mint_2,
+        &escrow.offer_data,
+        &index_seed,
+    );
+    send_signed(&mut env, offer, &seller)
+        .await
+        .expect_err("offer while paused did not fail");
+    // sellers can still reclaim their funds
+    cancel_offer(&mut env, escrow, false).await;
+
+    // admin moves in two steps, to a key that has to sign for it
+    let propose = instruction::propose_admin(&program_id, &admin.pubkey(), &Pubkey::default());
+    send_signed(&mut env, propose, &admin)
+        .await
+        .expect_err("proposing the default pubkey did not fail");
+    let propose =
+        instruction::propose_admin(&program_id, &next_admin.pubkey(), &next_admin.pubkey());
+    send_signed(&mut env, propose, &next_admin)
+        .await
+        .expect_err("proposal by non-admin did not fail");
+    let propose = instruction::propose_admin(&program_id, &admin.pubkey(), &next_admin.pubkey());
+    send_signed(&mut env, propose, &admin).await.unwrap();
+    let accept = instruction::accept_admin(&program_id, &seller.pubkey());
+    send_signed(&mut env, accept, &seller)
+        .await
+        .expect_err("accept by non-proposed admin did not fail");
+    let accept = instruction::accept_admin(&program_id, &next_admin.pubkey());
+    send_signed(&mut env, accept.clone(), &next_admin)
+        .await
+        .unwrap();
+    send_signed(&mut env, accept, &next_admin)
+        .await
+        .expect_err("second admin accept did not fail");
+    let resume = instruction::set_paused(&program_id, &admin.pubkey(), false);
+    send_signed(&mut env, resume, &admin)
+        .await
+        .expect_err("resume by previous admin did not fail");
+    let resume = instruction::set_paused(&program_id, &next_admin.pubkey(), false);
+    send_signed(&mut env, resume, &next_admin).await.unwrap();
+    let config = env
+        .ctx
+        .banks_client
+        .get_account(instruction::config_address(&program_id))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        ConfigPDA::unpack_from_slice(&config.data).unwrap(),
+        ConfigPDA {
+            admin: next_admin.pubkey(),
+            paused: false,
+            allow_list: false,
+            bump: Pubkey::find_program_address(&[ConfigPDA::SEED], &program_id).1,
+            pending_admin: Pubkey::default(),
+        }
+    );
+
+    let escrow = create_offer(&mut env).await;
+    accept_offer(&mut env, escrow).await;
+}
+
+#[tokio::test]
+async fn test_mint_allow_list() {
+    let mut env = init_env().await;
+    let program_id = env.program_key.pubkey();
+    let admin = keypair::Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let (mint_1, mint_2) = (env.mint_1.pubkey(), env.mint_2.pubkey());
+    let not_admin = keypair::Keypair::new();
+    let allow = instruction::allow_mint(&program_id, &not_admin.pubkey(), &mint_1);
+    send_signed(&mut env, allow, &not_admin)
+        .await
+        .expect_err("allow by non-admin did not fail");
+    let allow = instruction::allow_mint(&program_id, &admin.pubkey(), &mint_1);
+    send_signed(&mut env, allow, &admin).await.unwrap();
+    let enable = instruction::set_allow_list(&program_id, &admin.pubkey(), true);
+    send_signed(&mut env, enable, &admin).await.unwrap();
+
+    // the strike mint is not listed yet
+    send_allowed_offer(&mut env, 1, true)
+        .await
+        .expect_err("offer of unlisted mint did not fail");
+    let allow = instruction::allow_mint(&program_id, &admin.pubkey(), &mint_2);
+    send_signed(&mut env, allow, &admin).await.unwrap();
+    send_allowed_offer(&mut env, 2, false)
+        .await
+        .expect_err("offer without allowed mint accounts did not fail");
+    send_allowed_offer(&mut env, 3, true).await.unwrap();
+    // the list covers every escrow type
+    let (_, result) = send_create_vesting(&mut env, 0, 0, 100).await;
+    result.expect_err("vesting offer without allowed mint account did not fail");
+
+    let disallow = instruction::disallow_mint(&program_id, &admin.pubkey(), &mint_2);
+    send_signed(&mut env, disallow, &admin).await.unwrap();
+    assert_closed(
+        &mut env,
+        instruction::allowed_mint_address(&program_id, &mint_2),
+    )
+    .await;
+    send_allowed_offer(&mut env, 4, true)
+        .await
+        .expect_err("offer of removed mint did not fail");
+    let disable = instruction::set_allow_list(&program_id, &admin.pubkey(), false);
+    send_signed(&mut env, disable, &admin).await.unwrap();
+    send_allowed_offer(&mut env, 5, false).await.unwrap();
+    let (_, result) = send_create_vesting(&mut env, 0, 0, 100).await;
+    result.unwrap();
+}
+
+// arbiter escrows are funded from seller_temp, so the env seller acts as the arbiter buyer
+// and the env buyer as the arbiter seller being paid
+#[tokio::test]
+async fn test_arbiter_release() {
+    let mut env = init_env().await;
+    let arbiter_key = keypair::Keypair::new();
+    let arbiter = create_arbiter(&mut env, arbiter_key.pubkey()).await;
+    resolve_arbiter(&mut env, &arbiter, &arbiter_key, 10_000)
+        .await
+        .expect_err("resolve of undisputed escrow did not fail");
+
+    let payee_receive = env.buyer.mint_1;
+    let payee_init_balance = get_token_balance(&mut env, payee_receive).await;
+    release_arbiter(&mut env, &arbiter).await.unwrap();
+    let payee_balance = get_token_balance(&mut env, payee_receive).await;
+    assert_eq!(
+        payee_balance - payee_init_balance,
+        arbiter.arbiter_data.token_qty,
+        "incorrect token qty released to seller"
+    );
+    let pda = arbiter_pda(&env, &arbiter);
+    assert_closed(&mut env, pda).await;
+}
+
+// the pause stops payouts from the other escrow types too, refunds stay open
+#[tokio::test]
+async fn test_pause_escrow_types() {
+    let mut env = init_env().await;
+    let program_id = env.program_key.pubkey();
+    let admin = keypair::Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let arbiter = create_arbiter(&mut env, Pubkey::new_unique()).await;
+    let pause = instruction::set_paused(&program_id, &admin.pubkey(), true);
+    send_signed(&mut env, pause, &admin).await.unwrap();
+
+    release_arbiter(&mut env, &arbiter)
+        .await
+        .expect_err("arbiter release while paused did not fail");
+    let (_, result) = send_create_vesting(&mut env, 0, 0, 100).await;
+    result.expect_err("vesting offer while paused did not fail");
+    refund_arbiter(&mut env, &arbiter).await.unwrap();
+    let pda = arbiter_pda(&env, &arbiter);
+    assert_closed(&mut env, pda).await;
+
+    let resume = instruction::set_paused(&program_id, &admin.pubkey(), false);
+    send_signed(&mut env, resume, &admin).await.unwrap();
+    let (_, result) = send_create_vesting(&mut env, 0, 0, 100).await;
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn test_arbiter_refund() {
+    let mut env = init_env().await;
+    let arbiter = create_arbiter(&mut env, Pubkey::new_unique()).await;
+    refund_arbiter(&mut env, &arbiter).await.unwrap();
+    let pda = arbiter_pda(&env, &arbiter);
+    assert_closed(&mut env, pda).await;
+
+    let temp = env
+        .ctx
+        .banks_client
+        .get_account(env.seller_temp.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Account::unpack_from_slice(&temp.data[..]).unwrap().owner,
+        env.seller.main.pubkey(),
+        "buyer_temp not returned to buyer"
+    );
+}
+
+#[tokio::test]
+async fn test_arbiter_resolve() {
+    let mut env = init_env().await;
+    let arbiter_key = keypair::Keypair::new();
+    let arbiter = create_arbiter(&mut env, arbiter_key.pubkey()).await;
+    dispute_arbiter(&mut env, &arbiter, true).await.unwrap();
+    dispute_arbiter(&mut env, &arbiter, false)
+        .await
+        .expect_err("repeated dispute did not fail");
+    resolve_arbiter(&mut env, &arbiter, &keypair::Keypair::new(), 3_000)
+        .await
+        .expect_err("resolve by non-arbiter did not fail");
+
+    let payee_receive = env.buyer.mint_1;
+    let depositor_receive = env.seller.mint_1;
+    let payee_init_balance = get_token_balance(&mut env, payee_receive).await;
+    let depositor_init_balance = get_token_balance(&mut env, depositor_receive).await;
+    resolve_arbiter(&mut env, &arbiter, &arbiter_key, 3_000)
+        .await
+        .unwrap();
+    let payee_balance = get_token_balance(&mut env, payee_receive).await;
+    let depositor_balance = get_token_balance(&mut env, depositor_receive).await;
+
+    assert_eq!(
+        payee_balance - payee_init_balance,
+        1,
+        "incorrect seller share"
+    );
+    assert_eq!(
+        depositor_balance - depositor_init_balance,
+        4,
+        "incorrect buyer share"
+    );
+    let pda = arbiter_pda(&env, &arbiter);
+    assert_closed(&mut env, pda).await;
+}
+
+#[tokio::test]
+async fn test_milestone_release() {
+    let mut env = init_env().await;
+    let milestones = create_milestone(
+        &mut env,
+        vec![
+            Milestone {
+                amount: 2,
+                deadline: None,
+            },
+            Milestone {
+                amount: 3,
+                deadline: None,
+            },
+        ],
+    )
+    .await;
+
+    let payee_receive = env.buyer.mint_1;
+    let payee_init_balance = get_token_balance(&mut env, payee_receive).await;
+    approve_milestone(&mut env, &milestones, 1).await.unwrap();
+    assert_eq!(
+        get_token_balance(&mut env, payee_receive).await - payee_init_balance,
+        3,
+        "incorrect milestone amount released"
+    );
+    env.ctx.get_new_latest_blockhash().await.unwrap();
+    approve_milestone(&mut env, &milestones, 1)
+        .await
+        .expect_err("repeated milestone approval did not fail");
+    refund_milestone(&mut env, &milestones)
+        .await
+        .expect_err("refund without deadline did not fail");
+
+    approve_milestone(&mut env, &milestones, 0).await.unwrap();
+    assert_eq!(
+        get_token_balance(&mut env, payee_receive).await - payee_init_balance,
+        5,
+        "incorrect total released"
+    );
+    let pda = milestone_pda(&env, &milestones);
+    assert_closed(&mut env, pda).await;
+}
+
+#[tokio::test]
+async fn test_milestone_refund() {
+    let mut env = init_env().await;
+    let clock: Clock = env.ctx.banks_client.get_sysvar().await.unwrap();
+    let milestones = create_milestone(
+        &mut env,
+        vec![
+            Milestone {
+                amount: 2,
+                deadline: Some(clock.unix_timestamp + 50),
+            },
+            Milestone {
+                amount: 3,
+                deadline: Some(clock.unix_timestamp + 100),
+            },
+        ],
+    )
+    .await;
+    approve_milestone(&mut env, &milestones, 0).await.unwrap();
+
+    set_unix_timestamp(&mut env, clock.unix_timestamp + 50).await;
+    refund_milestone(&mut env, &milestones)
+        .await
+        .expect_err("refund before final deadline did not fail");
+
+    set_unix_timestamp(&mut env, clock.unix_timestamp + 100).await;
+    refund_milestone(&mut env, &milestones).await.unwrap();
+    let pda = milestone_pda(&env, &milestones);
+    assert_closed(&mut env, pda).await;
+
+    let temp = env
+        .ctx
+        .banks_client
+        .get_account(env.seller_temp.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    let temp = Account::unpack_from_slice(&temp.data[..]).unwrap();
+    assert_eq!(
+        temp.owner,
+        env.seller.main.pubkey(),
+        "payer_temp not returned to payer"
+    );
+    assert_eq!(temp.amount, 3, "incorrect amount refunded");
+}
+
+#[tokio::test]
+async fn test_vesting_withdraw() {
+    let mut env = init_env().await;
+    let clock: Clock = env.ctx.banks_client.get_sysvar().await.unwrap();
+    let start = clock.unix_timestamp;
+    for (start, cliff, end) in [(start, start, start), (i64::MIN, i64::MIN, i64::MAX)] {
+        let (_, result) = send_create_vesting(&mut env, start, cliff, end).await;
+        result.expect_err("empty or overflowing vesting schedule did not fail");
+    }
+    let vesting = create_vesting(&mut env, start, start + 10, start + 100).await;
+    let beneficiary_receive = env.buyer.mint_1;
+    let init_balance = get_token_balance(&mut env, beneficiary_receive).await;
+
+    set_unix_timestamp(&mut env, start + 5).await;
+    withdraw_vesting(&mut env, &vesting)
+        .await
+        .expect_err("withdraw before cliff did not fail");
+
+    set_unix_timestamp(&mut env, start + 40).await;
+    withdraw_vesting(&mut env, &vesting).await.unwrap();
+    assert_eq!(
+        get_token_balance(&mut env, beneficiary_receive).await - init_balance,
+        2,
+        "incorrect vested amount withdrawn"
+    );
+    env.ctx.get_new_latest_blockhash().await.unwrap();
+    withdraw_vesting(&mut env, &vesting)
+        .await
+        .expect_err("withdraw with nothing newly vested did not fail");
+
+    set_unix_timestamp(&mut env, start + 100).await;
+    withdraw_vesting(&mut env, &vesting).await.unwrap();
+    assert_eq!(
+        get_token_balance(&mut env, beneficiary_receive).await - init_balance,
+        vesting.vesting_data.token_qty,
+        "incorrect total withdrawn"
+    );
+    let pda = vesting_pda(&env, &vesting);
+    assert_closed(&mut env, pda).await;
+}
+
+#[test]
+fn test_vested_amount() {
+    let vesting = |start, cliff, end| VestingData {
+        token_type: Pubkey::new_unique(),
+        token_qty: u64::MAX,
+        beneficiary: Pubkey::new_unique(),
+        start,
+        cliff,
+        end,
+    };
+    let linear = vesting(-100, -100, 100);
+    assert_eq!(linear.vested_amount(-101), Ok(0));
+    assert_eq!(linear.vested_amount(0), Ok(u64::MAX / 2));
+    assert_eq!(linear.vested_amount(100), Ok(u64::MAX));
+    // a span wider than i64 is an error rather than a wrapped duration
+    assert_eq!(
+        vesting(i64::MIN, i64::MIN, i64::MAX).vested_amount(0),
+        Err(ProgramError::ArithmeticOverflow)
+    );
+}
+
+#[tokio::test]
+async fn test_multisig_offer_cancel() {
+    let mut env = init_env().await;
+    let signers = [
+        keypair::Keypair::new(),
+        keypair::Keypair::new(),
+        keypair::Keypair::new(),
+    ];
+    let multisig = create_multisig(&mut env, &signers, 2).await;
+    let program_id = env.program_key.pubkey();
+    let (authority, _bump) = MultisigPDA::authority(&multisig, &program_id);
+
+    // a signer count one more than the fixed format holds
+    let multisig_data = MultisigData {
+        m: 1,
+        signers: (0..MultisigData::MAX_SIGNERS)
+            .map(|_| Pubkey::new_unique())
+            .collect(),
+    };
+    let mut data = InstructionType::MULTISIG_CREATE {
+        multisig_data,
+        index_seed: get_seed(&env.index_seed),
+    }
+    .try_to_vec()
+    .unwrap();
+    data[2] = MultisigData::MAX_SIGNERS as u8 + 1;
+    let ix = Instruction::new_with_bytes(
+        program_id,
+        &data,
+        vec![
+            AccountMeta::new(env.ctx.payer.pubkey(), true),
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&env.ctx.payer.pubkey()),
+        &[&env.ctx.payer],
+        env.ctx.last_blockhash,
+    );
+    env.ctx
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .expect_err("multisig with more than MAX_SIGNERS signers did not fail");
+
+    println!("funding multisig authority and temp account");
+    let authority_temp = keypair::Keypair::new();
+    let token_rent = Rent::default().minimum_balance(165);
+    let ixs = [
+        system_instruction::transfer(&env.ctx.payer.pubkey(), &authority, 100000000),
+        system_instruction::create_account(
+            &env.ctx.payer.pubkey(),
+            &authority_temp.pubkey(),
+            token_rent,
+            165,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &authority_temp.pubkey(),
+            &env.mint_1.pubkey(),
+            &authority,
+        )
+        .unwrap(),
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &env.mint_1.pubkey(),
+            &authority_temp.pubkey(),
+            &env.ctx.payer.pubkey(),
+            &[&env.ctx.payer.pubkey()],
+            5,
+        )
+        .unwrap(),
+    ];
+    let tx = Transaction::new_signed_with_payer(
+        &ixs,
+        Some(&env.ctx.payer.pubkey()),
+        &[&env.ctx.payer, &authority_temp],
+        env.ctx.last_blockhash,
+    );
+    env.ctx.banks_client.process_transaction(tx).await.unwrap();
+
+    let offer_data = OfferData {
+        token_type: env.mint_1.pubkey(),
+        token_qty: 5,
+        strike_type: env.mint_2.pubkey(),
+        strike_qty: 5,
+    };
+    let (pda, _bump) = Pubkey::find_program_address(
+        &[&offer_data.get_seed(), &get_seed(&env.index_seed)],
+        &program_id,
+    );
+    let mut offer_ix_data = vec![0];
+    offer_ix_data.extend_from_slice(&offer_data.to_bytes());
+    offer_ix_data.extend_from_slice(&env.index_seed);
+    let offer_ix = Instruction::new_with_bytes(
+        program_id,
+        &offer_ix_data,
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(authority_temp.pubkey(), false),
+            AccountMeta::new(env.seller.mint_2, false),
+            AccountMeta::new(pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new_readonly(instruction::config_address(&program_id), false),
+        ],
+    );
+
+    approve_multisig(&mut env, multisig, &keypair::Keypair::new(), &offer_ix)
+        .await
+        .expect_err("approval by non-member did not fail");
+    approve_multisig(&mut env, multisig, &signers[0], &offer_ix)
+        .await
+        .unwrap();
+    execute_multisig(&mut env, multisig, &offer_ix)
+        .await
+        .expect_err("execution below threshold did not fail");
+    approve_multisig(&mut env, multisig, &signers[2], &offer_ix)
+        .await
+        .unwrap();
+    env.ctx.get_new_latest_blockhash().await.unwrap();
+    execute_multisig(&mut env, multisig, &offer_ix)
+        .await
+        .unwrap();
+
+    let pda_acc = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .expect("could not find escrow_pda account");
+    assert_eq!(
+        EscrowPDA::unpack_from_slice(&pda_acc.data[..])
+            .unwrap()
+            .seller_main,
+        authority,
+        "escrow not owned by multisig authority"
+    );
+
+    let cancel_ix = Instruction::new_with_bytes(
+        program_id,
+        &[2],
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(authority_temp.pubkey(), false),
+            AccountMeta::new(env.seller.mint_2, false),
+            AccountMeta::new(pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+    approve_multisig(&mut env, multisig, &signers[1], &cancel_ix)
+        .await
+        .unwrap();
+    approve_multisig(&mut env, multisig, &signers[2], &cancel_ix)
+        .await
+        .unwrap();
+    execute_multisig(&mut env, multisig, &cancel_ix)
+        .await
+        .unwrap();
+    assert_closed(&mut env, pda).await;
+
+    let temp = env
+        .ctx
+        .banks_client
+        .get_account(authority_temp.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Account::unpack_from_slice(&temp.data[..]).unwrap().owner,
+        authority,
+        "temp not returned to multisig authority"
+    );
+}
+
+#[tokio::test]
+async fn test_multisig_pending_action() {
+    let mut env = init_env().await;
+    let signers = [
+        keypair::Keypair::new(),
+        keypair::Keypair::new(),
+        keypair::Keypair::new(),
+    ];
+    let multisig = create_multisig(&mut env, &signers, 2).await;
+    let program_id = env.program_key.pubkey();
+    let first = Instruction::new_with_bytes(program_id, &[2], vec![]);
+    let second = Instruction::new_with_bytes(program_id, &[5], vec![]);
+
+    approve_multisig(&mut env, multisig, &signers[0], &first)
+        .await
+        .unwrap();
+    approve_multisig(&mut env, multisig, &signers[1], &second)
+        .await
+        .expect_err("approving another action reset a pending approval");
+    let multisig_data = get_multisig(&mut env, multisig).await;
+    assert_eq!(multisig_data.pending, MultisigPDA::action_hash(&first));
+    assert_eq!(multisig_data.approvals, 0b001);
+
+    // the only approver may still change their mind
+    approve_multisig(&mut env, multisig, &signers[0], &second)
+        .await
+        .unwrap();
+    env.ctx.get_new_latest_blockhash().await.unwrap();
+    approve_multisig(&mut env, multisig, &signers[1], &second)
+        .await
+        .unwrap();
+    let multisig_data = get_multisig(&mut env, multisig).await;
+    assert_eq!(multisig_data.pending, MultisigPDA::action_hash(&second));
+    assert_eq!(multisig_data.approvals, 0b011);
+}
+
+async fn get_multisig(test_env: &mut TestEnv, multisig: Pubkey) -> MultisigPDA {
+    let account = test_env
+        .ctx
+        .banks_client
+        .get_account(multisig)
+        .await
+        .unwrap()
+        .unwrap();
+    MultisigPDA::unpack_from_slice(&account.data).unwrap()
+}
+
+// processor! programs have no ProgramData account, so add one naming the upgrade authority
+fn add_upgrade_authority(
+    program_test: &mut ProgramTest,
+    program_id: &Pubkey,
+    authority: Option<Pubkey>,
+) {
+    let state = UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: authority,
+    };
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    let program_data = SolanaAccount::new_data(
+        Rent::default().minimum_balance(metadata_len),
+        &state,
+        &bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    program_test.add_account(instruction::program_data_address(program_id), program_data);
+}
+
+async fn init_env() -> TestEnv {
+    let mut env = init_env_without_config().await;
+    let admin = keypair::Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let init = instruction::config_init(&env.program_key.pubkey(), &admin.pubkey());
+    send_signed(&mut env, init, &admin).await.unwrap();
+    env
+}
+
+async fn init_env_without_config() -> TestEnv {
+    let program_key = keypair::Keypair::new();
+    let buyer = keypair::Keypair::new();
+    let seller = keypair::Keypair::new();
+    let seller_temp = keypair::Keypair::new();
+    let mint_1 = keypair::Keypair::new();
+    let mint_2 = keypair::Keypair::new();
+    let admin = keypair::Keypair::new();
+    let mut program_test = ProgramTest::new(
+        "sol_escrow",
+        program_key.pubkey(),
+        processor!(process_instruction),
+    );
+    add_upgrade_authority(
+        &mut program_test,
+        &program_key.pubkey(),
+        Some(admin.pubkey()),
+    );
+    let mut ctx = program_test.start_with_context().await;
+
+    let min_rent = Rent::default().minimum_balance(MINT_SIZE as usize);
+
+    let c1 = system_instruction::create_account(
+        &ctx.payer.pubkey(),
+        &mint_1.pubkey(),
+        min_rent,
+        MINT_SIZE,
+        &spl_token::id(),
+    );
+    let c2 = system_instruction::create_account(
+        &ctx.payer.pubkey(),
+        &mint_2.pubkey(),
+        min_rent,
+        MINT_SIZE,
+        &spl_token::id(),
+    );
+
+    let i1 = spl_token::instruction::initialize_mint(
+        &spl_token::id(),
+        &mint_1.pubkey(),
+        &ctx.payer.pubkey(),
+        None,
+        1,
+    )
+    .expect("could not create initialise_mint instruction");
+
+    let i2 = spl_token::instruction::initialize_mint(
+        &spl_token::id(),
+        &mint_2.pubkey(),
+        &ctx.payer.pubkey(),
+        None,
+        1,
+    )
+    .expect("could not create initialise_mint instruction");
+
+    let tx = Transaction::new_signed_with_payer(
+        &[c1],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer, &mint_1],
+        ctx.last_blockhash,
+    );
+    ctx.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[c2],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer, &mint_2],
+        ctx.last_blockhash,
+    );
+    ctx.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[i1],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        ctx.last_blockhash,
+    );
+    ctx.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[i2],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        ctx.last_blockhash,
+    );
+    ctx.banks_client.process_transaction(tx).await.unwrap();
+
+    let split_txs = system_instruction::transfer_many(
+        &ctx.payer.pubkey(),
+        &[
+            (buyer.pubkey(), 100000000),
+            (seller.pubkey(), 100000000),
+            (admin.pubkey(), 100000000),
+        ],
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &split_txs[0..3],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        ctx.last_blockhash,
+    );
+    ctx.banks_client.process_transaction(tx).await.unwrap();
+
+    let b1 = create_associated_token_account(
+        &ctx.payer.pubkey(),
+        &buyer.pubkey(),
+        &mint_1.pubkey(),
+        &spl_token::id(),
+    );
+    let b2 = create_associated_token_account(
+        &ctx.payer.pubkey(),
+        &buyer.pubkey(),
+        &mint_2.pubkey(),
+        &spl_token::id(),
+    );
+    let b3 = create_associated_token_account(
+        &ctx.payer.pubkey(),
+        &seller.pubkey(),
+        &mint_1.pubkey(),
+        &spl_token::id(),
+    );
+    let b4 = create_associated_token_account(
+        &ctx.payer.pubkey(),
+        &seller.pubkey(),
+        &mint_2.pubkey(),
+        &spl_token::id(),
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[b1, b2, b3, b4],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        ctx.last_blockhash,
+    );
+    ctx.banks_client.process_transaction(tx).await.unwrap();
+
+    let b1 = get_associated_token_address(&buyer.pubkey(), &mint_1.pubkey());
+    let b2 = get_associated_token_address(&buyer.pubkey(), &mint_2.pubkey());
+    let s1 = get_associated_token_address(&seller.pubkey(), &mint_1.pubkey());
+    let s2 = get_associated_token_address(&seller.pubkey(), &mint_2.pubkey());
+
+    let min_rent = Rent::default().minimum_balance(165);
+
+    let st = system_instruction::create_account(
+        &seller.pubkey(),
+        &seller_temp.pubkey(),
+        min_rent,
+        165,
+        &spl_token::id(),
+    );
+
+    let sti = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &seller_temp.pubkey(),
+        &mint_1.pubkey(),
+        &seller.pubkey(),
+    )
+    .unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[st, sti],
+        Some(&ctx.payer.pubkey()),
+        &[&seller_temp, &seller, &ctx.payer],
+        ctx.last_blockhash,
+    );
+    ctx.banks_client.process_transaction(tx).await.unwrap();
+
+    let asset_mint = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        &mint_1.pubkey(),
+        &seller_temp.pubkey(),
+        &ctx.payer.pubkey(),
+        &[&ctx.payer.pubkey()],
+        5,
+    )
+    .unwrap();
+
+    let strike_mint = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        &mint_2.pubkey(),
+        &b2,
+        &ctx.payer.pubkey(),
+        &[&ctx.payer.pubkey()],
+        5,
+    )
+    .unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[asset_mint, strike_mint],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        ctx.last_blockhash,
+    );
+    ctx.banks_client.process_transaction(tx).await.unwrap();
+
+    let buyer = PartyKeys {
+        main: buyer,
+        mint_1: b1,
+        mint_2: b2,
+    };
+
+    let seller = PartyKeys {
+        main: seller,
+        mint_1: s1,
+        mint_2: s2,
+    };
+
+    let mut index_seed = [0; 41];
+    index_seed[..32].copy_from_slice(&seller.main.pubkey().to_bytes());
+
+    TestEnv {
         buyer,
         seller,
         mint_1,
         mint_2,
         seller_temp,
         program_key,
+        admin,
         ctx,
         index_seed,
     }
 }
 
-async fn create_offer(test_env: &mut TestEnv) -> EscrowPDA {
-    let offer_data = OfferData {
-        token_type: test_env.mint_1.pubkey(),
-        token_qty: 5,
-        strike_type: test_env.mint_2.pubkey(),
-        strike_qty: 5,
+async fn create_offer(test_env: &mut TestEnv) -> EscrowPDA {
+    create_offer_with(test_env, None, None).await
+}
+
+async fn create_offer_with(
+    test_env: &mut TestEnv,
+    operator: Option<Pubkey>,
+    rent_payer: Option<&keypair::Keypair>,
+) -> EscrowPDA {
+    let offer_data = OfferData {
+        token_type: test_env.mint_1.pubkey(),
+        token_qty: 5,
+        strike_type: test_env.mint_2.pubkey(),
+        strike_qty: 5,
+    };
+
+    let (pda, bump) = Pubkey::find_program_address(
+        &[&offer_data.get_seed(), &get_seed(&test_env.index_seed)],
+        &test_env.program_key.pubkey(),
+    );
+
+    let mut accounts = vec![
+        AccountMeta {
+            pubkey: test_env.seller.main.pubkey(),
+            is_signer: true,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: test_env.seller_temp.pubkey(),
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: test_env.seller.mint_2,
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: pda,
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: system_program::id(),
+            is_signer: false,
+            is_writable: false,
+        },
+        AccountMeta {
+            pubkey: spl_token::id(),
+            is_signer: false,
+            is_writable: false,
+        },
+    ];
+    let program_id = test_env.program_key.pubkey();
+    accounts.push(AccountMeta::new_readonly(
+        operator.unwrap_or(program_id),
+        false,
+    ));
+    let mut signers = vec![&test_env.ctx.payer, &test_env.seller.main];
+    match rent_payer {
+        Some(rent_payer) => {
+            accounts.push(AccountMeta::new(rent_payer.pubkey(), true));
+            signers.push(rent_payer);
+        }
+        None => accounts.push(AccountMeta::new_readonly(program_id, false)),
+    }
+    accounts.push(AccountMeta::new_readonly(
+        instruction::config_address(&program_id),
+        false,
+    ));
+
+    let mut instruction_data = [0; 122];
+    instruction_data[0] = 0;
+    instruction_data[1..81].copy_from_slice(&offer_data.to_bytes());
+    instruction_data[81..].copy_from_slice(&test_env.index_seed);
+
+    println!("sending create_offer transaction");
+    let ix =
+        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &signers,
+        test_env.ctx.last_blockhash,
+    );
+    test_env
+        .ctx
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+
+    let expected_data = EscrowPDA {
+        offer_data,
+        seller_main: test_env.seller.main.pubkey(),
+        seller_temp: test_env.seller_temp.pubkey(),
+        seller_receive: test_env.seller.mint_2,
+        index_seed: get_seed(&test_env.index_seed),
+        operator,
+        rent_payer: rent_payer.map_or(test_env.seller.main.pubkey(), |payer| payer.pubkey()),
+        bump,
+    };
+
+    let pda_acc = test_env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .expect("could not find escrow_pda account");
+    let pda_data = EscrowPDA::unpack_from_slice(&pda_acc.data[..]).unwrap();
+
+    println!("asserting escrow PDA state");
+    assert_eq!(expected_data, pda_data, "incorrect escrow PDA data");
+
+    println!("trying illegal transaction");
+    let ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &test_env.seller_temp.pubkey(),
+        &test_env.buyer.mint_1,
+        &test_env.seller.main.pubkey(),
+        &[&test_env.seller.main.pubkey()],
+        5,
+    )
+    .unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env
+        .ctx
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .expect_err("illegal transaction did not fail");
+
+    expected_data
+}
+
+// sends ix signed by the payer and signer on a fresh blockhash, so repeats are not deduplicated
+async fn send_signed(
+    test_env: &mut TestEnv,
+    ix: Instruction,
+    signer: &keypair::Keypair,
+) -> Result<(), BanksClientError> {
+    test_env.ctx.get_new_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, signer],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+// offers 5 mint_1 for 5 mint_2 from a new seller_temp, optionally with the allowed mint accounts
+async fn send_allowed_offer(
+    test_env: &mut TestEnv,
+    contract_no: u64,
+    with_allowed_mints: bool,
+) -> Result<(), BanksClientError> {
+    let program_id = test_env.program_key.pubkey();
+    let seller_temp = new_seller_temp(test_env, 5).await;
+    let offer_data = OfferData {
+        token_type: test_env.mint_1.pubkey(),
+        token_qty: 5,
+        strike_type: test_env.mint_2.pubkey(),
+        strike_qty: 5,
+    };
+    let index_seed = instruction::index_seed(&test_env.seller.main.pubkey(), 0, contract_no);
+    let mut ix = instruction::offer(
+        &program_id,
+        &test_env.seller.main.pubkey(),
+        &seller_temp.pubkey(),
+        &test_env.seller.mint_2,
+        &offer_data,
+        &index_seed,
+    );
+    if with_allowed_mints {
+        ix = instruction::with_allowed_mints(&program_id, ix, &offer_data);
+    }
+    let seller = keypair::Keypair::from_bytes(&test_env.seller.main.to_bytes()).unwrap();
+    send_signed(test_env, ix, &seller).await
+}
+
+async fn get_token_balance(test_env: &mut TestEnv, pk: Pubkey) -> u64 {
+    let acc = test_env
+        .ctx
+        .banks_client
+        .get_account(pk)
+        .await
+        .unwrap()
+        .unwrap();
+    Account::unpack_from_slice(&acc.data[..]).unwrap().amount
+}
+
+async fn accept_offer(test_env: &mut TestEnv, escrow_pda: EscrowPDA) {
+    let seed = escrow_pda.offer_data.get_seed();
+    let (pda, _bump) = Pubkey::find_program_address(
+        &[&seed, &get_seed(&test_env.index_seed)],
+        &test_env.program_key.pubkey(),
+    );
+
+    let buyer_init_balance = get_token_balance(test_env, test_env.buyer.mint_1).await;
+    let seller_init_balance = get_token_balance(test_env, test_env.seller.mint_2).await;
+
+    send_accept(test_env, pda, Some(escrow_pda.offer_data))
+        .await
+        .unwrap();
+
+    test_env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .ok_or("")
+        .expect_err("PDA account not closed");
+    println!("PDA account closed");
+
+    let buyer_balance = get_token_balance(test_env, test_env.buyer.mint_1).await;
+    let seller_balance = get_token_balance(test_env, test_env.seller.mint_2).await;
+
+    println!("asserting transfer states");
+
+    assert_eq!(
+        buyer_balance - buyer_init_balance,
+        escrow_pda.offer_data.token_qty,
+        "incorrect token qty transferred to buyer"
+    );
+    assert_eq!(
+        seller_balance - seller_init_balance,
+        escrow_pda.offer_data.strike_qty,
+        "incorrect strike qty transferred to seller"
+    );
+}
+
+async fn send_accept(
+    test_env: &mut TestEnv,
+    pda: Pubkey,
+    expected: Option<OfferData>,
+) -> Result<(), BanksClientError> {
+    let accounts = vec![
+        AccountMeta {
+            pubkey: test_env.buyer.main.pubkey(),
+            is_signer: true,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: test_env.buyer.mint_2,
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: test_env.buyer.mint_1,
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: pda,
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: test_env.seller.main.pubkey(),
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: test_env.seller_temp.pubkey(),
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: test_env.seller.mint_2,
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: system_program::id(),
+            is_signer: false,
+            is_writable: false,
+        },
+        AccountMeta {
+            pubkey: spl_token::id(),
+            is_signer: false,
+            is_writable: false,
+        },
+        AccountMeta {
+            pubkey: test_env.program_key.pubkey(),
+            is_signer: false,
+            is_writable: false,
+        },
+        AccountMeta {
+            pubkey: instruction::config_address(&test_env.program_key.pubkey()),
+            is_signer: false,
+            is_writable: false,
+        },
+    ];
+
+    let instruction_data = match expected {
+        Some(_) => InstructionType::ACCEPT { expected }.try_to_vec().unwrap(),
+        // the baseline format, the tag and the raw index seed, which is ignored
+        None => [&[1], &test_env.index_seed[..]].concat(),
+    };
+
+    println!("sending accept_offer transaction");
+    let ix =
+        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.buyer.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+async fn cancel_offer(test_env: &mut TestEnv, escrow_pda: EscrowPDA, test_close: bool) {
+    let seed = escrow_pda.offer_data.get_seed();
+    let (pda, _bump) = Pubkey::find_program_address(
+        &[&seed, &get_seed(&test_env.index_seed)],
+        &test_env.program_key.pubkey(),
+    );
+
+    let accounts = vec![
+        AccountMeta {
+            pubkey: test_env.seller.main.pubkey(),
+            is_signer: true,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: test_env.seller_temp.pubkey(),
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: test_env.seller.mint_2,
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: pda,
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: system_program::id(),
+            is_signer: false,
+            is_writable: false,
+        },
+        AccountMeta {
+            pubkey: spl_token::id(),
+            is_signer: false,
+            is_writable: false,
+        },
+    ];
+
+    let mut instruction_data = [0; 42];
+    instruction_data[0] = 2;
+    instruction_data[1..].copy_from_slice(&test_env.index_seed);
+
+    println!("sending cancel_offer transaction");
+    let ix =
+        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env
+        .ctx
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+
+    test_env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .ok_or("")
+        .expect_err("PDA account not closed");
+    println!("PDA account closed");
+
+    if test_close {
+        println!("testing transfer from seller_temp");
+        let ix = spl_token::instruction::transfer(
+            &spl_token::id(),
+            &test_env.seller_temp.pubkey(),
+            &test_env.seller.mint_1,
+            &test_env.seller.main.pubkey(),
+            &[&test_env.seller.main.pubkey()],
+            5,
+        )
+        .unwrap();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&test_env.ctx.payer.pubkey()),
+            &[&test_env.ctx.payer, &test_env.seller.main],
+            test_env.ctx.last_blockhash,
+        );
+        test_env
+            .ctx
+            .banks_client
+            .process_transaction(tx)
+            .await
+            .unwrap();
+        println!("closing seller_temp");
+        let ix = spl_token::instruction::close_account(
+            &spl_token::id(),
+            &test_env.seller_temp.pubkey(),
+            &test_env.seller.main.pubkey(),
+            &test_env.seller.main.pubkey(),
+            &[&test_env.seller.main.pubkey()],
+        )
+        .unwrap();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&test_env.ctx.payer.pubkey()),
+            &[&test_env.ctx.payer, &test_env.seller.main],
+            test_env.ctx.last_blockhash,
+        );
+        test_env
+            .ctx
+            .banks_client
+            .process_transaction(tx)
+            .await
+            .unwrap();
+    }
+}
+
+async fn assert_closed(test_env: &mut TestEnv, pda: Pubkey) {
+    test_env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .ok_or("")
+        .expect_err("PDA account not closed");
+}
+
+async fn set_unix_timestamp(test_env: &mut TestEnv, unix_timestamp: i64) {
+    let mut clock: Clock = test_env.ctx.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp = unix_timestamp;
+    test_env.ctx.set_sysvar(&clock);
+    test_env.ctx.get_new_latest_blockhash().await.unwrap();
+}
+
+fn htlc_pda(test_env: &TestEnv, htlc: &HtlcPDA) -> Pubkey {
+    Pubkey::find_program_address(
+        &[&htlc.htlc_data.get_seed(), &htlc.index_seed],
+        &test_env.program_key.pubkey(),
+    )
+    .0
+}
+
+async fn create_htlc(test_env: &mut TestEnv, hashlock: [u8; 32]) -> HtlcPDA {
+    let clock: Clock = test_env.ctx.banks_client.get_sysvar().await.unwrap();
+    let htlc_data = HtlcData {
+        token_type: test_env.mint_1.pubkey(),
+        token_qty: 5,
+        buyer: test_env.buyer.main.pubkey(),
+        hashlock,
+        expiry: clock.unix_timestamp + 100,
+    };
+    let htlc = HtlcPDA {
+        htlc_data,
+        seller_main: test_env.seller.main.pubkey(),
+        seller_temp: test_env.seller_temp.pubkey(),
+        index_seed: get_seed(&test_env.index_seed),
+    };
+    let pda = htlc_pda(test_env, &htlc);
+
+    let accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(pda, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(
+            instruction::config_address(&test_env.program_key.pubkey()),
+            false,
+        ),
+    ];
+
+    let mut instruction_data = vec![3];
+    instruction_data.extend_from_slice(&htlc.htlc_data.try_to_vec().unwrap());
+    instruction_data.extend_from_slice(&get_seed(&test_env.index_seed));
+
+    println!("sending create_htlc transaction");
+    let ix =
+        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env
+        .ctx
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+
+    let pda_acc = test_env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .expect("could not find HTLC PDA account");
+    println!("asserting HTLC PDA state");
+    assert_eq!(
+        htlc,
+        HtlcPDA::unpack_from_slice(&pda_acc.data[..]).unwrap(),
+        "incorrect HTLC PDA data"
+    );
+    htlc
+}
+
+async fn claim_htlc(
+    test_env: &mut TestEnv,
+    htlc: &HtlcPDA,
+    preimage: [u8; 32],
+) -> Result<(), BanksClientError> {
+    let accounts = vec![
+        AccountMeta::new(test_env.buyer.main.pubkey(), true),
+        AccountMeta::new(test_env.buyer.mint_1, false),
+        AccountMeta::new(htlc_pda(test_env, htlc), false),
+        AccountMeta::new(test_env.seller.main.pubkey(), false),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(
+            instruction::config_address(&test_env.program_key.pubkey()),
+            false,
+        ),
+    ];
+
+    let mut instruction_data = vec![4];
+    instruction_data.extend_from_slice(&preimage);
+
+    println!("sending claim_htlc transaction");
+    let ix =
+        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.buyer.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+async fn refund_htlc(test_env: &mut TestEnv, htlc: &HtlcPDA) -> Result<(), BanksClientError> {
+    let accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(htlc_pda(test_env, htlc), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+
+    println!("sending refund_htlc transaction");
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[5], accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+async fn send_limit_offer(
+    test_env: &mut TestEnv,
+    limit_order: &LimitOrder,
+    strike_mint: Pubkey,
+) -> Result<(), BanksClientError> {
+    // an order that cannot be priced is sent to the address of a zero strike offer
+    let offer_data = limit_order.offer_data(1, 1).unwrap_or(OfferData {
+        token_type: limit_order.token_type,
+        token_qty: limit_order.token_qty,
+        strike_type: limit_order.strike_type,
+        strike_qty: 0,
+    });
+    let pda = instruction::escrow_address(
+        &test_env.program_key.pubkey(),
+        &offer_data,
+        &test_env.index_seed,
+    );
+
+    println!("sending limit offer transaction");
+    let ix = Instruction::new_with_bytes(
+        test_env.program_key.pubkey(),
+        &InstructionType::LIMIT_OFFER {
+            limit_order: *limit_order,
+            index_seed: get_seed(&test_env.index_seed),
+        }
+        .try_to_vec()
+        .unwrap(),
+        vec![
+            AccountMeta::new(test_env.seller.main.pubkey(), true),
+            AccountMeta::new(test_env.seller_temp.pubkey(), false),
+            AccountMeta::new_readonly(test_env.seller.mint_2, false),
+            AccountMeta::new(pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(limit_order.token_type, false),
+            AccountMeta::new_readonly(strike_mint, false),
+            AccountMeta::new_readonly(test_env.program_key.pubkey(), false),
+            AccountMeta::new_readonly(test_env.program_key.pubkey(), false),
+            AccountMeta::new_readonly(
+                instruction::config_address(&test_env.program_key.pubkey()),
+                false,
+            ),
+        ],
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+// mock feed in the OraclePrice layout, 4 decimal places
+fn set_oracle(test_env: &mut TestEnv, oracle: Pubkey, price: u64, publish_time: i64) {
+    let data = OraclePrice {
+        price,
+        expo: 4,
+        publish_time,
+    }
+    .try_to_vec()
+    .unwrap();
+    let account = SolanaAccount {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: Pubkey::new_unique(),
+        ..SolanaAccount::default()
+    };
+    test_env.ctx.set_account(&oracle, &account.into());
+}
+
+fn pegged_pda(test_env: &TestEnv, pegged: &PeggedPDA) -> Pubkey {
+    Pubkey::create_program_address(
+        &[
+            &pegged.pegged_data.get_seed(),
+            &pegged.index_seed,
+            &[pegged.bump],
+        ],
+        &test_env.program_key.pubkey(),
+    )
+    .unwrap()
+}
+
+async fn create_pegged(test_env: &mut TestEnv, oracle: Pubkey) -> PeggedPDA {
+    let pegged_data = PeggedData {
+        token_type: test_env.mint_1.pubkey(),
+        token_qty: 5,
+        strike_type: test_env.mint_2.pubkey(),
+        oracle,
+        spread_bps: 100,
+        max_staleness: 60,
+    };
+    let index_seed = get_seed(&test_env.index_seed);
+    let (pda, bump) = Pubkey::find_program_address(
+        &[&pegged_data.get_seed(), &index_seed],
+        &test_env.program_key.pubkey(),
+    );
+
+    println!("sending create_pegged transaction");
+    let ix = Instruction::new_with_bytes(
+        test_env.program_key.pubkey(),
+        &InstructionType::PEGGED_OFFER {
+            pegged_data,
+            index_seed,
+        }
+        .try_to_vec()
+        .unwrap(),
+        vec![
+            AccountMeta::new(test_env.seller.main.pubkey(), true),
+            AccountMeta::new(test_env.seller_temp.pubkey(), false),
+            AccountMeta::new_readonly(test_env.seller.mint_2, false),
+            AccountMeta::new(pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(
+                instruction::config_address(&test_env.program_key.pubkey()),
+                false,
+            ),
+        ],
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env
+        .ctx
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+
+    let pegged = PeggedPDA {
+        seller_main: test_env.seller.main.pubkey(),
+        seller_temp: test_env.seller_temp.pubkey(),
+        seller_receive: test_env.seller.mint_2,
+        pegged_data,
+        index_seed,
+        bump,
+    };
+    let pda_acc = test_env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .expect("could not find pegged PDA account");
+    println!("asserting pegged PDA state");
+    assert_eq!(
+        pegged,
+        PeggedPDA::unpack_from_slice(&pda_acc.data[..]).unwrap(),
+        "incorrect pegged PDA data"
+    );
+    pegged
+}
+
+async fn accept_pegged(
+    test_env: &mut TestEnv,
+    pegged: &PeggedPDA,
+    oracle: Pubkey,
+    max_strike_qty: u64,
+) -> Result<(), BanksClientError> {
+    println!("sending accept_pegged transaction");
+    let ix = Instruction::new_with_bytes(
+        test_env.program_key.pubkey(),
+        &InstructionType::PEGGED_ACCEPT { max_strike_qty }
+            .try_to_vec()
+            .unwrap(),
+        vec![
+            AccountMeta::new(test_env.buyer.main.pubkey(), true),
+            AccountMeta::new(test_env.buyer.mint_2, false),
+            AccountMeta::new(test_env.buyer.mint_1, false),
+            AccountMeta::new(pegged_pda(test_env, pegged), false),
+            AccountMeta::new(test_env.seller.main.pubkey(), false),
+            AccountMeta::new(test_env.seller_temp.pubkey(), false),
+            AccountMeta::new(test_env.seller.mint_2, false),
+            AccountMeta::new_readonly(oracle, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(
+                instruction::config_address(&test_env.program_key.pubkey()),
+                false,
+            ),
+        ],
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.buyer.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+fn arbiter_pda(test_env: &TestEnv, arbiter: &ArbiterPDA) -> Pubkey {
+    Pubkey::find_program_address(
+        &[&arbiter.arbiter_data.get_seed(), &arbiter.index_seed],
+        &test_env.program_key.pubkey(),
+    )
+    .0
+}
+
+async fn create_arbiter(test_env: &mut TestEnv, arbiter_key: Pubkey) -> ArbiterPDA {
+    let arbiter = ArbiterPDA {
+        arbiter_data: ArbiterData {
+            token_type: test_env.mint_1.pubkey(),
+            token_qty: 5,
+            seller: test_env.buyer.main.pubkey(),
+            arbiter: arbiter_key,
+        },
+        buyer_main: test_env.seller.main.pubkey(),
+        buyer_temp: test_env.seller_temp.pubkey(),
+        index_seed: get_seed(&test_env.index_seed),
+        disputed: false,
+    };
+    let pda = arbiter_pda(test_env, &arbiter);
+
+    let accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(pda, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(
+            instruction::config_address(&test_env.program_key.pubkey()),
+            false,
+        ),
+    ];
+
+    let mut instruction_data = vec![6];
+    instruction_data.extend_from_slice(&arbiter.arbiter_data.try_to_vec().unwrap());
+    instruction_data.extend_from_slice(&get_seed(&test_env.index_seed));
+
+    println!("sending create_arbiter transaction");
+    let ix =
+        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env
+        .ctx
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+
+    let pda_acc = test_env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .expect("could not find arbiter PDA account");
+    println!("asserting arbiter PDA state");
+    assert_eq!(
+        arbiter,
+        ArbiterPDA::unpack_from_slice(&pda_acc.data[..]).unwrap(),
+        "incorrect arbiter PDA data"
+    );
+    arbiter
+}
+
+async fn release_arbiter(
+    test_env: &mut TestEnv,
+    arbiter: &ArbiterPDA,
+) -> Result<(), BanksClientError> {
+    let accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(test_env.buyer.mint_1, false),
+        AccountMeta::new(arbiter_pda(test_env, arbiter), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(
+            instruction::config_address(&test_env.program_key.pubkey()),
+            false,
+        ),
+    ];
+
+    println!("sending release_arbiter transaction");
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[7], accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+async fn refund_arbiter(
+    test_env: &mut TestEnv,
+    arbiter: &ArbiterPDA,
+) -> Result<(), BanksClientError> {
+    let accounts = vec![
+        AccountMeta::new(test_env.buyer.main.pubkey(), true),
+        AccountMeta::new(test_env.seller.main.pubkey(), false),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(arbiter_pda(test_env, arbiter), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+
+    println!("sending refund_arbiter transaction");
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[8], accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.buyer.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+async fn dispute_arbiter(
+    test_env: &mut TestEnv,
+    arbiter: &ArbiterPDA,
+    by_seller: bool,
+) -> Result<(), BanksClientError> {
+    let party = if by_seller {
+        &test_env.buyer.main
+    } else {
+        &test_env.seller.main
     };
+    let accounts = vec![
+        AccountMeta::new_readonly(party.pubkey(), true),
+        AccountMeta::new(arbiter_pda(test_env, arbiter), false),
+    ];
 
-    let (pda, _bump) = Pubkey::find_program_address(
-        &[&offer_data.get_seed(), &get_seed(&test_env.index_seed)],
-        &test_env.program_key.pubkey(),
+    println!("sending dispute_arbiter transaction");
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[9], accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, party],
+        test_env.ctx.last_blockhash,
     );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
 
+async fn resolve_arbiter(
+    test_env: &mut TestEnv,
+    arbiter: &ArbiterPDA,
+    arbiter_key: &keypair::Keypair,
+    seller_bps: u16,
+) -> Result<(), BanksClientError> {
     let accounts = vec![
-        AccountMeta {
-            pubkey: test_env.seller.main.pubkey(),
-            is_signer: true,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: test_env.seller_temp.pubkey(),
-            is_signer: false,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: test_env.seller.mint_2.clone(),
-            is_signer: false,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: pda,
-            is_signer: false,
-            is_writable: true,
+        AccountMeta::new_readonly(arbiter_key.pubkey(), true),
+        AccountMeta::new(test_env.seller.main.pubkey(), false),
+        AccountMeta::new(test_env.seller.mint_1, false),
+        AccountMeta::new(test_env.buyer.mint_1, false),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(arbiter_pda(test_env, arbiter), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(
+            instruction::config_address(&test_env.program_key.pubkey()),
+            false,
+        ),
+    ];
+
+    let mut instruction_data = vec![10];
+    instruction_data.extend_from_slice(&seller_bps.to_le_bytes());
+
+    println!("sending resolve_arbiter transaction");
+    let ix =
+        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, arbiter_key],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+fn milestone_pda(test_env: &TestEnv, milestones: &MilestonePDA) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            &milestones.milestone_data.get_seed(),
+            &milestones.index_seed,
+        ],
+        &test_env.program_key.pubkey(),
+    )
+    .0
+}
+
+async fn create_milestone(test_env: &mut TestEnv, milestones: Vec<Milestone>) -> MilestonePDA {
+    let released = vec![false; milestones.len()];
+    let milestones = MilestonePDA {
+        milestone_data: MilestoneData {
+            token_type: test_env.mint_1.pubkey(),
+            payee: test_env.buyer.main.pubkey(),
+            milestones,
         },
-        AccountMeta {
-            pubkey: system_program::id(),
-            is_signer: false,
-            is_writable: false,
+        payer_main: test_env.seller.main.pubkey(),
+        payer_temp: test_env.seller_temp.pubkey(),
+        index_seed: get_seed(&test_env.index_seed),
+        released,
+    };
+    let pda = milestone_pda(test_env, &milestones);
+
+    let accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(pda, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(
+            instruction::config_address(&test_env.program_key.pubkey()),
+            false,
+        ),
+    ];
+
+    let mut instruction_data = vec![11];
+    instruction_data.extend_from_slice(&milestones.milestone_data.try_to_vec().unwrap());
+    instruction_data.extend_from_slice(&get_seed(&test_env.index_seed));
+
+    println!("sending create_milestone transaction");
+    let ix =
+        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env
+        .ctx
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+
+    let pda_acc = test_env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .expect("could not find milestone PDA account");
+    println!("asserting milestone PDA state");
+    assert_eq!(
+        milestones,
+        MilestonePDA::unpack_from_slice(&pda_acc.data[..]).unwrap(),
+        "incorrect milestone PDA data"
+    );
+    milestones
+}
+
+async fn approve_milestone(
+    test_env: &mut TestEnv,
+    milestones: &MilestonePDA,
+    milestone: u8,
+) -> Result<(), BanksClientError> {
+    let accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(test_env.buyer.mint_1, false),
+        AccountMeta::new(milestone_pda(test_env, milestones), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(
+            instruction::config_address(&test_env.program_key.pubkey()),
+            false,
+        ),
+    ];
+
+    println!("sending approve_milestone transaction");
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[12, milestone], accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+async fn refund_milestone(
+    test_env: &mut TestEnv,
+    milestones: &MilestonePDA,
+) -> Result<(), BanksClientError> {
+    let accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(milestone_pda(test_env, milestones), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+
+    println!("sending refund_milestone transaction");
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[13], accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+fn vesting_pda(test_env: &TestEnv, vesting: &VestingPDA) -> Pubkey {
+    Pubkey::find_program_address(
+        &[&vesting.vesting_data.get_seed(), &vesting.index_seed],
+        &test_env.program_key.pubkey(),
+    )
+    .0
+}
+
+async fn create_vesting(test_env: &mut TestEnv, start: i64, cliff: i64, end: i64) -> VestingPDA {
+    let (vesting, result) = send_create_vesting(test_env, start, cliff, end).await;
+    result.unwrap();
+
+    let pda = vesting_pda(test_env, &vesting);
+    let pda_acc = test_env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .expect("could not find vesting PDA account");
+    println!("asserting vesting PDA state");
+    assert_eq!(
+        vesting,
+        VestingPDA::unpack_from_slice(&pda_acc.data[..]).unwrap(),
+        "incorrect vesting PDA data"
+    );
+    vesting
+}
+
+async fn send_create_vesting(
+    test_env: &mut TestEnv,
+    start: i64,
+    cliff: i64,
+    end: i64,
+) -> (VestingPDA, Result<(), BanksClientError>) {
+    let vesting = VestingPDA {
+        vesting_data: VestingData {
+            token_type: test_env.mint_1.pubkey(),
+            token_qty: 5,
+            beneficiary: test_env.buyer.main.pubkey(),
+            start,
+            cliff,
+            end,
         },
-        AccountMeta {
-            pubkey: spl_token::id(),
-            is_signer: false,
-            is_writable: false,
+        grantor_main: test_env.seller.main.pubkey(),
+        grantor_temp: test_env.seller_temp.pubkey(),
+        index_seed: get_seed(&test_env.index_seed),
+        withdrawn: 0,
+    };
+    let pda = vesting_pda(test_env, &vesting);
+
+    let accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(pda, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(
+            instruction::config_address(&test_env.program_key.pubkey()),
+            false,
+        ),
+    ];
+
+    let mut instruction_data = vec![14];
+    instruction_data.extend_from_slice(&vesting.vesting_data.try_to_vec().unwrap());
+    instruction_data.extend_from_slice(&get_seed(&test_env.index_seed));
+
+    println!("sending create_vesting transaction");
+    let ix =
+        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    let result = test_env.ctx.banks_client.process_transaction(tx).await;
+    (vesting, result)
+}
+
+async fn withdraw_vesting(
+    test_env: &mut TestEnv,
+    vesting: &VestingPDA,
+) -> Result<(), BanksClientError> {
+    let accounts = vec![
+        AccountMeta::new(test_env.buyer.main.pubkey(), true),
+        AccountMeta::new(test_env.buyer.mint_1, false),
+        AccountMeta::new(vesting_pda(test_env, vesting), false),
+        AccountMeta::new(test_env.seller.main.pubkey(), false),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(
+            instruction::config_address(&test_env.program_key.pubkey()),
+            false,
+        ),
+    ];
+
+    println!("sending withdraw_vesting transaction");
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[15], accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.buyer.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+async fn create_multisig(test_env: &mut TestEnv, signers: &[keypair::Keypair], m: u8) -> Pubkey {
+    let multisig_data = MultisigData {
+        m,
+        signers: signers.iter().map(|s| s.pubkey()).collect(),
+    };
+    let (pda, _bump) = Pubkey::find_program_address(
+        &[&multisig_data.get_seed(), &get_seed(&test_env.index_seed)],
+        &test_env.program_key.pubkey(),
+    );
+
+    let accounts = vec![
+        AccountMeta::new(test_env.ctx.payer.pubkey(), true),
+        AccountMeta::new(pda, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let mut instruction_data = vec![16];
+    instruction_data.extend_from_slice(&multisig_data.try_to_vec().unwrap());
+    instruction_data.extend_from_slice(&get_seed(&test_env.index_seed));
+
+    println!("sending create_multisig transaction");
+    let ix =
+        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer],
+        test_env.ctx.last_blockhash,
+    );
+    test_env
+        .ctx
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+
+    let pda_acc = test_env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .expect("could not find multisig PDA account");
+    println!("asserting multisig PDA state");
+    assert_eq!(
+        MultisigPDA {
+            multisig_data,
+            index_seed: get_seed(&test_env.index_seed),
+            pending: [0; 32],
+            approvals: 0,
         },
+        MultisigPDA::unpack_from_slice(&pda_acc.data[..]).unwrap(),
+        "incorrect multisig PDA data"
+    );
+    pda
+}
+
+async fn approve_multisig(
+    test_env: &mut TestEnv,
+    multisig: Pubkey,
+    signer: &keypair::Keypair,
+    action: &Instruction,
+) -> Result<(), BanksClientError> {
+    let accounts = vec![
+        AccountMeta::new_readonly(signer.pubkey(), true),
+        AccountMeta::new(multisig, false),
     ];
 
-    let mut instruction_data = [0; 122];
-    instruction_data[0] = 0;
-    instruction_data[1..81].copy_from_slice(&offer_data.to_bytes());
-    instruction_data[81..].copy_from_slice(&test_env.index_seed);
+    let mut instruction_data = vec![17];
+    instruction_data.extend_from_slice(&MultisigPDA::action_hash(action));
 
-    println!("sending create_offer transaction");
+    println!("sending approve_multisig transaction");
     let ix =
         Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
     let tx = Transaction::new_signed_with_payer(
-        &[ix],
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, signer],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+async fn execute_multisig(
+    test_env: &mut TestEnv,
+    multisig: Pubkey,
+    action: &Instruction,
+) -> Result<(), BanksClientError> {
+    let program_id = test_env.program_key.pubkey();
+    let (authority, _bump) = MultisigPDA::authority(&multisig, &program_id);
+    let mut accounts = vec![
+        AccountMeta::new(multisig, false),
+        AccountMeta::new(authority, false),
+        AccountMeta::new_readonly(action.program_id, false),
+    ];
+    accounts.extend(action.accounts.iter().map(|meta| AccountMeta {
+        is_signer: meta.is_signer && meta.pubkey != authority,
+        ..meta.clone()
+    }));
+
+    let instruction_data = InstructionType::MULTISIG_EXECUTE {
+        data: action.data.clone(),
+    }
+    .try_to_vec()
+    .unwrap();
+
+    println!("sending execute_multisig transaction");
+    let ix = Instruction::new_with_bytes(program_id, &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+async fn set_operator(
+    test_env: &mut TestEnv,
+    escrow_pda: &EscrowPDA,
+    operator: Option<Pubkey>,
+) -> Result<(), BanksClientError> {
+    let (pda, _bump) = Pubkey::find_program_address(
+        &[&escrow_pda.offer_data.get_seed(), &escrow_pda.index_seed],
+        &test_env.program_key.pubkey(),
+    );
+    let mut accounts = vec![
+        AccountMeta::new_readonly(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(pda, false),
+    ];
+    if let Some(operator) = operator {
+        accounts.push(AccountMeta::new_readonly(operator, false));
+    }
+
+    println!("sending set_operator transaction");
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[19], accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+async fn cancel_offer_by_operator(
+    test_env: &mut TestEnv,
+    escrow_pda: &EscrowPDA,
+    operator: &keypair::Keypair,
+) -> Result<(), BanksClientError> {
+    let (pda, _bump) = Pubkey::find_program_address(
+        &[&escrow_pda.offer_data.get_seed(), &escrow_pda.index_seed],
+        &test_env.program_key.pubkey(),
+    );
+    let accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), false),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(test_env.seller.mint_2, false),
+        AccountMeta::new(pda, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(operator.pubkey(), true),
+    ];
+
+    println!("sending operator cancel_offer transaction");
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[2], accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, operator],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
+
+async fn new_seller_temp(test_env: &mut TestEnv, amount: u64) -> keypair::Keypair {
+    let temp = keypair::Keypair::new();
+    let create = system_instruction::create_account(
+        &test_env.seller.main.pubkey(),
+        &temp.pubkey(),
+        Rent::default().minimum_balance(165),
+        165,
+        &spl_token::id(),
+    );
+    let init = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &temp.pubkey(),
+        &test_env.mint_1.pubkey(),
+        &test_env.seller.main.pubkey(),
+    )
+    .unwrap();
+    let mint = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        &test_env.mint_1.pubkey(),
+        &temp.pubkey(),
+        &test_env.ctx.payer.pubkey(),
+        &[&test_env.ctx.payer.pubkey()],
+        amount,
+    )
+    .unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[create, init, mint],
         Some(&test_env.ctx.payer.pubkey()),
-        &[&test_env.ctx.payer, &test_env.seller.main],
+        &[&test_env.ctx.payer, &test_env.seller.main, &temp],
         test_env.ctx.last_blockhash,
     );
     test_env
@@ -323,133 +3398,114 @@ async fn create_offer(test_env: &mut TestEnv) -> EscrowPDA {
         .process_transaction(tx)
         .await
         .unwrap();
+    temp
+}
 
-    let expected_data = EscrowPDA {
-        offer_data,
-        seller_main: test_env.seller.main.pubkey(),
-        seller_temp: test_env.seller_temp.pubkey(),
-        seller_receive: test_env.seller.mint_2.clone(),
-        index_seed: get_seed(&test_env.index_seed),
-    };
-
-    let pda_acc = test_env
-        .ctx
-        .banks_client
-        .get_account(pda)
-        .await
-        .unwrap()
-        .expect("could not find escrow_pda account");
-    let pda_data = EscrowPDA::unpack_from_slice(&pda_acc.data[..]).unwrap();
-
-    println!("asserting escrow PDA state");
-    assert_eq!(expected_data, pda_data, "incorrect escrow PDA data");
+async fn cancel_many(
+    test_env: &mut TestEnv,
+    offers: &[(Pubkey, Pubkey)],
+    count: u8,
+) -> Result<(), BanksClientError> {
+    let mut accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    for (pda, seller_temp) in offers {
+        accounts.push(AccountMeta::new(*pda, false));
+        accounts.push(AccountMeta::new(*seller_temp, false));
+    }
 
-    println!("trying illegal transaction");
-    let ix = spl_token::instruction::transfer(
-        &spl_token::id(),
-        &test_env.seller_temp.pubkey(),
-        &test_env.buyer.mint_1,
-        &test_env.seller.main.pubkey(),
-        &[&test_env.seller.main.pubkey()],
-        5,
-    )
-    .unwrap();
+    println!("sending cancel_many transaction");
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[20, count], accounts);
     let tx = Transaction::new_signed_with_payer(
         &[ix],
         Some(&test_env.ctx.payer.pubkey()),
         &[&test_env.ctx.payer, &test_env.seller.main],
         test_env.ctx.last_blockhash,
     );
-    test_env
-        .ctx
-        .banks_client
-        .process_transaction(tx)
-        .await
-        .expect_err("illegal transaction did not fail");
-
-    expected_data
+    test_env.ctx.banks_client.process_transaction(tx).await
 }
 
-async fn get_token_balance(test_env: &mut TestEnv, pk: Pubkey) -> u64 {
-    let acc = test_env
-        .ctx
-        .banks_client
-        .get_account(pk)
-        .await
-        .unwrap()
+async fn create_many(
+    test_env: &mut TestEnv,
+    ladder: &[(OfferData, [u8; 41], Pubkey)],
+) -> Result<(), BanksClientError> {
+    let mut accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(test_env.seller.mint_1, false),
+        AccountMeta::new_readonly(test_env.seller.mint_2, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let mut offers = vec![];
+    for (offer_data, index_seed, temp) in ladder {
+        let (pda, _bump) = Pubkey::find_program_address(
+            &[&offer_data.get_seed(), &get_seed(index_seed)],
+            &test_env.program_key.pubkey(),
+        );
+        accounts.push(AccountMeta::new(*temp, false));
+        accounts.push(AccountMeta::new(pda, false));
+        offers.push((*offer_data, get_seed(index_seed)));
+    }
+    let instruction_data = InstructionType::CREATE_MANY { offers }
+        .try_to_vec()
         .unwrap();
-    Account::unpack_from_slice(&acc.data[..]).unwrap().amount
+    let program_id = test_env.program_key.pubkey();
+    accounts.extend([
+        AccountMeta::new_readonly(program_id, false),
+        AccountMeta::new_readonly(program_id, false),
+        AccountMeta::new_readonly(instruction::config_address(&program_id), false),
+    ]);
+
+    println!("sending create_many transaction");
+    let ix =
+        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.seller.main],
+        test_env.ctx.last_blockhash,
+    );
+    test_env.ctx.banks_client.process_transaction(tx).await
 }
 
-async fn accept_offer(test_env: &mut TestEnv, escrow_pda: EscrowPDA) {
-    let seed = escrow_pda.offer_data.get_seed();
+async fn create_offer_to_wallet(test_env: &mut TestEnv, wallet: Pubkey) -> (Pubkey, Pubkey) {
+    let offer_data = OfferData {
+        token_type: test_env.mint_1.pubkey(),
+        token_qty: 5,
+        strike_type: test_env.mint_2.pubkey(),
+        strike_qty: 5,
+    };
     let (pda, _bump) = Pubkey::find_program_address(
-        &[&seed, &get_seed(&test_env.index_seed)],
+        &[&offer_data.get_seed(), &get_seed(&test_env.index_seed)],
         &test_env.program_key.pubkey(),
     );
-
-    let buyer_init_balance = get_token_balance(test_env, test_env.buyer.mint_1.clone()).await;
-    let seller_init_balance = get_token_balance(test_env, test_env.seller.mint_2.clone()).await;
-
     let accounts = vec![
-        AccountMeta {
-            pubkey: test_env.buyer.main.pubkey(),
-            is_signer: true,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: test_env.buyer.mint_2.clone(),
-            is_signer: false,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: test_env.buyer.mint_1.clone(),
-            is_signer: false,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: pda,
-            is_signer: false,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: test_env.seller.main.pubkey(),
-            is_signer: false,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: test_env.seller_temp.pubkey(),
-            is_signer: false,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: test_env.seller.mint_2.clone(),
-            is_signer: false,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: system_program::id(),
-            is_signer: false,
-            is_writable: false,
-        },
-        AccountMeta {
-            pubkey: spl_token::id(),
-            is_signer: false,
-            is_writable: false,
-        },
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new_readonly(wallet, false),
+        AccountMeta::new(pda, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(test_env.program_key.pubkey(), false),
+        AccountMeta::new_readonly(test_env.program_key.pubkey(), false),
+        AccountMeta::new_readonly(
+            instruction::config_address(&test_env.program_key.pubkey()),
+            false,
+        ),
     ];
+    let mut instruction_data = vec![0];
+    instruction_data.extend_from_slice(&offer_data.to_bytes());
+    instruction_data.extend_from_slice(&test_env.index_seed);
 
-    let mut instruction_data = [0; 42];
-    instruction_data[0] = 1;
-    instruction_data[1..].copy_from_slice(&test_env.index_seed);
-
-    println!("sending accept_offer transaction");
+    println!("sending create_offer transaction");
     let ix =
         Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
     let tx = Transaction::new_signed_with_payer(
         &[ix],
         Some(&test_env.ctx.payer.pubkey()),
-        &[&test_env.ctx.payer, &test_env.buyer.main],
+        &[&test_env.ctx.payer, &test_env.seller.main],
         test_env.ctx.last_blockhash,
     );
     test_env
@@ -459,146 +3515,131 @@ async fn accept_offer(test_env: &mut TestEnv, escrow_pda: EscrowPDA) {
         .await
         .unwrap();
 
-    test_env
+    let acc = test_env
         .ctx
         .banks_client
         .get_account(pda)
         .await
         .unwrap()
-        .ok_or("")
-        .expect_err("PDA account not closed");
-    println!("PDA account closed");
-
-    let buyer_balance = get_token_balance(test_env, test_env.buyer.mint_1.clone()).await;
-    let seller_balance = get_token_balance(test_env, test_env.seller.mint_2.clone()).await;
+        .unwrap();
+    let escrow = EscrowPDA::unpack_from_slice(&acc.data[..]).unwrap();
+    (pda, escrow.seller_receive)
+}
 
-    println!("asserting transfer states");
+async fn accept_with_atas(
+    test_env: &mut TestEnv,
+    pda: Pubkey,
+    seller_receive: Pubkey,
+    seller_wallet: Pubkey,
+) -> Result<(), BanksClientError> {
+    let accounts = vec![
+        AccountMeta::new(test_env.buyer.main.pubkey(), true),
+        AccountMeta::new(test_env.buyer.mint_2, false),
+        AccountMeta::new(test_env.buyer.mint_1, false),
+        AccountMeta::new(pda, false),
+        AccountMeta::new(test_env.seller.main.pubkey(), false),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(seller_receive, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(test_env.mint_1.pubkey(), false),
+        AccountMeta::new_readonly(test_env.mint_2.pubkey(), false),
+        AccountMeta::new_readonly(seller_wallet, false),
+        AccountMeta::new_readonly(
+            instruction::config_address(&test_env.program_key.pubkey()),
+            false,
+        ),
+    ];
 
-    assert_eq!(
-        buyer_balance - buyer_init_balance,
-        escrow_pda.offer_data.token_qty,
-        "incorrect token qty transferred to buyer"
-    );
-    assert_eq!(
-        seller_balance - seller_init_balance,
-        escrow_pda.offer_data.strike_qty,
-        "incorrect strike qty transferred to seller"
+    println!("sending accept_offer transaction");
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[1], accounts);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_env.ctx.payer.pubkey()),
+        &[&test_env.ctx.payer, &test_env.buyer.main],
+        test_env.ctx.last_blockhash,
     );
+    test_env.ctx.banks_client.process_transaction(tx).await
 }
 
-async fn cancel_offer(test_env: &mut TestEnv, escrow_pda: EscrowPDA, test_close: bool) {
-    let seed = escrow_pda.offer_data.get_seed();
+async fn get_lamports(test_env: &mut TestEnv, pk: Pubkey) -> u64 {
+    test_env.ctx.banks_client.get_balance(pk).await.unwrap()
+}
+
+async fn cancel_offer_with_rent_payer(
+    test_env: &mut TestEnv,
+    escrow_pda: &EscrowPDA,
+    rent_payer: Option<Pubkey>,
+) -> Result<(), BanksClientError> {
     let (pda, _bump) = Pubkey::find_program_address(
-        &[&seed, &get_seed(&test_env.index_seed)],
+        &[&escrow_pda.offer_data.get_seed(), &escrow_pda.index_seed],
         &test_env.program_key.pubkey(),
     );
-
-    let accounts = vec![
-        AccountMeta {
-            pubkey: test_env.seller.main.pubkey(),
-            is_signer: true,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: test_env.seller_temp.pubkey(),
-            is_signer: false,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: test_env.seller.mint_2.clone(),
-            is_signer: false,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: pda,
-            is_signer: false,
-            is_writable: true,
-        },
-        AccountMeta {
-            pubkey: system_program::id(),
-            is_signer: false,
-            is_writable: false,
-        },
-        AccountMeta {
-            pubkey: spl_token::id(),
-            is_signer: false,
-            is_writable: false,
-        },
+    let mut accounts = vec![
+        AccountMeta::new(test_env.seller.main.pubkey(), true),
+        AccountMeta::new(test_env.seller_temp.pubkey(), false),
+        AccountMeta::new(test_env.seller.mint_2, false),
+        AccountMeta::new(pda, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
     ];
-
-    let mut instruction_data = [0; 42];
-    instruction_data[0] = 2;
-    instruction_data[1..].copy_from_slice(&test_env.index_seed);
+    if let Some(rent_payer) = rent_payer {
+        accounts.push(AccountMeta::new_readonly(
+            test_env.program_key.pubkey(),
+            false,
+        ));
+        accounts.push(AccountMeta::new(rent_payer, false));
+    }
 
     println!("sending cancel_offer transaction");
-    let ix =
-        Instruction::new_with_bytes(test_env.program_key.pubkey(), &instruction_data, accounts);
+    let ix = Instruction::new_with_bytes(test_env.program_key.pubkey(), &[2], accounts);
     let tx = Transaction::new_signed_with_payer(
         &[ix],
         Some(&test_env.ctx.payer.pubkey()),
         &[&test_env.ctx.payer, &test_env.seller.main],
         test_env.ctx.last_blockhash,
     );
-    test_env
-        .ctx
-        .banks_client
-        .process_transaction(tx)
-        .await
-        .unwrap();
+    test_env.ctx.banks_client.process_transaction(tx).await
+}
 
-    test_env
-        .ctx
-        .banks_client
-        .get_account(pda)
-        .await
-        .unwrap()
-        .ok_or("")
-        .expect_err("PDA account not closed");
-    println!("PDA account closed");
+#[test]
+fn test_escrow_view() {
+    let escrow = EscrowPDA {
+        seller_main: Pubkey::new_unique(),
+        seller_temp: Pubkey::new_unique(),
+        seller_receive: Pubkey::new_unique(),
+        offer_data: OfferData {
+            token_type: Pubkey::new_unique(),
+            token_qty: 5,
+            strike_type: Pubkey::new_unique(),
+            strike_qty: 10,
+        },
+        index_seed: [7; 32],
+        rent_payer: Pubkey::new_unique(),
+        operator: Some(Pubkey::new_unique()),
+        bump: 254,
+    };
+    let mut data = vec![0; EscrowPDA::LEN];
+    escrow.pack_into_slice(&mut data);
+    let view = EscrowView::load(&data).unwrap();
+    assert_eq!(EscrowPDA::from(view), escrow);
+    assert_eq!(view.offer_data(), escrow.offer_data);
+    assert_eq!(view.operator(), escrow.operator);
 
-    if test_close {
-        println!("testing transfer from seller_temp");
-        let ix = spl_token::instruction::transfer(
-            &spl_token::id(),
-            &test_env.seller_temp.pubkey(),
-            &test_env.seller.mint_1.clone(),
-            &test_env.seller.main.pubkey(),
-            &[&test_env.seller.main.pubkey()],
-            5,
-        )
-        .unwrap();
-        let tx = Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&test_env.ctx.payer.pubkey()),
-            &[&test_env.ctx.payer, &test_env.seller.main],
-            test_env.ctx.last_blockhash,
-        );
-        test_env
-            .ctx
-            .banks_client
-            .process_transaction(tx)
-            .await
-            .unwrap();
-        println!("closing seller_temp");
-        let ix = spl_token::instruction::close_account(
-            &spl_token::id(),
-            &test_env.seller_temp.pubkey(),
-            &test_env.seller.main.pubkey(),
-            &test_env.seller.main.pubkey(),
-            &[&test_env.seller.main.pubkey()],
-        )
-        .unwrap();
-        let tx = Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&test_env.ctx.payer.pubkey()),
-            &[&test_env.ctx.payer, &test_env.seller.main],
-            test_env.ctx.last_blockhash,
-        );
-        test_env
-            .ctx
-            .banks_client
-            .process_transaction(tx)
-            .await
-            .unwrap();
-    }
+    // partial updates through the view match a full repack
+    let view = EscrowView::load_mut(&mut data).unwrap();
+    view.set_operator(None);
+    view.set_strike_qty(12);
+    let mut expected = escrow;
+    expected.operator = None;
+    expected.offer_data.strike_qty = 12;
+    let mut packed = vec![0; EscrowPDA::LEN];
+    expected.pack_into_slice(&mut packed);
+    assert_eq!(data, packed);
+    assert_eq!(EscrowPDA::unpack_from_slice(&data).unwrap(), expected);
+
+    assert!(EscrowView::load(&data[..EscrowPDA::V1_LEN]).is_err());
+    data[EscrowPDA::OPERATOR_OFFSET] = 2;
+    assert!(EscrowView::load(&data).is_err());
 }
@@ -0,0 +1,115 @@
+// property tests for the amount arithmetic. inputs come from a seeded rng with a random bit
+// width each, so runs are reproducible and the small, exact and near-overflow cases all show up
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sol_escrow::math::{self, MathError, Rounding};
+use solana_program::program_error::ProgramError;
+
+const CASES: usize = 100_000;
+
+fn amount(rng: &mut StdRng) -> u64 {
+    let bits = rng.gen_range(0..=64);
+    rng.gen::<u64>().checked_shr(64 - bits).unwrap_or(0)
+}
+
+#[test]
+fn test_mul_div_rounding() {
+    let mut rng = StdRng::seed_from_u64(48);
+    for _ in 0..CASES {
+        let (a, b, denominator) = (amount(&mut rng), amount(&mut rng), amount(&mut rng).max(1));
+        let numerator = a as u128 * b as u128;
+        let divisor = denominator as u128;
+        let floor = numerator / divisor;
+        let result = |rounding| math::mul_div(a, b, denominator, rounding);
+
+        if floor > u64::MAX as u128 {
+            for rounding in [Rounding::Down, Rounding::Up, Rounding::Nearest] {
+                assert_eq!(result(rounding), Err(ProgramError::ArithmeticOverflow));
+            }
+            continue;
+        }
+        let down = result(Rounding::Down).unwrap() as u128;
+        assert!(down * divisor <= numerator);
+        assert!(numerator - down * divisor < divisor);
+
+        let exact = down * divisor == numerator;
+        match (result(Rounding::Up), result(Rounding::Nearest)) {
+            (Ok(up), Ok(nearest)) => {
+                let (up, nearest) = (up as u128, nearest as u128);
+                assert_eq!(up, if exact { down } else { down + 1 });
+                assert!(down <= nearest && nearest <= up);
+                // nearest is never further from the exact quotient than the other choice
+                let distance = |q: u128| (q * divisor).abs_diff(numerator);
+                assert!(distance(nearest) <= distance(down) && distance(nearest) <= distance(up));
+            }
+            // rounding up past u64::MAX is only possible from a quotient of exactly u64::MAX
+            (up, nearest) => {
+                assert_eq!(down, u64::MAX as u128);
+                assert!(!exact);
+                assert_eq!(up, Err(ProgramError::ArithmeticOverflow));
+                assert!(nearest.is_err() || nearest == Ok(u64::MAX));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_mul_div_wide_overflow() {
+    let mut rng = StdRng::seed_from_u64(480);
+    for _ in 0..CASES {
+        let a = (amount(&mut rng) as u128) << rng.gen_range(0..64);
+        let b = (amount(&mut rng) as u128) << rng.gen_range(0..64);
+        let denominator = amount(&mut rng).max(1) as u128;
+        let result = math::mul_div_wide(a, b, denominator, Rounding::Down);
+        match a.checked_mul(b) {
+            Some(numerator) => assert_eq!(result, Ok(numerator / denominator)),
+            None => assert_eq!(result, Err(ProgramError::ArithmeticOverflow)),
+        }
+    }
+}
+
+#[test]
+fn test_math_edges() {
+    for rounding in [Rounding::Down, Rounding::Up, Rounding::Nearest] {
+        assert_eq!(
+            math::mul_div(1, 1, 0, rounding),
+            Err(MathError::DivideByZero.into())
+        );
+        assert_eq!(
+            math::mul_div_wide(0, 0, 0, rounding),
+            Err(ProgramError::Custom(0))
+        );
+        assert_eq!(
+            math::mul_div(u64::MAX, u64::MAX, u64::MAX, rounding),
+            Ok(u64::MAX)
+        );
+        assert_eq!(math::mul_div(0, u64::MAX, 1, rounding), Ok(0));
+    }
+    // half way rounds up
+    assert_eq!(math::mul_div(5, 1, 2, Rounding::Nearest), Ok(3));
+    assert_eq!(math::mul_div(7, 1, 3, Rounding::Nearest), Ok(2));
+    assert_eq!(math::mul_div(8, 1, 3, Rounding::Nearest), Ok(3));
+    assert_eq!(math::mul_div(7, 1, 3, Rounding::Up), Ok(3));
+
+    assert_eq!(math::pow10(38), Ok(10u128.pow(38)));
+    assert_eq!(math::pow10(39), Err(ProgramError::ArithmeticOverflow));
+    assert_eq!(
+        math::to_u64(u64::MAX as u128 + 1),
+        Err(ProgramError::ArithmeticOverflow)
+    );
+    assert_eq!(
+        math::checked_add(u64::MAX, 1),
+        Err(ProgramError::ArithmeticOverflow)
+    );
+    assert_eq!(
+        math::checked_sub(0, 1),
+        Err(ProgramError::ArithmeticOverflow)
+    );
+
+    assert_eq!(math::elapsed(-5, 5), Ok(10));
+    assert_eq!(
+        math::elapsed(i64::MIN, i64::MAX),
+        Err(ProgramError::ArithmeticOverflow)
+    );
+    assert_eq!(math::elapsed(1, 0), Err(ProgramError::ArithmeticOverflow));
+}
@@ -0,0 +1,396 @@
+#![cfg(all(feature = "test-bpf", feature = "client"))]
+
+// the simulator runs the processor's checks outside of a transaction, where program-test's
+// syscall stubs have no invoke context to log to. the stubs are process-wide, so this file
+// holds a single test and swaps them out only around each simulation
+
+use borsh::BorshSerialize;
+use sol_escrow::{
+    entrypoint::process_instruction,
+    instruction::{self, InstructionType},
+    simulator::{
+        simulate_accept, simulate_cancel, AcceptState, BalanceChange, CancelState, SimAccount,
+    },
+    state::{ConfigPDA, EscrowPDA, OfferData},
+};
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account as SolanaAccount,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signer::{keypair::Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token::state::{Account, AccountState, Mint};
+
+struct OffChain;
+impl SyscallStubs for OffChain {}
+
+fn off_chain<T>(simulate: impl FnOnce() -> T) -> T {
+    let stubs = set_syscall_stubs(Box::new(OffChain));
+    let result = simulate();
+    set_syscall_stubs(stubs);
+    result
+}
+
+struct SimEnv {
+    ctx: ProgramTestContext,
+    program_id: Pubkey,
+    mint_authority: Keypair,
+    seller: Keypair,
+    buyer: Keypair,
+    mint_1: Pubkey,
+    mint_2: Pubkey,
+}
+
+#[tokio::test]
+async fn test_simulator_parity() {
+    let mut env = init_env().await;
+    let (program_id, buyer, mint_1, mint_2) =
+        (env.program_id, env.buyer.pubkey(), env.mint_1, env.mint_2);
+    let seller_receive = add_token_account(&mut env, mint_2, true, 0).await;
+    let buyer_strike = add_token_account(&mut env, mint_2, false, 20).await;
+    let buyer_receive = add_token_account(&mut env, mint_1, false, 0).await;
+    let wrong_strike = add_token_account(&mut env, mint_1, false, 20).await;
+
+    let (pda, escrow) = open_offer(&mut env, seller_receive, 0).await;
+    let accept = |strike_acc| {
+        instruction::accept(
+            &program_id,
+            &buyer,
+            &strike_acc,
+            &buyer_receive,
+            &pda,
+            &escrow,
+        )
+    };
+
+    let mut worse = escrow.offer_data;
+    worse.strike_qty -= 1;
+    let mut ix = accept(buyer_strike);
+    ix.data = InstructionType::ACCEPT {
+        expected: Some(worse),
+    }
+    .try_to_vec()
+    .unwrap();
+    let err = assert_accept_parity(&mut env, ix, buyer_strike, Some(worse)).await;
+    assert_eq!(err, Some(ProgramError::InvalidArgument));
+
+    let ix = accept(wrong_strike);
+    let err = assert_accept_parity(&mut env, ix, wrong_strike, Some(escrow.offer_data)).await;
+    assert_eq!(err, Some(ProgramError::InvalidArgument));
+
+    let ix = accept(buyer_strike);
+    let err = assert_accept_parity(&mut env, ix, buyer_strike, Some(escrow.offer_data)).await;
+    assert_eq!(err, None);
+
+    // a temp holding more than token_qty cannot be closed once the offer is paid out
+    let (pda, escrow) = open_offer(&mut env, seller_receive, 1).await;
+    mint_to(&mut env, mint_1, escrow.seller_temp, 1).await;
+    let ix = instruction::accept(
+        &program_id,
+        &buyer,
+        &buyer_strike,
+        &buyer_receive,
+        &pda,
+        &escrow,
+    );
+    let err = assert_accept_parity(&mut env, ix, buyer_strike, Some(escrow.offer_data)).await;
+    assert_eq!(
+        err,
+        Some(spl_token::error::TokenError::NonNativeHasBalance.into())
+    );
+
+    let operator = Keypair::new();
+    let mut ix = instruction::cancel(&program_id, &pda, &escrow);
+    ix.accounts[0].is_signer = false;
+    ix.accounts
+        .push(AccountMeta::new_readonly(operator.pubkey(), true));
+    let state = cancel_state(&mut env, pda, &escrow, Some(&operator)).await;
+    let predicted = off_chain(|| simulate_cancel(&program_id, &state));
+    let result = send(&mut env, ix, &operator).await;
+    assert_eq!(result, predicted.as_ref().map(|_| ()).map_err(error_code));
+    assert_eq!(
+        predicted.unwrap_err(),
+        ProgramError::MissingRequiredSignature
+    );
+
+    let ix = instruction::cancel(&program_id, &pda, &escrow);
+    let state = cancel_state(&mut env, pda, &escrow, None).await;
+    let predicted = off_chain(|| simulate_cancel(&program_id, &state)).unwrap();
+    let seller = Keypair::from_bytes(&env.seller.to_bytes()).unwrap();
+    send(&mut env, ix, &seller).await.unwrap();
+    assert_balance_changes(&mut env, &[state.seller, state.escrow_pda], &predicted).await;
+}
+
+// simulates ix against the current bank state, sends it, and checks both agree
+async fn assert_accept_parity(
+    env: &mut SimEnv,
+    ix: Instruction,
+    strike_acc: Pubkey,
+    expected: Option<OfferData>,
+) -> Option<ProgramError> {
+    let key = |index: usize| ix.accounts[index].pubkey;
+    let state = AcceptState {
+        buyer: sim_account(env, key(0)).await,
+        strike_acc: sim_account(env, strike_acc).await,
+        buyer_receive: sim_account(env, key(2)).await,
+        escrow_pda: sim_account(env, key(3)).await,
+        seller: sim_account(env, key(4)).await,
+        seller_temp: sim_account(env, key(5)).await,
+        seller_receive: sim_account(env, key(6)).await,
+        config: sim_account(env, key(10)).await,
+        rent_payer: None,
+    };
+    let predicted = off_chain(|| simulate_accept(&env.program_id, &state, expected));
+    let buyer = Keypair::from_bytes(&env.buyer.to_bytes()).unwrap();
+    let result = send(env, ix, &buyer).await;
+    match predicted {
+        Err(err) => {
+            assert_eq!(result, Err(error_code(&err)));
+            Some(err)
+        }
+        Ok(changes) => {
+            result.unwrap();
+            let before = [
+                state.strike_acc,
+                state.buyer_receive,
+                state.escrow_pda,
+                state.seller,
+                state.seller_temp,
+                state.seller_receive,
+            ];
+            assert_balance_changes(env, &before, &changes).await;
+            None
+        }
+    }
+}
+
+async fn cancel_state(
+    env: &mut SimEnv,
+    pda: Pubkey,
+    escrow: &EscrowPDA,
+    operator: Option<&Keypair>,
+) -> CancelState {
+    let operator = match operator {
+        Some(operator) => Some(sim_account(env, operator.pubkey()).await),
+        None => None,
+    };
+    CancelState {
+        seller: sim_account(env, escrow.seller_main).await,
+        seller_temp: sim_account(env, escrow.seller_temp).await,
+        seller_receive: sim_account(env, escrow.seller_receive).await,
+        escrow_pda: sim_account(env, pda).await,
+        operator,
+        rent_payer: None,
+    }
+}
+
+async fn assert_balance_changes(
+    env: &mut SimEnv,
+    before: &[SimAccount],
+    changes: &[BalanceChange],
+) {
+    assert!(!changes.is_empty());
+    for change in changes {
+        let (key, delta) = match change {
+            BalanceChange::Token { account, delta, .. } => (account, delta),
+            BalanceChange::Lamports { account, delta } => (account, delta),
+        };
+        let old = before.iter().find(|account| account.key == *key).unwrap();
+        let new = sim_account(env, *key).await;
+        let (old, new) = match change {
+            BalanceChange::Token { .. } => (token_amount(old), token_amount(&new)),
+            BalanceChange::Lamports { .. } => (old.lamports as i128, new.lamports as i128),
+        };
+        assert_eq!(new - old, *delta, "balance change differs for {}", key);
+    }
+}
+
+fn token_amount(account: &SimAccount) -> i128 {
+    match account.data.len() {
+        0 => 0,
+        _ => Account::unpack_from_slice(&account.data).unwrap().amount as i128,
+    }
+}
+
+fn error_code(err: &ProgramError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::from(u64::from(err.clone())))
+}
+
+async fn sim_account(env: &mut SimEnv, key: Pubkey) -> SimAccount {
+    match env.ctx.banks_client.get_account(key).await.unwrap() {
+        Some(account) => SimAccount {
+            key,
+            lamports: account.lamports,
+            data: account.data,
+            owner: account.owner,
+        },
+        None => SimAccount::wallet(key, 0),
+    }
+}
+
+async fn send(env: &mut SimEnv, ix: Instruction, signer: &Keypair) -> Result<(), TransactionError> {
+    let blockhash = env
+        .ctx
+        .banks_client
+        .get_new_latest_blockhash(&env.ctx.last_blockhash)
+        .await
+        .unwrap();
+    env.ctx.last_blockhash = blockhash;
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&env.ctx.payer.pubkey()),
+        &[&env.ctx.payer, signer],
+        blockhash,
+    );
+    env.ctx
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+async fn open_offer(
+    env: &mut SimEnv,
+    seller_receive: Pubkey,
+    contract_no: u64,
+) -> (Pubkey, EscrowPDA) {
+    let seller_temp = add_token_account(env, env.mint_1, true, 5).await;
+    let offer_data = OfferData {
+        token_type: env.mint_1,
+        token_qty: 5,
+        strike_type: env.mint_2,
+        strike_qty: 10,
+    };
+    let index_seed = instruction::index_seed(&env.seller.pubkey(), 0, contract_no);
+    let ix = instruction::offer(
+        &env.program_id,
+        &env.seller.pubkey(),
+        &seller_temp,
+        &seller_receive,
+        &offer_data,
+        &index_seed,
+    );
+    let seller = Keypair::from_bytes(&env.seller.to_bytes()).unwrap();
+    send(env, ix, &seller).await.unwrap();
+
+    let pda = instruction::escrow_address(&env.program_id, &offer_data, &index_seed);
+    let account = env
+        .ctx
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    (pda, EscrowPDA::unpack_from_slice(&account.data).unwrap())
+}
+
+async fn mint_to(env: &mut SimEnv, mint: Pubkey, account: Pubkey, amount: u64) {
+    let ix = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        &mint,
+        &account,
+        &env.mint_authority.pubkey(),
+        &[],
+        amount,
+    )
+    .unwrap();
+    let authority = Keypair::from_bytes(&env.mint_authority.to_bytes()).unwrap();
+    send(env, ix, &authority).await.unwrap();
+}
+
+// accounts are written straight into the bank, leaving the program's own instructions
+// as the only transactions under test
+async fn add_token_account(env: &mut SimEnv, mint: Pubkey, seller: bool, amount: u64) -> Pubkey {
+    let key = Pubkey::new_unique();
+    let owner = match seller {
+        true => env.seller.pubkey(),
+        false => env.buyer.pubkey(),
+    };
+    let state = Account {
+        mint,
+        owner,
+        amount,
+        state: AccountState::Initialized,
+        ..Account::default()
+    };
+    let mut data = vec![0; Account::LEN];
+    state.pack_into_slice(&mut data);
+    env.ctx
+        .set_account(&key, &packed_account(data, spl_token::id()).into());
+
+    let mut mint_account = env
+        .ctx
+        .banks_client
+        .get_account(mint)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut mint_state = Mint::unpack_from_slice(&mint_account.data).unwrap();
+    mint_state.supply += amount;
+    mint_state.pack_into_slice(&mut mint_account.data);
+    env.ctx.set_account(&mint, &mint_account.into());
+    key
+}
+
+fn packed_account(data: Vec<u8>, owner: Pubkey) -> SolanaAccount {
+    SolanaAccount {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        ..SolanaAccount::default()
+    }
+}
+
+async fn init_env() -> SimEnv {
+    let program_id = Pubkey::new_unique();
+    let mut program_test =
+        ProgramTest::new("sol_escrow", program_id, processor!(process_instruction));
+    let (mint_authority, seller, buyer) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let (mint_1, mint_2) = (Pubkey::new_unique(), Pubkey::new_unique());
+    for mint in [mint_1, mint_2] {
+        let state = Mint {
+            mint_authority: COption::Some(mint_authority.pubkey()),
+            decimals: 1,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        let mut data = vec![0; Mint::LEN];
+        state.pack_into_slice(&mut data);
+        program_test.add_account(mint, packed_account(data, spl_token::id()));
+    }
+    for wallet in [&seller, &buyer] {
+        program_test.add_account(
+            wallet.pubkey(),
+            SolanaAccount::new(100_000_000, 0, &solana_sdk::system_program::id()),
+        );
+    }
+    // offers need an initialised config, which is only read here
+    let (config, bump) = Pubkey::find_program_address(&[ConfigPDA::SEED], &program_id);
+    let config_data = ConfigPDA {
+        admin: Pubkey::new_unique(),
+        paused: false,
+        allow_list: false,
+        bump,
+        pending_admin: Pubkey::default(),
+    };
+    let mut data = vec![0; ConfigPDA::LEN];
+    config_data.pack_into_slice(&mut data);
+    program_test.add_account(config, packed_account(data, program_id));
+
+    SimEnv {
+        ctx: program_test.start_with_context().await,
+        program_id,
+        mint_authority,
+        seller,
+        buyer,
+        mint_1,
+        mint_2,
+    }
+}